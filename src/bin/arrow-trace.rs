@@ -0,0 +1,103 @@
+// Copyright 2020 Angelcam, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Arrow message trace decoder. The tool pretty-prints message traces
+//! recorded by the Arrow Client (see the --trace-file option).
+
+use std::env;
+use std::io;
+use std::process;
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use arrow_client::config::FromJson;
+use arrow_client::json;
+use arrow_client::net::arrow::TraceRecord;
+
+/// Print usage and exit the process with a given exit code.
+fn usage(exit_code: i32) -> ! {
+    println!("USAGE: arrow-trace [OPTIONS] [FILE]...\n");
+    println!("    FILE      trace file recorded by the Arrow Client (the standard input");
+    println!("              is used if no file is given)\n");
+    println!("OPTIONS:\n");
+    println!("    --control  print only Control Protocol messages");
+    println!("    --help     print this help");
+
+    process::exit(exit_code)
+}
+
+/// Print all records from a given trace.
+fn print_trace<R>(name: &str, trace: R, control_only: bool) -> io::Result<()>
+where
+    R: BufRead,
+{
+    for (i, line) in trace.lines().enumerate() {
+        let line = line?;
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let record = json::parse(&line)
+            .map_err(|err| err.to_string())
+            .and_then(|value| TraceRecord::from_json(value).map_err(|err| err.to_string()));
+
+        match record {
+            Ok(record) => {
+                if !control_only || record.service_id == 0 {
+                    println!("{}", record);
+                }
+            }
+            Err(err) => eprintln!("{}:{}: malformed trace record ({})", name, i + 1, err),
+        }
+    }
+
+    Ok(())
+}
+
+/// Arrow trace decoder main function.
+fn main() {
+    let mut control_only = false;
+    let mut files = Vec::new();
+
+    for arg in env::args().skip(1) {
+        match &arg as &str {
+            "--control" => control_only = true,
+            "--help" => usage(0),
+            arg if arg.starts_with("--") => {
+                println!("ERROR: unknown argument: \"{}\"\n", arg);
+                usage(1);
+            }
+            _ => files.push(arg),
+        }
+    }
+
+    let res = if files.is_empty() {
+        let stdin = io::stdin();
+
+        print_trace("stdin", stdin.lock(), control_only)
+    } else {
+        files.iter().try_for_each(|name| {
+            let file = File::open(name)?;
+
+            print_trace(name, BufReader::new(file), control_only)
+        })
+    };
+
+    if let Err(err) = res {
+        eprintln!("ERROR: {}", err);
+        process::exit(1);
+    }
+}
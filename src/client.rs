@@ -14,10 +14,15 @@
 
 use std::process;
 
+use std::collections::HashMap;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-use futures::future::{AbortHandle, Future, FutureExt};
+use futures::channel::mpsc;
+use futures::channel::mpsc::{UnboundedReceiver, UnboundedSender};
+use futures::channel::oneshot;
+use futures::future::{AbortHandle, Either, Future, FutureExt};
 use futures::stream::StreamExt;
 use futures::task::{Context, Poll};
 
@@ -27,58 +32,458 @@ use crate::cmd_handler;
 use crate::net::arrow;
 
 use crate::cmd_handler::{Command, CommandChannel};
-use crate::config::Config;
-use crate::context::{ApplicationContext, ConnectionState};
-use crate::net::arrow::{ArrowError, ErrorKind};
+use crate::config::{Config, ConfigError};
+use crate::context::{ApplicationContext, ConnectionState, SuspendReason};
+use crate::net::arrow::{ArrowError, ConnectionStats, DefaultTransport, ErrorKind, SessionInfo};
+use crate::net::arrow::{
+    DEFAULT_ARROW_PROTOCOL_VERSION, MAX_ARROW_PROTOCOL_VERSION, MIN_ARROW_PROTOCOL_VERSION,
+};
+
+pub use crate::net::arrow::{ArrowStream, ArrowTransport, ArrowTransportFuture};
 use crate::net::raw::ether::MacAddr;
 use crate::svc_table::Service;
 use crate::utils::logger::{BoxLogger, Logger};
 use crate::ArrowClientEventListener;
 
-/// Connection retry timeout.
-const RETRY_TIMEOUT: Duration = Duration::from_secs(60);
+/// Policy for reconnecting to the Arrow Service after a connection failure.
+///
+/// The delay between two consecutive connection attempts starts at the
+/// initial delay and it's doubled after every failure until it reaches the
+/// maximum delay. A random part of the delay (given by the jitter factor) is
+/// subtracted in order to spread reconnects of many clients in time. The
+/// backoff is reset once a connection stays up for a given period of time.
+#[derive(Debug, Copy, Clone)]
+pub struct ReconnectPolicy {
+    initial_delay: Duration,
+    max_delay: Duration,
+    jitter: f64,
+    reset_after: Duration,
+}
+
+impl ReconnectPolicy {
+    /// Create a new reconnect policy with default parameters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set delay before the first reconnect attempt.
+    pub fn initial_delay(&mut self, delay: Duration) -> &mut Self {
+        self.initial_delay = delay;
+        self
+    }
+
+    /// Set the maximum delay between two connection attempts.
+    pub fn max_delay(&mut self, delay: Duration) -> &mut Self {
+        self.max_delay = delay;
+        self
+    }
+
+    /// Set the jitter factor. It's the maximum fraction of the delay that
+    /// can be randomly subtracted. The value is clamped into the `0..=1`
+    /// range. NaN is ignored (i.e. the current value is kept).
+    pub fn jitter(&mut self, jitter: f64) -> &mut Self {
+        if !jitter.is_nan() {
+            // f64::clamp() is not available in older Rust versions
+            #[allow(clippy::manual_clamp)]
+            let jitter = jitter.max(0.0).min(1.0);
+
+            self.jitter = jitter;
+        }
+
+        self
+    }
+
+    /// Set the minimum duration of a connection that is considered stable.
+    /// The backoff will be reset after such connection.
+    pub fn reset_after(&mut self, period: Duration) -> &mut Self {
+        self.reset_after = period;
+        self
+    }
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_secs(5),
+            max_delay: Duration::from_secs(60),
+            jitter: 0.5,
+            reset_after: Duration::from_secs(300),
+        }
+    }
+}
+
+/// Maximum length of the pairing window.
+const MAX_PAIRING_WINDOW: Duration = Duration::from_secs(100 * 365 * 24 * 3600);
+
+/// Policy for reconnecting to the Arrow Service while the client waits for
+/// pairing (i.e. while the Arrow Service rejects the client as unauthorized).
+///
+/// The pairing window starts when the client starts or when it enters the
+/// pairing mode again. The client retries using the fast retry interval in
+/// the first phase of the window and using the slow retry interval for the
+/// rest of the window. Once the window ends, the client is either suspended
+/// or it keeps retrying using a given interval.
+#[derive(Debug, Copy, Clone)]
+pub struct PairingPolicy {
+    window: Duration,
+    fast_phase: Duration,
+    fast_retry_interval: Duration,
+    slow_retry_interval: Duration,
+    retry_after_window: Option<Duration>,
+}
+
+impl PairingPolicy {
+    /// Create a new pairing policy with default parameters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set length of the pairing window.
+    pub fn window(&mut self, window: Duration) -> &mut Self {
+        self.window = window;
+        self
+    }
+
+    /// Set length of the first phase of the pairing window (i.e. the phase
+    /// using the fast retry interval).
+    pub fn fast_phase(&mut self, phase: Duration) -> &mut Self {
+        self.fast_phase = phase;
+        self
+    }
+
+    /// Set retry interval for the first phase of the pairing window.
+    pub fn fast_retry_interval(&mut self, interval: Duration) -> &mut Self {
+        self.fast_retry_interval = interval;
+        self
+    }
+
+    /// Set retry interval for the rest of the pairing window.
+    pub fn slow_retry_interval(&mut self, interval: Duration) -> &mut Self {
+        self.slow_retry_interval = interval;
+        self
+    }
+
+    /// Set retry interval after the pairing window ends. `None` means that
+    /// the client will be suspended. Note that zero retry intervals are not
+    /// allowed.
+    pub fn retry_after_window(&mut self, interval: Option<Duration>) -> &mut Self {
+        self.retry_after_window = interval;
+        self
+    }
+
+    /// Check the policy parameters. Zero retry intervals and pairing windows
+    /// longer than `MAX_PAIRING_WINDOW` are rejected.
+    pub(crate) fn validate(&self) -> Result<(), ConfigError> {
+        let zero = Duration::from_secs(0);
+
+        if self.fast_retry_interval == zero
+            || self.slow_retry_interval == zero
+            || self.retry_after_window == Some(zero)
+        {
+            return Err(ConfigError::new(
+                "pairing policy retry intervals must not be zero",
+            ));
+        }
+
+        if self.window > MAX_PAIRING_WINDOW || Instant::now().checked_add(self.window).is_none() {
+            return Err(ConfigError::new("pairing window is too long"));
+        }
+
+        Ok(())
+    }
+
+    /// Get the retry interval for a given pairing window deadline. `None`
+    /// means that the client should be suspended.
+    fn retry_interval(&self, now: Instant, deadline: Instant) -> Option<Duration> {
+        let slow_phase = self.window.checked_sub(self.fast_phase).unwrap_or_default();
+
+        if (now + slow_phase) < deadline {
+            Some(self.fast_retry_interval)
+        } else if now < deadline {
+            Some(self.slow_retry_interval)
+        } else {
+            self.retry_after_window
+        }
+    }
+}
+
+impl Default for PairingPolicy {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(1200),
+            fast_phase: Duration::from_secs(600),
+            fast_retry_interval: Duration::from_secs(10),
+            slow_retry_interval: Duration::from_secs(30),
+            retry_after_window: None,
+        }
+    }
+}
+
+/// Exponential backoff state.
+struct ReconnectBackoff {
+    policy: ReconnectPolicy,
+    failures: u32,
+}
+
+impl ReconnectBackoff {
+    /// Create a new backoff for a given reconnect policy.
+    fn new(policy: ReconnectPolicy) -> Self {
+        Self {
+            policy,
+            failures: 0,
+        }
+    }
+
+    /// Reset the backoff.
+    fn reset(&mut self) {
+        self.failures = 0;
+    }
+
+    /// Reset the backoff if a connection started at a given time stayed up
+    /// for long enough.
+    fn connection_closed(&mut self, started: Instant) {
+        if started.elapsed() >= self.policy.reset_after {
+            self.reset();
+        }
+    }
+
+    /// Get the delay before the next connection attempt (without jitter) and
+    /// increment the failure counter.
+    fn next_base_delay(&mut self) -> Duration {
+        // 2^16 is more than enough for any reasonable delay
+        let multiplier = 1u32 << self.failures.min(16);
+
+        self.failures = self.failures.saturating_add(1);
+
+        self.policy
+            .initial_delay
+            .checked_mul(multiplier)
+            .unwrap_or(self.policy.max_delay)
+            .min(self.policy.max_delay)
+    }
+
+    /// Get the delay before the next connection attempt and increment the
+    /// failure counter.
+    fn next_delay(&mut self) -> Duration {
+        let delay = self.next_base_delay();
+
+        let jitter = delay.mul_f64(self.policy.jitter * random_fraction());
+
+        delay - jitter
+    }
+}
+
+/// Get a random number from the `0..1` range.
+fn random_fraction() -> f64 {
+    // NOTE: we already have a decent source of randomness in the UUID crate,
+    // the first four bytes of a v4 UUID are random
+    let uuid = Uuid::new_v4();
+    let bytes = uuid.as_bytes();
+
+    let n = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+
+    f64::from(n) / (f64::from(u32::MAX) + 1.0)
+}
+
+/// Arrow Service endpoint.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ArrowServiceEndpoint {
+    address: String,
+    weight: u32,
+}
+
+impl ArrowServiceEndpoint {
+    /// Create a new Arrow Service endpoint. The weight is the number of
+    /// consecutive failed connection attempts after which the client fails
+    /// over to the next endpoint (zero is treated as one).
+    pub fn new<T>(address: T, weight: u32) -> Self
+    where
+        T: ToString,
+    {
+        Self {
+            address: address.to_string(),
+            weight: weight.max(1),
+        }
+    }
+
+    /// Get address of the endpoint.
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Get weight of the endpoint.
+    pub fn weight(&self) -> u32 {
+        self.weight
+    }
+}
+
+/// Ordered list of Arrow Service endpoints.
+struct EndpointList {
+    endpoints: Vec<ArrowServiceEndpoint>,
+    current: usize,
+    failures: u32,
+}
+
+impl EndpointList {
+    /// Create a new list of endpoints.
+    fn new(endpoints: Vec<ArrowServiceEndpoint>) -> Self {
+        assert!(!endpoints.is_empty());
+
+        Self {
+            endpoints,
+            current: 0,
+            failures: 0,
+        }
+    }
+
+    /// Get the current endpoint.
+    fn current(&self) -> &ArrowServiceEndpoint {
+        &self.endpoints[self.current]
+    }
+
+    /// Mark the current endpoint as working.
+    fn success(&mut self) {
+        self.failures = 0;
+    }
 
-/// Get maximum duration of the pairing mode.
-const PAIRING_MODE_TIMEOUT: Duration = Duration::from_secs(1200);
+    /// Report a failed connection attempt to the current endpoint. The method
+    /// returns `true` if the client should fail over to another endpoint.
+    fn failure(&mut self) -> bool {
+        self.failures += 1;
+
+        if self.failures < self.current().weight() {
+            return false;
+        }
+
+        self.failures = 0;
+        self.current = (self.current + 1) % self.endpoints.len();
+
+        self.endpoints.len() > 1
+    }
+}
+
+/// Version of the Arrow protocol negotiated with a particular Arrow Service.
+#[derive(Copy, Clone)]
+struct ProtocolVersion {
+    current: u8,
+    max: u8,
+}
+
+impl Default for ProtocolVersion {
+    fn default() -> Self {
+        Self {
+            current: DEFAULT_ARROW_PROTOCOL_VERSION,
+            max: MAX_ARROW_PROTOCOL_VERSION,
+        }
+    }
+}
+
+/// Commands that can be sent to the Arrow client main task.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum MainTaskCommand {
+    /// Reset the pairing window and resume the client if it is suspended.
+    EnterPairingMode,
+    /// Resume the client if it is suspended. The pairing window and the
+    /// reconnect backoff are reset.
+    Resume,
+}
+
+/// A channel for sending commands to the Arrow client main task. The channel
+/// is cloneable and every copy of it will send commands to the same task.
+#[derive(Clone)]
+pub struct MainTaskChannel {
+    tx: UnboundedSender<MainTaskCommand>,
+}
+
+impl MainTaskChannel {
+    /// Create a new main task channel.
+    fn new(tx: UnboundedSender<MainTaskCommand>) -> Self {
+        Self { tx }
+    }
+
+    /// Send a given command.
+    pub fn send(&self, cmd: MainTaskCommand) {
+        // the main task may be already gone, there is nothing to do in such
+        // case
+        let _ = self.tx.unbounded_send(cmd);
+    }
+}
 
 /// This future ensures maintaining connection with a remote Arrow Service.
 struct ArrowMainTask {
     app_context: ApplicationContext,
     cmd_channel: CommandChannel,
     logger: BoxLogger,
-    default_addr: String,
+    transport: Arc<dyn ArrowTransport>,
+    endpoints: EndpointList,
     current_addr: String,
+    protocol_versions: HashMap<String, ProtocolVersion>,
     last_attempt: Instant,
     pairing_mode_timeout: Instant,
+    pairing_policy: PairingPolicy,
+    backoff: ReconnectBackoff,
     diagnostic_mode: bool,
+    commands: UnboundedReceiver<MainTaskCommand>,
 }
 
 impl ArrowMainTask {
+    /// Create a new task and run it.
+    async fn start(
+        app_context: ApplicationContext,
+        cmd_channel: CommandChannel,
+        transport: Arc<dyn ArrowTransport>,
+        commands: UnboundedReceiver<MainTaskCommand>,
+    ) {
+        let mut task = Self::new(app_context, cmd_channel, transport, commands);
+
+        while !task.app_context.is_shutting_down() {
+            let connection_result = task.connect().await;
+
+            // the connection was closed because of the shutdown
+            if task.app_context.is_shutting_down() {
+                break;
+            }
+
+            task.process_result(connection_result).await;
+        }
+
+        task.app_context.set_active_arrow_service(None);
+        task.app_context
+            .set_connection_state(ConnectionState::Disconnected(None));
+    }
+
     /// Create a new task.
-    async fn start(app_context: ApplicationContext, cmd_channel: CommandChannel) {
+    fn new(
+        app_context: ApplicationContext,
+        cmd_channel: CommandChannel,
+        transport: Arc<dyn ArrowTransport>,
+        commands: UnboundedReceiver<MainTaskCommand>,
+    ) -> Self {
         let logger = app_context.get_logger();
-        let addr = app_context.get_arrow_service_address();
+        let endpoints = EndpointList::new(app_context.get_arrow_service_endpoints());
+        let addr = endpoints.current().address().to_string();
         let diagnostic_mode = app_context.get_diagnostic_mode();
+        let reconnect_policy = app_context.get_reconnect_policy();
+        let pairing_policy = app_context.get_pairing_policy();
 
         let now = Instant::now();
 
-        let pairing_mode_timeout = now + PAIRING_MODE_TIMEOUT;
+        let pairing_mode_timeout = now + pairing_policy.window;
 
-        let mut task = ArrowMainTask {
+        Self {
             app_context,
             cmd_channel,
             logger,
-            default_addr: addr.clone(),
+            transport,
+            endpoints,
             current_addr: addr,
+            protocol_versions: HashMap::new(),
             last_attempt: now,
             pairing_mode_timeout,
+            pairing_policy,
+            backoff: ReconnectBackoff::new(reconnect_policy),
             diagnostic_mode,
-        };
-
-        loop {
-            let connection_result = task.connect().await;
-
-            task.process_result(connection_result).await;
+            commands,
         }
     }
 
@@ -92,24 +497,102 @@ impl ArrowMainTask {
 
         self.last_attempt = Instant::now();
 
+        self.app_context.connection_attempt();
         self.app_context
-            .set_connection_state(ConnectionState::Connected);
+            .set_connection_state(ConnectionState::Connecting);
+
+        let mut protocol_version = self.get_protocol_version();
 
-        arrow::connect(
+        let mut version = protocol_version.current;
+
+        let res = arrow::connect(
             self.app_context.clone(),
             self.cmd_channel.clone(),
+            self.transport.as_ref(),
             &self.current_addr,
+            &mut version,
         )
-        .await
+        .await;
+
+        // use a newer version of the Arrow protocol next time if the remote
+        // service announced it
+        let version = version.min(protocol_version.max);
+
+        if version > protocol_version.current {
+            log_info!(
+                &mut self.logger,
+                "the remote service {} supports Arrow Protocol v{}",
+                self.current_addr,
+                version
+            );
+
+            protocol_version.current = version;
+
+            self.protocol_versions
+                .insert(self.current_addr.clone(), protocol_version);
+        }
+
+        res
+    }
+
+    /// Get version of the Arrow protocol to be used for the current Arrow
+    /// Service.
+    fn get_protocol_version(&self) -> ProtocolVersion {
+        self.protocol_versions
+            .get(&self.current_addr)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Switch to the previous version of the Arrow protocol for the current
+    /// Arrow Service. The method should be called only if the Arrow Service
+    /// explicitly rejected the current version. It returns `false` if there
+    /// is no older version to try.
+    fn step_down_protocol_version(&mut self) -> bool {
+        let mut protocol_version = self.get_protocol_version();
+
+        if protocol_version.current <= MIN_ARROW_PROTOCOL_VERSION {
+            return false;
+        }
+
+        // never try the unsupported version again
+        protocol_version.current -= 1;
+        protocol_version.max = protocol_version.current;
+
+        self.protocol_versions
+            .insert(self.current_addr.clone(), protocol_version);
+
+        log_info!(
+            &mut self.logger,
+            "Arrow Protocol v{} is not supported by the remote service {}, falling back to v{}",
+            protocol_version.current + 1,
+            self.current_addr,
+            protocol_version.current
+        );
+
+        true
     }
 
     /// Process a given connection result.
     async fn process_result(&mut self, res: Result<String, ArrowError>) {
+        // check if the connection has been established and clear the active
+        // service
+        let connected = self.app_context.get_active_arrow_service().is_some();
+
+        self.app_context.set_active_arrow_service(None);
+
         if self.diagnostic_mode {
             diagnose_connection_result(&res);
         } else if let Ok(addr) = res {
+            self.app_context
+                .set_connection_state(ConnectionState::Redirecting);
+
             // set redirection
             self.current_addr = addr;
+
+            self.backoff.reset();
+
+            self.app_context.set_backoff_state(0, None);
         } else if let Err(err) = res {
             let cstate = if err.kind() == ErrorKind::Unauthorized {
                 log_info!(
@@ -122,22 +605,137 @@ impl ArrowMainTask {
             } else {
                 log_warn!(&mut self.logger, "{}", err);
 
-                ConnectionState::Disconnected
+                ConnectionState::Disconnected(Some(err.kind()))
             };
 
             self.app_context.set_connection_state(cstate);
 
-            let retry = process_connection_error(err, self.last_attempt, self.pairing_mode_timeout);
+            // start again with the initial delay if the connection was
+            // stable for long enough
+            self.backoff.connection_closed(self.last_attempt);
+
+            let kind = err.kind();
 
-            self.current_addr = self.default_addr.clone();
+            // the pairing window might have been reset in the meantime
+            self.process_pending_commands();
 
-            let fut = wait_for_retry(&mut self.logger, retry);
+            // try an older version of the Arrow protocol immediately if the
+            // current one is not supported by the remote service
+            let retry = if kind == ErrorKind::UnsupportedProtocolVersion
+                && self.step_down_protocol_version()
+            {
+                ConnectionRetry::Timeout(Duration::from_secs(0))
+            } else {
+                process_connection_error(
+                    err,
+                    self.pairing_mode_timeout,
+                    &self.pairing_policy,
+                    &mut self.backoff,
+                )
+            };
+
+            let retry_delay = match retry {
+                ConnectionRetry::Timeout(t) if t > Duration::from_secs(0) => Some(t),
+                _ => None,
+            };
+
+            self.app_context
+                .set_backoff_state(self.backoff.failures, retry_delay);
+
+            if let ConnectionRetry::Suspend(reason) = retry {
+                self.app_context
+                    .set_connection_state(ConnectionState::Suspended(reason));
+            }
 
-            fut.await;
+            if connected {
+                // the current endpoint works, we'll try it first again
+                self.endpoints.success();
+            } else if kind != ErrorKind::Unauthorized
+                && kind != ErrorKind::UnsupportedProtocolVersion
+                && self.endpoints.failure()
+            {
+                log_info!(
+                    &mut self.logger,
+                    "failing over to Arrow Service {}",
+                    self.endpoints.current().address()
+                );
+            }
+
+            self.current_addr = self.endpoints.current().address().to_string();
+
+            self.wait_for_retry(retry).await;
         } else {
             panic!("unexpected Result variant")
         }
     }
+
+    /// Process a given main task command.
+    fn process_command(&mut self, cmd: MainTaskCommand) {
+        match cmd {
+            MainTaskCommand::EnterPairingMode => {
+                log_info!(&mut self.logger, "entering the pairing mode");
+
+                self.pairing_mode_timeout = Instant::now() + self.pairing_policy.window;
+            }
+            MainTaskCommand::Resume => {
+                // start over, the client would be suspended again right away
+                // otherwise
+                self.pairing_mode_timeout = Instant::now() + self.pairing_policy.window;
+
+                self.backoff.reset();
+
+                self.app_context.set_backoff_state(0, None);
+            }
+        }
+    }
+
+    /// Process all commands received so far (without waiting for new ones).
+    fn process_pending_commands(&mut self) {
+        while let Some(Some(cmd)) = self.commands.next().now_or_never() {
+            self.process_command(cmd);
+        }
+    }
+
+    /// Process a given connection retry object.
+    async fn wait_for_retry(&mut self, connection_retry: ConnectionRetry) {
+        // the waiting will be interrupted if the client is shutting down
+        let shutdown = self.app_context.get_shutdown_signal();
+
+        match connection_retry {
+            ConnectionRetry::Timeout(t) if t > Duration::from_millis(500) => {
+                log_info!(
+                    &mut self.logger,
+                    "retrying in {}.{:03} seconds",
+                    t.as_secs(),
+                    t.subsec_millis()
+                );
+
+                let delay = tokio::time::delay_for(t);
+
+                futures::future::select(delay, shutdown).await;
+            }
+            ConnectionRetry::Timeout(_) => (),
+            ConnectionRetry::Suspend(reason) => {
+                log_info!(&mut self.logger, "{}", reason);
+                log_info!(&mut self.logger, "suspending the connection thread");
+
+                // wait until the client is asked to continue (all commands
+                // resume the thread)
+                match futures::future::select(self.commands.next(), shutdown).await {
+                    Either::Left((Some(cmd), _)) => {
+                        self.process_command(cmd);
+
+                        log_info!(&mut self.logger, "resuming the connection thread");
+                    }
+                    Either::Left((None, shutdown)) => {
+                        // the client is gone, there is no way to resume
+                        let _ = shutdown.await;
+                    }
+                    Either::Right(_) => (),
+                }
+            }
+        }
+    }
 }
 
 /// Connection retry variants. There are only two options - the connection can
@@ -149,90 +747,31 @@ enum ConnectionRetry {
     Suspend(SuspendReason),
 }
 
-/// Reason for suspending the Arrow connection thread.
-#[derive(Debug, Copy, Clone)]
-enum SuspendReason {
-    NotInPairingMode,
-    UnsupportedProtocolVersion,
-}
-
-impl SuspendReason {
-    fn to_string(&self) -> &str {
-        match *self {
-            SuspendReason::NotInPairingMode => "pairing window timeout",
-            SuspendReason::UnsupportedProtocolVersion => "unsupported protocol version",
-        }
-    }
-}
-
 /// Process a given connection error and return a ConnectionRetry instance.
+/// The retry timeout is relative to the time of the failure.
 fn process_connection_error(
     connection_error: ArrowError,
-    last_attempt: Instant,
     pairing_mode_timeout: Instant,
+    pairing_policy: &PairingPolicy,
+    backoff: &mut ReconnectBackoff,
 ) -> ConnectionRetry {
     let now = Instant::now();
 
     match connection_error.kind() {
-        // the client is not authorized to access the service yet; check the
-        // pairing mode timeout
-        ErrorKind::Unauthorized => {
-            if (now + Duration::from_secs(600)) < pairing_mode_timeout {
-                // retry every 10 seconds in the first 10 minutes since the
-                // first "unauthorized" response
-                ConnectionRetry::Timeout(Duration::from_secs(10))
-            } else if now < pairing_mode_timeout {
-                // retry every 30 seconds after the first 10 minutes since the
-                // first "unauthorized" response
-                ConnectionRetry::Timeout(Duration::from_secs(30))
-            } else {
-                // suspend the thread after the first 20 minutes since the
-                // client thread start
-                ConnectionRetry::Suspend(SuspendReason::NotInPairingMode)
-            }
-        }
+        // the client is not authorized to access the service yet; the retry
+        // interval depends on the pairing policy and on the pairing mode
+        // timeout
+        ErrorKind::Unauthorized => match pairing_policy.retry_interval(now, pairing_mode_timeout) {
+            Some(interval) => ConnectionRetry::Timeout(interval),
+            None => ConnectionRetry::Suspend(SuspendReason::NotInPairingMode),
+        },
         // suspend the thread if the version of the Arrow Protocol is not
         // supported by either side
         ErrorKind::UnsupportedProtocolVersion => {
             ConnectionRetry::Suspend(SuspendReason::UnsupportedProtocolVersion)
         }
         // in all other cases
-        _ => {
-            let next_attempt = last_attempt + RETRY_TIMEOUT;
-
-            if next_attempt > now {
-                ConnectionRetry::Timeout(next_attempt - now)
-            } else {
-                ConnectionRetry::Timeout(Duration::from_secs(0))
-            }
-        }
-    }
-}
-
-/// Process a given connection retry object.
-async fn wait_for_retry(logger: &mut dyn Logger, connection_retry: ConnectionRetry) {
-    match connection_retry {
-        ConnectionRetry::Timeout(t) if t > Duration::from_millis(500) => {
-            log_info!(
-                logger,
-                "retrying in {}.{:03} seconds",
-                t.as_secs(),
-                t.subsec_millis()
-            );
-
-            let delay = tokio::time::delay_for(t);
-
-            delay.await;
-        }
-        ConnectionRetry::Timeout(_) => (),
-        ConnectionRetry::Suspend(reason) => {
-            log_info!(logger, "{}", reason.to_string());
-            log_info!(logger, "suspending the connection thread");
-
-            let halt = futures::future::pending::<()>();
-
-            halt.await;
-        }
+        _ => ConnectionRetry::Timeout(backoff.next_delay()),
     }
 }
 
@@ -249,6 +788,26 @@ fn diagnose_connection_result(connection_result: &Result<String, ArrowError>) ->
     }
 }
 
+/// Create a future serving the control socket (if configured).
+#[cfg(not(target_os = "windows"))]
+fn create_control_socket(
+    context: &ApplicationContext,
+    channel: &MainTaskChannel,
+) -> Option<Pin<Box<dyn Future<Output = ()> + Send>>> {
+    context
+        .get_control_socket()
+        .map(|path| crate::control::serve(context.clone(), path, channel.clone()).boxed())
+}
+
+/// Dummy control socket (Unix sockets are not supported on Windows).
+#[cfg(target_os = "windows")]
+fn create_control_socket(
+    _: &ApplicationContext,
+    _: &MainTaskChannel,
+) -> Option<Pin<Box<dyn Future<Output = ()> + Send>>> {
+    None
+}
+
 /// Arrow client task. It must be awaited, otherwise it won't do anything.
 pub struct ArrowClientTask {
     inner: Pin<Box<dyn Future<Output = ()> + Send>>,
@@ -267,21 +826,61 @@ pub struct ArrowClient {
     application_context: ApplicationContext,
 
     command_channel: Option<CommandChannel>,
+    main_task_channel: Option<MainTaskChannel>,
 
     cancel_main_task: Option<AbortHandle>,
     cancel_nw_scan: Option<AbortHandle>,
+    cancel_control_socket: Option<AbortHandle>,
+
+    task_done: Option<oneshot::Receiver<()>>,
+    task_done_blocking: Option<std::sync::mpsc::Receiver<()>>,
 }
 
 impl ArrowClient {
-    /// Create a new Arrow client from a given config.
+    /// Create a new Arrow client from a given config. The client will use
+    /// the default transport (i.e. TLS over TCP).
     pub fn new(config: Config) -> (ArrowClient, ArrowClientTask) {
         let context = ApplicationContext::new(config);
 
+        let transport = DefaultTransport::new(context.clone());
+
+        Self::with_context(context, Arc::new(transport))
+    }
+
+    /// Create a new Arrow client from a given config. The client will use a
+    /// given transport for connecting to the Arrow Service.
+    pub fn with_transport<T>(config: Config, transport: T) -> (ArrowClient, ArrowClientTask)
+    where
+        T: ArrowTransport + 'static,
+    {
+        let context = ApplicationContext::new(config);
+
+        Self::with_context(context, Arc::new(transport))
+    }
+
+    /// Create a new Arrow client from a given application context and
+    /// transport.
+    fn with_context(
+        context: ApplicationContext,
+        transport: Arc<dyn ArrowTransport>,
+    ) -> (ArrowClient, ArrowClientTask) {
         // create command handler
         let (cmd_channel, cmd_handler) = cmd_handler::new(context.clone());
 
+        let (main_task_tx, main_task_rx) = mpsc::unbounded();
+
+        let main_task_channel = MainTaskChannel::new(main_task_tx);
+
         // create Arrow client main task
-        let arrow_main_task = ArrowMainTask::start(context.clone(), cmd_channel.clone());
+        let arrow_main_task = ArrowMainTask::start(
+            context.clone(),
+            cmd_channel.clone(),
+            transport,
+            main_task_rx,
+        );
+
+        // create control socket task
+        let control_socket = create_control_socket(&context, &main_task_channel);
 
         let nw_scan_cmd_channel = cmd_channel.clone();
 
@@ -297,12 +896,30 @@ impl ArrowClient {
         let (periodic_network_scan, cancel_nw_scan) =
             futures::future::abortable(periodic_network_scan.boxed());
 
+        let (control_socket, cancel_control_socket) = match control_socket {
+            Some(control_socket) => {
+                let (task, handle) = futures::future::abortable(control_socket);
+
+                (Some(task), Some(handle))
+            }
+            None => (None, None),
+        };
+
+        let (task_done_tx, task_done_rx) = oneshot::channel();
+        let (task_done_blocking_tx, task_done_blocking_rx) = std::sync::mpsc::sync_channel(1);
+
         let ctx = context.clone();
 
+        let task_cmd_channel = cmd_channel.clone();
+
         let task = async move {
-            tokio::spawn(cmd_handler);
+            let cmd_handler = tokio::spawn(cmd_handler);
             tokio::spawn(periodic_network_scan.map(|_| ()));
 
+            if let Some(control_socket) = control_socket {
+                tokio::spawn(control_socket.map(|_| ()));
+            }
+
             let mut logger = ctx.get_logger();
 
             log_info!(
@@ -312,7 +929,19 @@ impl ArrowClient {
                 ctx.get_arrow_mac_address()
             );
 
-            arrow_main_task.await.unwrap_or(());
+            // the main task terminates on its own only if the client is
+            // shutting down, we need to wait for the command handler as well
+            // in such case (it may be waiting for the network scanner)
+            if arrow_main_task.await.is_ok() {
+                task_cmd_channel.send(Command::Shutdown);
+
+                let _ = cmd_handler.await;
+
+                log_info!(&mut logger, "Arrow Client stopped");
+            }
+
+            let _ = task_done_tx.send(());
+            let _ = task_done_blocking_tx.send(());
         };
 
         let arrow_client_task = ArrowClientTask {
@@ -323,9 +952,14 @@ impl ArrowClient {
             application_context: context,
 
             command_channel: Some(cmd_channel),
+            main_task_channel: Some(main_task_channel),
 
             cancel_main_task: Some(cancel_main_task),
             cancel_nw_scan: Some(cancel_nw_scan),
+            cancel_control_socket,
+
+            task_done: Some(task_done_rx),
+            task_done_blocking: Some(task_done_blocking_rx),
         };
 
         (arrow_client, arrow_client_task)
@@ -336,6 +970,12 @@ impl ArrowClient {
         self.application_context.get_arrow_uuid()
     }
 
+    /// Get the client logger.
+    #[doc(hidden)]
+    pub fn get_logger(&self) -> BoxLogger {
+        self.application_context.get_logger()
+    }
+
     /// Get Arrow client MAC address.
     pub fn get_mac_address(&self) -> MacAddr {
         self.application_context.get_arrow_mac_address()
@@ -346,6 +986,12 @@ impl ArrowClient {
         self.application_context.get_connection_state()
     }
 
+    /// Get address of the Arrow Service the client is currently connected
+    /// to (if any).
+    pub fn get_active_arrow_service(&self) -> Option<String> {
+        self.application_context.get_active_arrow_service()
+    }
+
     /// Check if the client is currently scanning network.
     pub fn is_scanning(&self) -> bool {
         self.application_context.is_scanning()
@@ -366,6 +1012,17 @@ impl ArrowClient {
             .collect()
     }
 
+    /// Get information about all active service sessions.
+    pub fn get_sessions(&self) -> Vec<SessionInfo> {
+        self.application_context.get_sessions()
+    }
+
+    /// Get Arrow Service connection statistics (round-trip time, number of
+    /// reconnects, uptime, etc.).
+    pub fn get_connection_stats(&self) -> ConnectionStats {
+        self.application_context.get_connection_stats()
+    }
+
     /// Add a new event listener.
     pub fn add_event_listener<T>(&mut self, listener: T)
     where
@@ -389,18 +1046,99 @@ impl ArrowClient {
         }
     }
 
+    /// Reset the pairing window (i.e. the client will keep trying to connect
+    /// to the Arrow Service while waiting for pairing) and resume the client
+    /// if it has been suspended.
+    pub fn enter_pairing_mode(&mut self) {
+        if let Some(channel) = self.main_task_channel.as_ref() {
+            channel.send(MainTaskCommand::EnterPairingMode);
+        }
+    }
+
+    /// Resume the client if it has been suspended (e.g. because the pairing
+    /// window expired). The pairing window and the reconnect backoff are
+    /// reset.
+    pub fn resume(&mut self) {
+        if let Some(channel) = self.main_task_channel.as_ref() {
+            channel.send(MainTaskCommand::Resume);
+        }
+    }
+
+    /// Shut down the Arrow client gracefully. All active sessions will be
+    /// closed (the Arrow Service will be notified about each of them), all
+    /// pending control messages will be flushed and the connection will be
+    /// closed. The method also waits for the network scanner to finish. The
+    /// client will be closed forcibly if the shutdown does not complete
+    /// within a given timeout.
+    pub async fn shutdown(&mut self, timeout: Duration) {
+        self.request_shutdown();
+
+        if let Some(task_done) = self.task_done.take() {
+            if tokio::time::timeout(timeout, task_done).await.is_err() {
+                self.log_shutdown_timeout();
+            }
+        }
+
+        self.close()
+    }
+
+    /// Shut down the Arrow client gracefully blocking the current thread.
+    /// See `shutdown()` for more details. The method must not be called from
+    /// within the client task (e.g. from an event listener) as it would block
+    /// the task until the timeout expires.
+    pub fn shutdown_blocking(&mut self, timeout: Duration) {
+        self.request_shutdown();
+
+        if let Some(task_done) = self.task_done_blocking.take() {
+            if task_done.recv_timeout(timeout).is_err() {
+                self.log_shutdown_timeout();
+            }
+        }
+
+        self.close()
+    }
+
+    /// Stop the background tasks that are not needed for the shutdown and
+    /// signal the shutdown to the rest of the client.
+    fn request_shutdown(&mut self) {
+        if let Some(handle) = self.cancel_nw_scan.take() {
+            handle.abort();
+        }
+
+        if let Some(handle) = self.cancel_control_socket.take() {
+            handle.abort();
+        }
+
+        self.application_context.shutdown();
+    }
+
+    /// Log that the client could not be shut down gracefully.
+    fn log_shutdown_timeout(&self) {
+        let mut logger = self.application_context.get_logger();
+
+        log_warn!(
+            &mut logger,
+            "unable to shut down the Arrow Client gracefully (timeout)"
+        );
+    }
+
     /// Close the Arrow client.
     pub fn close(&mut self) {
         if let Some(handle) = self.cancel_nw_scan.take() {
             handle.abort();
         }
 
+        if let Some(handle) = self.cancel_control_socket.take() {
+            handle.abort();
+        }
+
         if let Some(handle) = self.cancel_main_task.take() {
             handle.abort();
         }
 
         // we need to drop also the command channel in order to stop the related background task
         self.command_channel = None;
+        self.main_task_channel = None;
     }
 }
 
@@ -409,3 +1147,617 @@ impl Drop for ArrowClient {
         self.close()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::thread;
+
+    use std::net::TcpListener;
+    use std::sync::{mpsc, Mutex};
+
+    use futures::channel::oneshot;
+
+    use crate::net::arrow::mock::{
+        MockArrowService, MockConnection, TestCa, EC_NO_ERROR, EC_UNSUPPORTED_PROTOCOL_VERSION,
+    };
+    use crate::runtime;
+
+    /// Create a client configuration for a given primary address and a given
+    /// list of fallback addresses.
+    fn create_config(ca: &TestCa, primary: &str, fallback: &[&str]) -> Config {
+        let mut policy = ReconnectPolicy::new();
+
+        policy
+            .initial_delay(Duration::from_millis(10))
+            .max_delay(Duration::from_millis(10))
+            .jitter(0.0);
+
+        let mut builder = Config::builder();
+
+        builder
+            .mac_address(Some(MacAddr::new(0, 1, 2, 3, 4, 5)))
+            .reconnect_policy(policy);
+
+        for addr in fallback {
+            builder.add_arrow_service_address(addr, 1);
+        }
+
+        builder.build(ca.storage(), primary).unwrap()
+    }
+
+    /// Event listener collecting connection state changes.
+    struct StateListener {
+        states: Arc<Mutex<Vec<ConnectionState>>>,
+    }
+
+    impl ArrowClientEventListener for StateListener {
+        fn connection_state_changed(&mut self, state: ConnectionState) {
+            self.states.lock().unwrap().push(state);
+        }
+    }
+
+    /// Run a given client until a given mock Arrow Service script signals
+    /// that the client is connected. A given check is executed after that
+    /// while the connection is still open. The check also gets all
+    /// connection state changes reported so far.
+    fn run_client<S, C>(config: Config, script: S, check: C)
+    where
+        S: FnOnce() -> MockConnection + Send + 'static,
+        C: FnOnce(&ArrowClient, &[ConnectionState]),
+    {
+        let (connected_tx, connected_rx) = oneshot::channel();
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+
+        let handle = thread::spawn(move || {
+            let mut connection = script();
+
+            // make sure that the REGISTER ACK has been processed
+            connection.ping();
+
+            connected_tx.send(()).unwrap();
+
+            // keep the connection open until the check is done
+            let _ = release_rx.recv();
+        });
+
+        runtime::run(async move {
+            let (mut client, task) = ArrowClient::new(config);
+
+            let states = Arc::new(Mutex::new(Vec::new()));
+
+            client.add_event_listener(StateListener {
+                states: states.clone(),
+            });
+
+            tokio::spawn(task);
+
+            connected_rx
+                .await
+                .expect("mock Arrow Service script failed");
+
+            check(&client, &states.lock().unwrap());
+
+            release_tx.send(()).unwrap();
+        });
+
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_redirect() {
+        let ca = TestCa::new();
+
+        let svc_a = MockArrowService::new(&ca);
+        let svc_b = MockArrowService::new(&ca);
+
+        let addr_a = svc_a.address();
+        let addr_b = svc_b.address();
+
+        let config = create_config(&ca, &addr_a, &[]);
+
+        let expected = addr_b.clone();
+
+        let script = move || {
+            let mut connection = svc_a.accept();
+
+            connection.expect_register(EC_NO_ERROR);
+            connection.redirect(&addr_b);
+
+            let mut connection = svc_b.accept();
+
+            connection.expect_register(EC_NO_ERROR);
+
+            connection
+        };
+
+        run_client(config, script, |client, states| {
+            assert_eq!(client.get_active_arrow_service(), Some(expected));
+            assert_eq!(client.get_connection_state(), ConnectionState::Connected);
+
+            let expected_states = [
+                ConnectionState::Connecting,
+                ConnectionState::Handshaking,
+                ConnectionState::Connected,
+                ConnectionState::Redirecting,
+                ConnectionState::Connecting,
+                ConnectionState::Handshaking,
+                ConnectionState::Connected,
+            ];
+
+            assert_eq!(states, &expected_states[..]);
+        });
+    }
+
+    #[test]
+    fn test_failover() {
+        let ca = TestCa::new();
+
+        // get an address where nobody listens
+        let addr_a = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .to_string();
+
+        let svc_b = MockArrowService::new(&ca);
+
+        let addr_b = svc_b.address();
+
+        let config = create_config(&ca, &addr_a, &[&addr_b]);
+
+        let script = move || {
+            let mut connection = svc_b.accept();
+
+            connection.expect_register(EC_NO_ERROR);
+
+            connection
+        };
+
+        run_client(config, script, |client, states| {
+            assert_eq!(client.get_active_arrow_service(), Some(addr_b));
+
+            let failure = ConnectionState::Disconnected(Some(ErrorKind::ConnectionError));
+
+            assert_eq!(states[..2], [ConnectionState::Connecting, failure]);
+        });
+    }
+
+    #[test]
+    fn test_protocol_version_fallback() {
+        let ca = TestCa::new();
+
+        let svc = MockArrowService::new(&ca);
+
+        let config = create_config(&ca, &svc.address(), &[]);
+
+        let addr = svc.address();
+
+        let script = move || {
+            let mut connection = svc.accept();
+
+            // announce a newer version of the Arrow protocol
+            connection.use_version(MAX_ARROW_PROTOCOL_VERSION);
+            connection.expect_register(EC_NO_ERROR);
+            connection.redirect(&addr);
+
+            assert_eq!(connection.version(), DEFAULT_ARROW_PROTOCOL_VERSION);
+
+            let mut connection = svc.accept();
+
+            connection.expect_register(EC_UNSUPPORTED_PROTOCOL_VERSION);
+
+            assert_eq!(connection.version(), MAX_ARROW_PROTOCOL_VERSION);
+
+            let mut connection = svc.accept();
+
+            connection.expect_register(EC_NO_ERROR);
+
+            assert_eq!(connection.version(), MAX_ARROW_PROTOCOL_VERSION - 1);
+
+            connection
+        };
+
+        run_client(config, script, |client, _| {
+            assert_eq!(client.get_connection_state(), ConnectionState::Connected);
+        });
+    }
+
+    #[test]
+    fn test_protocol_version_kept_on_close() {
+        let ca = TestCa::new();
+
+        let svc = MockArrowService::new(&ca);
+
+        let config = create_config(&ca, &svc.address(), &[]);
+
+        let addr = svc.address();
+
+        let script = move || {
+            let mut connection = svc.accept();
+
+            connection.use_version(MAX_ARROW_PROTOCOL_VERSION);
+            connection.expect_register(EC_NO_ERROR);
+            connection.redirect(&addr);
+
+            let connection = svc.accept();
+
+            // a transient failure during the handshake must not be taken
+            // as a rejection of the protocol version
+            assert_eq!(connection.close_on_register(), MAX_ARROW_PROTOCOL_VERSION);
+
+            let mut connection = svc.accept();
+
+            connection.expect_register(EC_NO_ERROR);
+
+            assert_eq!(connection.version(), MAX_ARROW_PROTOCOL_VERSION);
+
+            connection
+        };
+
+        run_client(config, script, |client, _| {
+            assert_eq!(client.get_connection_state(), ConnectionState::Connected);
+        });
+    }
+
+    #[test]
+    fn test_resume() {
+        let ca = TestCa::new();
+
+        let config = create_config(&ca, "127.0.0.1:1", &[]);
+
+        let context = ApplicationContext::new(config);
+
+        let (cmd_channel, _) = cmd_handler::new(context.clone());
+
+        let transport = Arc::new(DefaultTransport::new(context.clone()));
+
+        let (tx, rx) = futures::channel::mpsc::unbounded();
+
+        let channel = MainTaskChannel::new(tx);
+
+        let mut task = ArrowMainTask::new(context, cmd_channel, transport, rx);
+
+        // the pairing window has expired
+        task.pairing_mode_timeout = Instant::now();
+
+        channel.send(MainTaskCommand::EnterPairingMode);
+
+        runtime::run(async move {
+            let retry = ConnectionRetry::Suspend(SuspendReason::NotInPairingMode);
+
+            task.wait_for_retry(retry).await;
+
+            assert!(task.pairing_mode_timeout > Instant::now() + Duration::from_secs(600));
+        });
+    }
+
+    #[test]
+    fn test_resume_resets_backoff() {
+        let ca = TestCa::new();
+
+        let config = create_config(&ca, "127.0.0.1:1", &[]);
+
+        let context = ApplicationContext::new(config);
+
+        let (cmd_channel, _) = cmd_handler::new(context.clone());
+
+        let transport = Arc::new(DefaultTransport::new(context.clone()));
+
+        let (tx, rx) = futures::channel::mpsc::unbounded();
+
+        let channel = MainTaskChannel::new(tx);
+
+        let mut task = ArrowMainTask::new(context, cmd_channel, transport, rx);
+
+        // the pairing window has expired after a few failed attempts
+        task.pairing_mode_timeout = Instant::now();
+
+        for _ in 0..5 {
+            task.backoff.next_delay();
+        }
+
+        channel.send(MainTaskCommand::Resume);
+
+        runtime::run(async move {
+            let retry = ConnectionRetry::Suspend(SuspendReason::NotInPairingMode);
+
+            task.wait_for_retry(retry).await;
+
+            assert!(task.pairing_mode_timeout > Instant::now() + Duration::from_secs(600));
+            assert_eq!(task.backoff.failures, 0);
+        });
+    }
+
+    #[test]
+    fn test_shutdown() {
+        let ca = TestCa::new();
+
+        let svc = MockArrowService::new(&ca);
+
+        let config = create_config(&ca, &svc.address(), &[]);
+
+        let (connected_tx, connected_rx) = oneshot::channel();
+
+        let handle = thread::spawn(move || {
+            let mut connection = svc.accept();
+
+            connection.expect_register(EC_NO_ERROR);
+            connection.ping();
+
+            connected_tx.send(()).unwrap();
+
+            connection.expect_close();
+        });
+
+        runtime::run(async move {
+            let (mut client, task) = ArrowClient::new(config);
+
+            let task = tokio::spawn(task);
+
+            connected_rx
+                .await
+                .expect("mock Arrow Service script failed");
+
+            let timeout = Duration::from_secs(10);
+
+            let start = Instant::now();
+
+            client.shutdown(timeout).await;
+
+            assert!(start.elapsed() < timeout);
+
+            assert_eq!(
+                client.get_connection_state(),
+                ConnectionState::Disconnected(None)
+            );
+
+            assert!(client.get_active_arrow_service().is_none());
+
+            task.await.unwrap();
+        });
+
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_shutdown_blocking() {
+        let ca = TestCa::new();
+
+        let svc = MockArrowService::new(&ca);
+
+        let config = create_config(&ca, &svc.address(), &[]);
+
+        let (connected_tx, connected_rx) = mpsc::channel();
+
+        let handle = thread::spawn(move || {
+            let mut connection = svc.accept();
+
+            connection.expect_register(EC_NO_ERROR);
+            connection.ping();
+
+            connected_tx.send(()).unwrap();
+
+            connection.expect_close();
+        });
+
+        let (mut client, task) = ArrowClient::new(config);
+
+        let task = thread::spawn(move || runtime::run(task));
+
+        connected_rx
+            .recv()
+            .expect("mock Arrow Service script failed");
+
+        let timeout = Duration::from_secs(10);
+
+        let start = Instant::now();
+
+        client.shutdown_blocking(timeout);
+
+        assert!(start.elapsed() < timeout);
+
+        assert_eq!(
+            client.get_connection_state(),
+            ConnectionState::Disconnected(None)
+        );
+
+        task.join().unwrap();
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_pairing_policy() {
+        let mut policy = PairingPolicy::new();
+
+        let now = Instant::now();
+
+        let deadline = now + Duration::from_secs(1200);
+
+        let fast = Some(Duration::from_secs(10));
+        let slow = Some(Duration::from_secs(30));
+
+        assert_eq!(policy.retry_interval(now, deadline), fast);
+        assert_eq!(
+            policy.retry_interval(now + Duration::from_secs(700), deadline),
+            slow
+        );
+        assert_eq!(policy.retry_interval(deadline, deadline), None);
+
+        policy
+            .window(Duration::from_secs(60))
+            .fast_phase(Duration::from_secs(120))
+            .fast_retry_interval(Duration::from_secs(1))
+            .retry_after_window(Some(Duration::from_secs(3600)));
+
+        let deadline = now + Duration::from_secs(60);
+
+        // the fast phase covers the whole window
+        assert_eq!(
+            policy.retry_interval(now + Duration::from_secs(59), deadline),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(
+            policy.retry_interval(deadline, deadline),
+            Some(Duration::from_secs(3600))
+        );
+
+        assert!(policy.validate().is_ok());
+
+        policy.retry_after_window(Some(Duration::from_secs(0)));
+
+        assert!(policy.validate().is_err());
+
+        policy
+            .retry_after_window(None)
+            .fast_retry_interval(Duration::from_secs(0));
+
+        assert!(policy.validate().is_err());
+
+        policy
+            .fast_retry_interval(Duration::from_secs(1))
+            .window(Duration::from_secs(u64::MAX));
+
+        assert!(policy.validate().is_err());
+    }
+
+    #[test]
+    fn test_endpoint_failover() {
+        let mut endpoints = EndpointList::new(vec![
+            ArrowServiceEndpoint::new("a", 2),
+            ArrowServiceEndpoint::new("b", 1),
+        ]);
+
+        assert_eq!(endpoints.current().address(), "a");
+        assert!(!endpoints.failure());
+        assert_eq!(endpoints.current().address(), "a");
+        assert!(endpoints.failure());
+        assert_eq!(endpoints.current().address(), "b");
+
+        endpoints.success();
+
+        assert!(endpoints.failure());
+        assert_eq!(endpoints.current().address(), "a");
+        assert!(!endpoints.failure());
+
+        endpoints.success();
+
+        assert!(!endpoints.failure());
+        assert_eq!(endpoints.current().address(), "a");
+    }
+
+    #[test]
+    fn test_single_endpoint_failover() {
+        let mut endpoints = EndpointList::new(vec![ArrowServiceEndpoint::new("a", 0)]);
+
+        assert!(!endpoints.failure());
+        assert_eq!(endpoints.current().address(), "a");
+    }
+
+    #[test]
+    fn test_reconnect_backoff() {
+        let mut policy = ReconnectPolicy::new();
+
+        policy
+            .initial_delay(Duration::from_secs(1))
+            .max_delay(Duration::from_secs(10))
+            .jitter(0.0);
+
+        let mut backoff = ReconnectBackoff::new(policy);
+
+        assert_eq!(backoff.next_delay(), Duration::from_secs(1));
+        assert_eq!(backoff.next_delay(), Duration::from_secs(2));
+        assert_eq!(backoff.next_delay(), Duration::from_secs(4));
+        assert_eq!(backoff.next_delay(), Duration::from_secs(8));
+        assert_eq!(backoff.next_delay(), Duration::from_secs(10));
+        assert_eq!(backoff.next_delay(), Duration::from_secs(10));
+
+        backoff.reset();
+
+        assert_eq!(backoff.next_delay(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_reconnect_backoff_jitter() {
+        let mut policy = ReconnectPolicy::new();
+
+        policy
+            .initial_delay(Duration::from_secs(8))
+            .max_delay(Duration::from_secs(8))
+            .jitter(0.5);
+
+        let mut backoff = ReconnectBackoff::new(policy);
+
+        for _ in 0..100 {
+            let delay = backoff.next_delay();
+
+            assert!(delay > Duration::from_secs(4));
+            assert!(delay <= Duration::from_secs(8));
+        }
+    }
+
+    #[test]
+    fn test_reconnect_backoff_limits() {
+        let mut policy = ReconnectPolicy::new();
+
+        policy
+            .initial_delay(Duration::from_secs(u64::MAX / 2))
+            .max_delay(Duration::from_secs(60))
+            .jitter(0.0)
+            .jitter(f64::NAN);
+
+        let mut backoff = ReconnectBackoff::new(policy);
+
+        // the delay must not overflow
+        for _ in 0..20 {
+            assert_eq!(backoff.next_delay(), Duration::from_secs(60));
+        }
+    }
+
+    #[test]
+    fn test_retry_after_long_connection() {
+        let mut policy = ReconnectPolicy::new();
+
+        let reset_after = Duration::from_secs(10);
+
+        policy
+            .initial_delay(Duration::from_secs(5))
+            .max_delay(Duration::from_secs(60))
+            .jitter(0.0)
+            .reset_after(reset_after);
+
+        let mut backoff = ReconnectBackoff::new(policy);
+
+        let pairing_policy = PairingPolicy::new();
+
+        let next_retry = |backoff: &mut ReconnectBackoff| {
+            let retry = process_connection_error(
+                ArrowError::other("connection lost"),
+                Instant::now(),
+                &pairing_policy,
+                backoff,
+            );
+
+            match retry {
+                ConnectionRetry::Timeout(t) => t,
+                _ => panic!("timeout expected"),
+            }
+        };
+
+        assert_eq!(next_retry(&mut backoff), Duration::from_secs(5));
+        assert_eq!(next_retry(&mut backoff), Duration::from_secs(10));
+
+        // a short connection does not reset the backoff
+        backoff.connection_closed(Instant::now());
+
+        assert_eq!(next_retry(&mut backoff), Duration::from_secs(20));
+
+        // the connection stayed up longer than the reset period, the retry
+        // delay must start again from the initial delay (and it must be
+        // counted from the failure, not from the connection attempt)
+        backoff.connection_closed(Instant::now() - reset_after - Duration::from_secs(1));
+
+        assert_eq!(next_retry(&mut backoff), Duration::from_secs(5));
+    }
+}
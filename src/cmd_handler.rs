@@ -47,6 +47,7 @@ pub enum Command {
     ResetServiceTable,
     ScanNetwork,
     PeriodicNetworkScan,
+    Shutdown,
 }
 
 /// Command handler event.
@@ -88,6 +89,7 @@ struct CommandHandlerContext {
     cmd_sender: CommandSender,
     last_nw_scan: Option<Instant>,
     scanner: Option<JoinHandle<()>>,
+    shutdown: bool,
 }
 
 impl CommandHandlerContext {
@@ -101,9 +103,16 @@ impl CommandHandlerContext {
             cmd_sender,
             last_nw_scan: None,
             scanner: None,
+            shutdown: false,
         }
     }
 
+    /// Check if the command handler should terminate (i.e. it has been asked
+    /// to shut down and there is no running network scanner).
+    fn is_terminated(&self) -> bool {
+        self.shutdown && self.scanner.is_none()
+    }
+
     /// Process a given command handler event.
     fn proces_event(&mut self, event: Event) {
         match event {
@@ -118,9 +127,23 @@ impl CommandHandlerContext {
             Command::ResetServiceTable => self.reset_service_table(),
             Command::ScanNetwork => self.scan_network(),
             Command::PeriodicNetworkScan => self.periodic_network_scan(),
+            Command::Shutdown => self.shutdown(),
         }
     }
 
+    /// Stop accepting new network scan requests. The handler will terminate
+    /// once the currently running network scan (if any) completes.
+    fn shutdown(&mut self) {
+        if self.scanner.is_some() {
+            log_info!(
+                self.logger,
+                "waiting for the network scanner thread to finish..."
+            );
+        }
+
+        self.shutdown = true;
+    }
+
     /// Reset service table.
     fn reset_service_table(&mut self) {
         self.app_context.reset_service_table()
@@ -141,7 +164,7 @@ impl CommandHandlerContext {
     #[cfg(feature = "discovery")]
     /// Trigger a network scan.
     fn scan_network(&mut self) {
-        if !self.app_context.get_discovery() || self.scanner.is_some() {
+        if !self.app_context.get_discovery() || self.scanner.is_some() || self.shutdown {
             return;
         }
 
@@ -191,6 +214,10 @@ impl CommandHandler {
         let handler = async move {
             while let Some(event) = rx.next().await {
                 context.proces_event(event);
+
+                if context.is_terminated() {
+                    break;
+                }
             }
         };
 
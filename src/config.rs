@@ -17,15 +17,18 @@ use std::io;
 use std::process;
 use std::str;
 
-use std::collections::HashSet;
+use std::env;
+
+use std::collections::{HashMap, HashSet};
 use std::env::Args;
 use std::error::Error;
 use std::fmt::{Display, Formatter};
 use std::iter::FromIterator;
 use std::net::{SocketAddr, SocketAddrV4, SocketAddrV6};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 
 use json::JsonValue;
 
@@ -41,8 +44,9 @@ use crate::net::raw::devices::EthernetDevice;
 use crate::net::tls::TlsConnector;
 use crate::net::url::Url;
 use crate::storage::{DefaultStorage, Storage};
-use crate::svc_table::{SharedServiceTable, SharedServiceTableRef};
+use crate::svc_table::{ServiceTableError, SharedServiceTable, SharedServiceTableRef};
 use crate::utils::logger::file::FileLogger;
+use crate::utils::logger::ring::{LogBuffer, RingBufferLogger};
 use crate::utils::logger::stderr::StderrLogger;
 
 #[cfg(not(target_os = "windows"))]
@@ -51,6 +55,9 @@ use crate::utils::logger::syslog::Syslog;
 use crate::utils::logger::{BoxLogger, DummyLogger, Logger, Severity};
 use crate::utils::RuntimeError;
 
+pub use crate::client::{ArrowServiceEndpoint, PairingPolicy, ReconnectPolicy};
+pub use crate::net::arrow::MessageTracer;
+pub use crate::net::proxy::{Proxy, ProxyError, ProxyType};
 pub use crate::net::raw::ether::{AddrParseError, MacAddr};
 pub use crate::svc_table::{Service, ServiceType};
 pub use crate::utils::json::{FromJson, ParseError, ToJson};
@@ -81,6 +88,15 @@ const MJPEG_PATHS_FILE: &str = "/etc/arrow/mjpeg-paths";
 /// Default port number for connecting to an Arrow Service.
 const DEFAULT_ARROW_SERVICE_PORT: u16 = 8900;
 
+/// Default period of sending PING messages to the Arrow Service.
+const DEFAULT_PING_PERIOD: Duration = Duration::from_secs(60);
+
+/// Minimum period of sending PING messages to the Arrow Service.
+const MIN_PING_PERIOD: Duration = Duration::from_secs(1);
+
+/// Default number of log entries kept in memory.
+const DEFAULT_LOG_BUFFER_SIZE: usize = 1000;
+
 /// List of cipher that can be used for TLS connections to Arrow services.
 const SSL_CIPHER_LIST: &str = "HIGH:!aNULL:!kRSA:!PSK:!MD5:!RC4";
 
@@ -92,7 +108,7 @@ pub struct ConfigError {
 
 impl ConfigError {
     /// Create a new error.
-    fn new<T>(msg: T) -> Self
+    pub(crate) fn new<T>(msg: T) -> Self
     where
         T: ToString,
     {
@@ -118,6 +134,20 @@ pub struct ConfigBuilder {
     diagnostic_mode: bool,
     discovery: bool,
     discovery_whitelist: HashSet<String>,
+    reconnect_policy: ReconnectPolicy,
+    pairing_policy: PairingPolicy,
+    arrow_svc_weight: u32,
+    fallback_svc_endpoints: Vec<ArrowServiceEndpoint>,
+    proxy: Option<Proxy>,
+    max_sessions: Option<usize>,
+    session_idle_timeout: Option<Duration>,
+    rate_limit: Option<u32>,
+    service_rate_limits: HashMap<SocketAddr, u32>,
+    ping_period: Duration,
+    control_socket: Option<PathBuf>,
+    log_buffer_size: usize,
+    message_tracer: Option<MessageTracer>,
+    compressed_svc_types: HashSet<ServiceType>,
     verbose: bool,
 }
 
@@ -131,6 +161,20 @@ impl ConfigBuilder {
             diagnostic_mode: false,
             discovery: false,
             discovery_whitelist: HashSet::new(),
+            reconnect_policy: ReconnectPolicy::default(),
+            pairing_policy: PairingPolicy::default(),
+            arrow_svc_weight: 1,
+            fallback_svc_endpoints: Vec::new(),
+            proxy: None,
+            max_sessions: None,
+            session_idle_timeout: None,
+            rate_limit: None,
+            service_rate_limits: HashMap::new(),
+            ping_period: DEFAULT_PING_PERIOD,
+            control_socket: None,
+            log_buffer_size: DEFAULT_LOG_BUFFER_SIZE,
+            message_tracer: None,
+            compressed_svc_types: HashSet::from_iter(vec![ServiceType::HTTP, ServiceType::TCP]),
             verbose: false,
         }
     }
@@ -185,6 +229,133 @@ impl ConfigBuilder {
         self
     }
 
+    /// Set policy for reconnecting to the Arrow Service.
+    pub fn reconnect_policy(&mut self, policy: ReconnectPolicy) -> &mut Self {
+        self.reconnect_policy = policy;
+        self
+    }
+
+    /// Set policy for reconnecting to the Arrow Service while the client
+    /// waits for pairing. Policies with zero retry intervals or with
+    /// a pairing window that is too long will be rejected by `build()`.
+    pub fn pairing_policy(&mut self, policy: PairingPolicy) -> &mut Self {
+        self.pairing_policy = policy;
+        self
+    }
+
+    /// Set weight of the primary Arrow Service address (i.e. the number of
+    /// consecutive failed connection attempts before the client fails over to
+    /// the next address).
+    pub fn arrow_service_weight(&mut self, weight: u32) -> &mut Self {
+        self.arrow_svc_weight = weight;
+        self
+    }
+
+    /// Add a fallback Arrow Service address with a given weight. Fallback
+    /// addresses are used in the order they were added whenever the
+    /// previous one fails.
+    pub fn add_arrow_service_address<T>(&mut self, addr: T, weight: u32) -> &mut Self
+    where
+        T: ToString,
+    {
+        self.fallback_svc_endpoints
+            .push(ArrowServiceEndpoint::new(addr, weight));
+        self
+    }
+
+    /// Set proxy for connecting to the Arrow Service. The standard proxy
+    /// environment variables (`https_proxy`, `all_proxy` and `no_proxy`)
+    /// are used if there is no proxy given explicitly.
+    pub fn proxy(&mut self, proxy: Option<Proxy>) -> &mut Self {
+        self.proxy = proxy;
+        self
+    }
+
+    /// Set maximum number of concurrent service sessions (`None` means
+    /// unlimited). Any further session requested by the Arrow Service will
+    /// be rejected.
+    pub fn max_sessions(&mut self, max: Option<usize>) -> &mut Self {
+        self.max_sessions = max;
+        self
+    }
+
+    /// Set session idle timeout (`None` means no timeout). Sessions without
+    /// any traffic in either direction for longer than the timeout will be
+    /// closed.
+    pub fn session_idle_timeout(&mut self, timeout: Option<Duration>) -> &mut Self {
+        self.session_idle_timeout = timeout;
+        self
+    }
+
+    /// Set global bandwidth limit (in bytes per second) for data sent from
+    /// local services to the Arrow Service (`None` means no limit). Control
+    /// messages are not affected by the limit. Use `service_rate_limit()` or
+    /// `Service::with_rate_limit()` to limit individual services.
+    pub fn rate_limit(&mut self, limit: Option<u32>) -> &mut Self {
+        self.rate_limit = limit;
+        self
+    }
+
+    /// Set bandwidth limit (in bytes per second) for services at a given
+    /// address (`None` means no limit). The limit applies also to discovered
+    /// services and to services added remotely. Limits set using
+    /// `Service::with_rate_limit()` take precedence.
+    pub fn service_rate_limit(&mut self, addr: SocketAddr, limit: Option<u32>) -> &mut Self {
+        if let Some(limit) = limit {
+            self.service_rate_limits.insert(addr, limit);
+        } else {
+            self.service_rate_limits.remove(&addr);
+        }
+
+        self
+    }
+
+    /// Set period of sending PING messages to the Arrow Service. The PING
+    /// messages are used for measuring round-trip time and for detecting
+    /// broken connections. Periods shorter than one second (including zero)
+    /// are rounded up to one second.
+    pub fn ping_period(&mut self, period: Duration) -> &mut Self {
+        self.ping_period = period.max(MIN_PING_PERIOD);
+        self
+    }
+
+    /// Set path of a Unix socket accepting local control commands (e.g. for
+    /// entering the pairing mode).
+    pub fn control_socket<T>(&mut self, path: Option<T>) -> &mut Self
+    where
+        PathBuf: From<T>,
+    {
+        self.control_socket = path.map(PathBuf::from);
+        self
+    }
+
+    /// Set number of the most recent log entries kept in memory (zero
+    /// disables the in-memory log). The entries can be retrieved remotely by
+    /// the Arrow Service.
+    pub fn log_buffer_size(&mut self, size: usize) -> &mut Self {
+        self.log_buffer_size = size;
+        self
+    }
+
+    /// Set tracer recording all messages exchanged with the Arrow Service
+    /// (`None` means no tracing).
+    pub fn message_tracer(&mut self, tracer: Option<MessageTracer>) -> &mut Self {
+        self.message_tracer = tracer;
+        self
+    }
+
+    /// Set service types whose session data will be compressed (HTTP and TCP
+    /// services by default). The compression is used only if the client was
+    /// built with the `compression` feature and if it is supported by the
+    /// Arrow Service.
+    pub fn compressed_service_types<I>(&mut self, svc_types: I) -> &mut Self
+    where
+        I: IntoIterator<Item = ServiceType>,
+    {
+        self.compressed_svc_types = HashSet::from_iter(svc_types);
+        self
+    }
+
     /// Enable/disable verbose logging.
     pub fn verbose(&mut self, enabled: bool) -> &mut Self {
         self.verbose = enabled;
@@ -201,10 +372,24 @@ impl ConfigBuilder {
         S: 'static + Storage + Send,
         T: ToString,
     {
-        let mut logger = self
+        self.pairing_policy.validate()?;
+
+        // use the standard proxy environment variables if there is no proxy
+        // given explicitly
+        let (proxy, no_proxy) = if self.proxy.is_some() {
+            (self.proxy, Vec::new())
+        } else {
+            (proxy_from_env()?, no_proxy_from_env())
+        };
+
+        let logger = self
             .logger
             .unwrap_or_else(|| BoxLogger::new(DummyLogger::default()));
 
+        let log_buffer = LogBuffer::new(self.log_buffer_size);
+
+        let mut logger = BoxLogger::new(RingBufferLogger::new(logger, log_buffer.clone()));
+
         let config = storage.load_configuration().map_err(|err| {
             ConfigError::new(format!("unable to load client configuration: {}", err))
         })?;
@@ -229,20 +414,40 @@ impl ConfigBuilder {
             storage.load_mjpeg_paths(),
         );
 
+        let mut arrow_svc_endpoints = vec![ArrowServiceEndpoint::new(
+            arrow_service_address,
+            self.arrow_svc_weight,
+        )];
+
+        arrow_svc_endpoints.extend(self.fallback_svc_endpoints);
+
         let mut config = Config {
             version: config.version,
             uuid: config.uuid,
             passwd: config.passwd,
             arrow_mac: mac,
-            arrow_svc_addr: arrow_service_address.to_string(),
+            arrow_svc_endpoints,
             diagnostic_mode: self.diagnostic_mode,
             discovery: self.discovery,
             discovery_whitelist: Arc::new(self.discovery_whitelist),
             rtsp_paths: Arc::new(rtsp_paths.unwrap_or_default()),
             mjpeg_paths: Arc::new(mjpeg_paths.unwrap_or_default()),
+            reconnect_policy: self.reconnect_policy,
+            pairing_policy: self.pairing_policy,
+            proxy,
+            no_proxy,
+            max_sessions: self.max_sessions,
+            session_idle_timeout: self.session_idle_timeout,
+            rate_limit: self.rate_limit,
+            service_rate_limits: self.service_rate_limits,
+            ping_period: self.ping_period,
+            control_socket: self.control_socket,
             default_svc_table: config.svc_table.clone(),
             svc_table: config.svc_table,
             logger,
+            log_buffer,
+            message_tracer: self.message_tracer,
+            compressed_svc_types: Arc::new(self.compressed_svc_types),
             storage: Box::new(storage),
         };
 
@@ -251,6 +456,8 @@ impl ConfigBuilder {
         }
 
         for svc in self.services {
+            let svc = config.apply_service_rate_limit(svc);
+
             config.svc_table.add_static(svc.clone());
             config.default_svc_table.add_static(svc);
         }
@@ -287,6 +494,12 @@ impl Default for LoggerType {
 struct ConfigParser {
     arrow_mac: Option<MacAddr>,
     arrow_svc_addr: String,
+    arrow_svc_weight: u32,
+    fallback_svc_endpoints: Vec<ArrowServiceEndpoint>,
+    proxy: Option<Proxy>,
+    rate_limit: Option<u32>,
+    service_rate_limits: Vec<(SocketAddr, u32)>,
+    ping_period: Duration,
     ca_certificates: Vec<PathBuf>,
     services: Vec<Service>,
     logger_type: LoggerType,
@@ -304,6 +517,14 @@ struct ConfigParser {
     log_file_size: usize,
     log_file_rotations: usize,
     lock_file: Option<PathBuf>,
+    control_socket: Option<PathBuf>,
+    pairing_policy: PairingPolicy,
+    log_buffer_size: usize,
+    trace_file: Option<PathBuf>,
+    trace_file_size: usize,
+    trace_file_rotations: usize,
+    trace_payload: bool,
+    compressed_svc_types: Option<Vec<ServiceType>>,
 }
 
 impl ConfigParser {
@@ -312,6 +533,12 @@ impl ConfigParser {
         Self {
             arrow_mac: None,
             arrow_svc_addr: String::new(),
+            arrow_svc_weight: 1,
+            fallback_svc_endpoints: Vec::new(),
+            proxy: None,
+            rate_limit: None,
+            service_rate_limits: Vec::new(),
+            ping_period: DEFAULT_PING_PERIOD,
             ca_certificates: Vec::new(),
             services: Vec::new(),
             logger_type: LoggerType::default(),
@@ -329,6 +556,14 @@ impl ConfigParser {
             log_file_size: 10 * 1024,
             log_file_rotations: 1,
             lock_file: None,
+            control_socket: None,
+            pairing_policy: PairingPolicy::default(),
+            log_buffer_size: DEFAULT_LOG_BUFFER_SIZE,
+            trace_file: None,
+            trace_file_size: 1024 * 1024,
+            trace_file_rotations: 1,
+            trace_payload: false,
+            compressed_svc_types: None,
         }
     }
 
@@ -355,6 +590,28 @@ impl ConfigParser {
         Ok(logger)
     }
 
+    /// Create a new Arrow message tracer (if requested).
+    fn create_message_tracer(&self) -> Result<Option<MessageTracer>, ConfigError> {
+        if let Some(path) = self.trace_file.as_ref() {
+            let tracer = MessageTracer::new(
+                path,
+                self.trace_file_size,
+                self.trace_file_rotations,
+                self.trace_payload,
+            )
+            .map_err(|_| {
+                ConfigError::new(format!(
+                    "unable to open the given trace file: \"{}\"",
+                    path.to_string_lossy()
+                ))
+            })?;
+
+            Ok(Some(tracer))
+        } else {
+            Ok(None)
+        }
+    }
+
     /// Build application configuration.
     fn build(self) -> Result<Config, ConfigError> {
         // because of the lock file, we need to create the storage builder before creating the
@@ -364,6 +621,7 @@ impl ConfigParser {
                 .map_err(ConfigError::new)?;
 
         let logger = self.create_logger()?;
+        let tracer = self.create_message_tracer()?;
 
         storage_builder
             .logger(logger.clone())
@@ -385,8 +643,28 @@ impl ConfigParser {
             .diagnostic_mode(self.diagnostic_mode)
             .discovery(self.discovery)
             .discovery_whitelist(self.discovery_whitelist)
+            .arrow_service_weight(self.arrow_svc_weight)
+            .proxy(self.proxy)
+            .rate_limit(self.rate_limit)
+            .ping_period(self.ping_period)
+            .control_socket(self.control_socket)
+            .pairing_policy(self.pairing_policy)
+            .log_buffer_size(self.log_buffer_size)
+            .message_tracer(tracer)
             .verbose(self.verbose);
 
+        if let Some(svc_types) = self.compressed_svc_types {
+            config_builder.compressed_service_types(svc_types);
+        }
+
+        for (addr, limit) in self.service_rate_limits {
+            config_builder.service_rate_limit(addr, Some(limit));
+        }
+
+        for endpoint in self.fallback_svc_endpoints {
+            config_builder.add_arrow_service_address(endpoint.address(), endpoint.weight());
+        }
+
         let config = config_builder.build(storage, self.arrow_svc_addr)?;
 
         Ok(config)
@@ -414,6 +692,7 @@ impl ConfigParser {
                 "--diagnostic-mode" => self.diagnostic_mode(),
                 "--log-stderr" => self.log_stderr(),
                 "--log-stderr-pretty" => self.log_stderr_pretty(),
+                "--trace-payload" => self.trace_payload(),
 
                 arg => {
                     if arg.starts_with("--config-file=") {
@@ -436,6 +715,30 @@ impl ConfigParser {
                         self.log_file_rotations(arg)?;
                     } else if arg.starts_with("--lock-file=") {
                         self.lock_file(arg)?
+                    } else if arg.starts_with("--control-socket=") {
+                        self.control_socket(arg)?
+                    } else if arg.starts_with("--pairing-policy=") {
+                        self.pairing_policy(arg)?
+                    } else if arg.starts_with("--fallback-address=") {
+                        self.fallback_address(arg)?
+                    } else if arg.starts_with("--proxy=") {
+                        self.proxy(arg)?
+                    } else if arg.starts_with("--rate-limit=") {
+                        self.rate_limit(arg)?
+                    } else if arg.starts_with("--service-rate-limit=") {
+                        self.service_rate_limit(arg)?
+                    } else if arg.starts_with("--ping-period=") {
+                        self.ping_period(arg)?
+                    } else if arg.starts_with("--log-buffer-size=") {
+                        self.log_buffer_size(arg)?
+                    } else if arg.starts_with("--trace-file=") {
+                        self.trace_file(arg);
+                    } else if arg.starts_with("--trace-file-size=") {
+                        self.trace_file_size(arg)?;
+                    } else if arg.starts_with("--trace-file-rotations=") {
+                        self.trace_file_rotations(arg)?;
+                    } else if arg.starts_with("--compress=") {
+                        self.compress(arg)?;
                     } else {
                         return Err(ConfigError::new(format!("unknown argument: \"{}\"", arg)));
                     }
@@ -452,12 +755,22 @@ impl ConfigParser {
             .next()
             .ok_or_else(|| ConfigError::new("missing Angelcam Arrow Service address"))?;
 
-        // add the default port number if the given address has no port
-        if addr.ends_with(']') || !addr.contains(':') {
-            self.arrow_svc_addr = format!("{}:{}", addr, DEFAULT_ARROW_SERVICE_PORT);
-        } else {
-            self.arrow_svc_addr = addr;
-        }
+        let endpoint = parse_arrow_service_endpoint(&addr)?;
+
+        self.arrow_svc_addr = endpoint.address().to_string();
+        self.arrow_svc_weight = endpoint.weight();
+
+        Ok(())
+    }
+
+    /// Process the fallback-address argument.
+    fn fallback_address(&mut self, arg: &str) -> Result<(), ConfigError> {
+        // skip "--fallback-address=" length
+        let addr = &arg[19..];
+
+        let endpoint = parse_arrow_service_endpoint(addr)?;
+
+        self.fallback_svc_endpoints.push(endpoint);
 
         Ok(())
     }
@@ -607,7 +920,7 @@ impl ConfigParser {
         let size = &arg[16..];
 
         self.log_file_size = size.parse().map_err(|_| {
-            ConfigError::new(format!("invalid value given for {}, number expeced", arg))
+            ConfigError::new(format!("invalid value given for {}, number expected", arg))
         })?;
 
         Ok(())
@@ -619,7 +932,7 @@ impl ConfigParser {
         let rotations = &arg[21..];
 
         self.log_file_rotations = rotations.parse().map_err(|_| {
-            ConfigError::new(format!("invalid value given for {}, number expeced", arg))
+            ConfigError::new(format!("invalid value given for {}, number expected", arg))
         })?;
 
         Ok(())
@@ -686,6 +999,202 @@ impl ConfigParser {
 
         Ok(())
     }
+
+    /// Process the control-socket argument.
+    fn control_socket(&mut self, arg: &str) -> Result<(), ConfigError> {
+        // skip "--control-socket=" length
+        let control_socket = &arg[17..];
+
+        self.control_socket = Some(control_socket.into());
+
+        Ok(())
+    }
+
+    /// Process the pairing-policy argument.
+    fn pairing_policy(&mut self, arg: &str) -> Result<(), ConfigError> {
+        // skip "--pairing-policy=" length
+        let policy = &arg[17..];
+
+        let values = policy
+            .split(',')
+            .map(|v| v.parse().map(Duration::from_secs))
+            .collect::<Result<Vec<_>, _>>()
+            .ok()
+            .filter(|values| values.len() == 4 || values.len() == 5)
+            .ok_or_else(|| {
+                ConfigError::new(format!(
+                    "invalid value given for {}, four or five comma separated numbers expected",
+                    arg
+                ))
+            })?;
+
+        self.pairing_policy
+            .window(values[0])
+            .fast_phase(values[1])
+            .fast_retry_interval(values[2])
+            .slow_retry_interval(values[3])
+            .retry_after_window(values.get(4).copied().filter(|v| v.as_secs() > 0));
+
+        Ok(())
+    }
+
+    /// Process the proxy argument.
+    fn proxy(&mut self, arg: &str) -> Result<(), ConfigError> {
+        // skip "--proxy=" length
+        let proxy = &arg[8..];
+
+        self.proxy = Some(Proxy::from_str(proxy).map_err(ConfigError::new)?);
+
+        Ok(())
+    }
+
+    /// Process the rate-limit argument.
+    fn rate_limit(&mut self, arg: &str) -> Result<(), ConfigError> {
+        // skip "--rate-limit=" length
+        let limit = &arg[13..];
+
+        let limit = limit.parse().map_err(|_| {
+            ConfigError::new(format!("invalid value given for {}, number expected", arg))
+        })?;
+
+        self.rate_limit = Some(limit);
+
+        Ok(())
+    }
+
+    /// Process the service-rate-limit argument.
+    fn service_rate_limit(&mut self, arg: &str) -> Result<(), ConfigError> {
+        // skip "--service-rate-limit=" length
+        let value = &arg[21..];
+
+        let mut parts = value.rsplitn(2, ',');
+
+        let limit = parts.next().and_then(|limit| limit.parse().ok());
+        let addr = parts.next().and_then(|addr| addr.parse().ok());
+
+        if let (Some(addr), Some(limit)) = (addr, limit) {
+            self.service_rate_limits.push((addr, limit));
+
+            Ok(())
+        } else {
+            Err(ConfigError::new(format!(
+                "invalid value given for {}, IP address, port and a number expected",
+                arg
+            )))
+        }
+    }
+
+    /// Process the log-buffer-size argument.
+    fn log_buffer_size(&mut self, arg: &str) -> Result<(), ConfigError> {
+        // skip "--log-buffer-size=" length
+        let size = &arg[18..];
+
+        let size = size.parse().map_err(|_| {
+            ConfigError::new(format!("invalid value given for {}, number expected", arg))
+        })?;
+
+        self.log_buffer_size = size;
+
+        Ok(())
+    }
+
+    /// Process the trace-file argument.
+    fn trace_file(&mut self, arg: &str) {
+        // skip "--trace-file=" length
+        let trace_file = &arg[13..];
+
+        self.trace_file = Some(trace_file.into());
+    }
+
+    /// Process the trace-file-size argument.
+    fn trace_file_size(&mut self, arg: &str) -> Result<(), ConfigError> {
+        // skip "--trace-file-size=" length
+        let size = &arg[18..];
+
+        self.trace_file_size = size.parse().map_err(|_| {
+            ConfigError::new(format!("invalid value given for {}, number expected", arg))
+        })?;
+
+        Ok(())
+    }
+
+    /// Process the trace-file-rotations argument.
+    fn trace_file_rotations(&mut self, arg: &str) -> Result<(), ConfigError> {
+        // skip "--trace-file-rotations=" length
+        let rotations = &arg[23..];
+
+        self.trace_file_rotations = rotations.parse().map_err(|_| {
+            ConfigError::new(format!("invalid value given for {}, number expected", arg))
+        })?;
+
+        Ok(())
+    }
+
+    /// Process the trace-payload argument.
+    fn trace_payload(&mut self) {
+        self.trace_payload = true;
+    }
+
+    /// Process the compress argument.
+    fn compress(&mut self, arg: &str) -> Result<(), ConfigError> {
+        if !cfg!(feature = "compression") {
+            return Err(ConfigError::new(format!("unknown argument: \"{}\"", arg)));
+        }
+
+        // skip "--compress=" length
+        let names = &arg[11..];
+
+        let mut svc_types = Vec::new();
+
+        if names != "none" {
+            for name in names.split(',') {
+                match name {
+                    "rtsp" => svc_types.extend_from_slice(&[
+                        ServiceType::RTSP,
+                        ServiceType::LockedRTSP,
+                        ServiceType::UnknownRTSP,
+                        ServiceType::UnsupportedRTSP,
+                    ]),
+                    "mjpeg" => {
+                        svc_types.extend_from_slice(&[ServiceType::MJPEG, ServiceType::LockedMJPEG])
+                    }
+                    "http" => svc_types.push(ServiceType::HTTP),
+                    "tcp" => svc_types.push(ServiceType::TCP),
+                    _ => {
+                        return Err(ConfigError::new(format!(
+                            "invalid value given for {}, comma separated list of service types (rtsp, mjpeg, http, tcp) or \"none\" expected",
+                            arg
+                        )))
+                    }
+                }
+            }
+        }
+
+        self.compressed_svc_types = Some(svc_types);
+
+        Ok(())
+    }
+
+    /// Process the ping-period argument.
+    fn ping_period(&mut self, arg: &str) -> Result<(), ConfigError> {
+        // skip "--ping-period=" length
+        let period = &arg[14..];
+
+        let period = period
+            .parse()
+            .ok()
+            .filter(|&period| period > 0)
+            .ok_or_else(|| {
+                ConfigError::new(format!(
+                    "invalid value given for {}, positive number expected",
+                    arg
+                ))
+            })?;
+
+        self.ping_period = Duration::from_secs(period);
+
+        Ok(())
+    }
 }
 
 /// Client identification that can be publicly available.
@@ -806,15 +1315,28 @@ pub struct Config {
     uuid: Uuid,
     passwd: Uuid,
     arrow_mac: MacAddr,
-    arrow_svc_addr: String,
+    arrow_svc_endpoints: Vec<ArrowServiceEndpoint>,
     diagnostic_mode: bool,
     discovery: bool,
     discovery_whitelist: Arc<HashSet<String>>,
     rtsp_paths: Arc<Vec<String>>,
     mjpeg_paths: Arc<Vec<String>>,
+    reconnect_policy: ReconnectPolicy,
+    pairing_policy: PairingPolicy,
+    proxy: Option<Proxy>,
+    no_proxy: Vec<String>,
+    max_sessions: Option<usize>,
+    session_idle_timeout: Option<Duration>,
+    rate_limit: Option<u32>,
+    service_rate_limits: HashMap<SocketAddr, u32>,
+    ping_period: Duration,
+    control_socket: Option<PathBuf>,
     svc_table: SharedServiceTable,
     default_svc_table: SharedServiceTable,
     logger: BoxLogger,
+    log_buffer: LogBuffer,
+    message_tracer: Option<MessageTracer>,
+    compressed_svc_types: Arc<HashSet<ServiceType>>,
     storage: Box<dyn Storage + Send>,
 }
 
@@ -830,10 +1352,17 @@ impl Config {
         ConfigParser::new().parse(args)?.build()
     }
 
-    /// Get address of the remote Arrow Service.
+    /// Get address of the primary remote Arrow Service.
     #[doc(hidden)]
     pub fn get_arrow_service_address(&self) -> &str {
-        &self.arrow_svc_addr
+        self.arrow_svc_endpoints[0].address()
+    }
+
+    /// Get an ordered list of remote Arrow Service endpoints (the primary
+    /// one goes first).
+    #[doc(hidden)]
+    pub fn get_arrow_service_endpoints(&self) -> &[ArrowServiceEndpoint] {
+        &self.arrow_svc_endpoints
     }
 
     /// Get Arrow Client UUID.
@@ -884,12 +1413,106 @@ impl Config {
         self.mjpeg_paths.clone()
     }
 
+    /// Get policy for reconnecting to the Arrow Service.
+    #[doc(hidden)]
+    pub fn get_reconnect_policy(&self) -> ReconnectPolicy {
+        self.reconnect_policy
+    }
+
+    /// Get policy for reconnecting to the Arrow Service while the client
+    /// waits for pairing.
+    #[doc(hidden)]
+    pub fn get_pairing_policy(&self) -> PairingPolicy {
+        self.pairing_policy
+    }
+
     /// Get logger.
     #[doc(hidden)]
     pub fn get_logger(&self) -> BoxLogger {
         self.logger.clone()
     }
 
+    /// Get buffer containing the most recent log entries.
+    #[doc(hidden)]
+    pub fn get_log_buffer(&self) -> LogBuffer {
+        self.log_buffer.clone()
+    }
+
+    /// Get the Arrow message tracer.
+    #[doc(hidden)]
+    pub fn get_message_tracer(&self) -> Option<MessageTracer> {
+        self.message_tracer.clone()
+    }
+
+    /// Get service types whose session data will be compressed.
+    #[doc(hidden)]
+    pub fn get_compressed_service_types(&self) -> Arc<HashSet<ServiceType>> {
+        self.compressed_svc_types.clone()
+    }
+
+    /// Get proxy for connecting to a given Arrow Service address (in the
+    /// "host:port" format). There is no proxy for hosts listed in the
+    /// `no_proxy` environment variable.
+    #[doc(hidden)]
+    pub fn get_proxy(&self, addr: &str) -> Option<&Proxy> {
+        let host = if let Some(rest) = addr.strip_prefix('[') {
+            rest.split(']').next().unwrap_or(rest)
+        } else {
+            addr.rsplitn(2, ':').last().unwrap_or(addr)
+        };
+
+        if is_no_proxy_host(&self.no_proxy, host) {
+            None
+        } else {
+            self.proxy.as_ref()
+        }
+    }
+
+    /// Get maximum number of concurrent service sessions.
+    #[doc(hidden)]
+    pub fn get_max_sessions(&self) -> Option<usize> {
+        self.max_sessions
+    }
+
+    /// Get session idle timeout.
+    #[doc(hidden)]
+    pub fn get_session_idle_timeout(&self) -> Option<Duration> {
+        self.session_idle_timeout
+    }
+
+    /// Get global bandwidth limit (in bytes per second).
+    #[doc(hidden)]
+    pub fn get_rate_limit(&self) -> Option<u32> {
+        self.rate_limit
+    }
+
+    /// Set the configured bandwidth limit for a given service (if the
+    /// service does not have its own limit).
+    fn apply_service_rate_limit(&self, svc: Service) -> Service {
+        if svc.rate_limit().is_some() {
+            return svc;
+        }
+
+        let limit = svc
+            .address()
+            .and_then(|addr| self.service_rate_limits.get(&addr))
+            .copied();
+
+        svc.with_rate_limit(limit)
+    }
+
+    /// Get period of sending PING messages to the Arrow Service.
+    #[doc(hidden)]
+    pub fn get_ping_period(&self) -> Duration {
+        self.ping_period
+    }
+
+    /// Get path of the control socket.
+    #[doc(hidden)]
+    pub fn get_control_socket(&self) -> Option<&Path> {
+        self.control_socket.as_deref()
+    }
+
     /// Get TLS connector for a given server hostname.
     #[doc(hidden)]
     pub fn get_tls_connector(&mut self) -> Result<TlsConnector, RuntimeError> {
@@ -951,6 +1574,8 @@ impl Config {
         let old_version = self.svc_table.version();
 
         for svc in services {
+            let svc = self.apply_service_rate_limit(svc);
+
             self.svc_table.add(svc);
         }
 
@@ -967,6 +1592,61 @@ impl Config {
         }
     }
 
+    /// Add a given remotely managed static service into the service table
+    /// and return its ID.
+    #[doc(hidden)]
+    pub fn add_remote_service(&mut self, svc: Service) -> u16 {
+        let svc = self.apply_service_rate_limit(svc);
+
+        let id = self.svc_table.add_remote(svc);
+
+        self.service_table_changed();
+
+        id
+    }
+
+    /// Replace service with a given ID.
+    #[doc(hidden)]
+    pub fn update_remote_service(
+        &mut self,
+        id: u16,
+        svc: Service,
+    ) -> Result<(), ServiceTableError> {
+        let svc = self.apply_service_rate_limit(svc);
+
+        self.svc_table.update_remote(id, svc)?;
+        self.service_table_changed();
+
+        Ok(())
+    }
+
+    /// Enable or disable a given static service.
+    #[doc(hidden)]
+    pub fn set_service_enabled(&mut self, id: u16, enabled: bool) -> Result<(), ServiceTableError> {
+        self.svc_table.set_enabled(id, enabled)?;
+        self.service_table_changed();
+
+        Ok(())
+    }
+
+    /// Remove a given static service from the service table.
+    #[doc(hidden)]
+    pub fn remove_service(&mut self, id: u16) -> Result<(), ServiceTableError> {
+        self.svc_table.remove(id)?;
+        self.service_table_changed();
+
+        Ok(())
+    }
+
+    /// Increment the configuration version and save the configuration.
+    fn service_table_changed(&mut self) {
+        self.version += 1;
+
+        if let Err(err) = self.save() {
+            log_warn!(&mut self.logger, "{}", err);
+        }
+    }
+
     /// Update connection state.
     #[doc(hidden)]
     pub fn update_connection_state(&mut self, state: ConnectionState) {
@@ -1128,12 +1808,117 @@ fn parse_mjpeg_url(url: &str) -> Result<Service, ConfigError> {
     }
 }
 
+/// Get proxy from the standard proxy environment variables.
+fn proxy_from_env() -> Result<Option<Proxy>, ConfigError> {
+    let vars = ["https_proxy", "HTTPS_PROXY", "all_proxy", "ALL_PROXY"];
+
+    for var in &vars {
+        let value = match env::var(var) {
+            Ok(value) if !value.is_empty() => value,
+            _ => continue,
+        };
+
+        let proxy = Proxy::from_str(&value)
+            .map_err(|err| ConfigError::new(format!("invalid value of {}: {}", var, err)))?;
+
+        return Ok(Some(proxy));
+    }
+
+    Ok(None)
+}
+
+/// Get the list of hosts that should not be accessed through a proxy from
+/// the `no_proxy` environment variable.
+fn no_proxy_from_env() -> Vec<String> {
+    let value = env::var("no_proxy")
+        .or_else(|_| env::var("NO_PROXY"))
+        .unwrap_or_default();
+
+    parse_no_proxy(&value)
+}
+
+/// Parse a given comma separated list of hosts that should not be accessed
+/// through a proxy.
+fn parse_no_proxy(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|host| host.trim())
+        .filter(|host| !host.is_empty())
+        .map(|host| {
+            // the port number is ignored
+            let host = if host.starts_with('[') {
+                host.split(']').next().unwrap_or(host)
+            } else if host.matches(':').count() == 1 {
+                host.split(':').next().unwrap_or(host)
+            } else {
+                host
+            };
+
+            host.trim_start_matches('[')
+                .trim_start_matches("*.")
+                .trim_start_matches('.')
+                .to_lowercase()
+        })
+        .collect()
+}
+
+/// Check if a given host matches any of the `no_proxy` entries. An entry
+/// matches the host itself and all its subdomains. A single "*" matches all
+/// hosts.
+fn is_no_proxy_host(no_proxy: &[String], host: &str) -> bool {
+    let host = host.to_lowercase();
+
+    no_proxy.iter().any(|entry| {
+        entry == "*"
+            || host == *entry
+            || (host.ends_with(entry.as_str()) && host[..host.len() - entry.len()].ends_with('.'))
+    })
+}
+
+/// Parse a given Arrow Service endpoint in the "host[:port][,weight]" format.
+fn parse_arrow_service_endpoint(endpoint: &str) -> Result<ArrowServiceEndpoint, ConfigError> {
+    let (addr, weight) = match endpoint.rfind(',') {
+        Some(pos) => (&endpoint[..pos], Some(&endpoint[pos + 1..])),
+        None => (endpoint, None),
+    };
+
+    if addr.is_empty() {
+        return Err(ConfigError::new(format!(
+            "invalid Arrow Service address given: {}",
+            endpoint
+        )));
+    }
+
+    let weight = match weight {
+        Some(w) => w.parse().map_err(|_| {
+            ConfigError::new(format!(
+                "invalid Arrow Service weight given: {}, number expected",
+                endpoint
+            ))
+        })?,
+        None => 1,
+    };
+
+    // add the default port number if the given address has no port
+    let addr = if addr.ends_with(']') || !addr.contains(':') {
+        format!("{}:{}", addr, DEFAULT_ARROW_SERVICE_PORT)
+    } else {
+        addr.to_string()
+    };
+
+    Ok(ArrowServiceEndpoint::new(addr, weight))
+}
+
 /// Print usage and exit the process with a given exit code.
 #[doc(hidden)]
 pub fn usage(exit_code: i32) -> ! {
-    println!("USAGE: arrow-client arr-host[:arr-port] [OPTIONS]\n");
+    println!("USAGE: arrow-client arr-host[:arr-port][,weight] [OPTIONS]\n");
     println!("    arr-host  Angelcam Arrow Service host");
-    println!("    arr-port  Angelcam Arrow Service port\n");
+    println!("    arr-port  Angelcam Arrow Service port");
+    println!("              (use the \"wss://arr-host[:arr-port][/path]\" format to connect");
+    println!("              using the WebSocket transport)");
+    println!("    weight    number of consecutive failed connection attempts before");
+    println!("              failing over to the next Arrow Service (default value: 1)\n");
     println!("OPTIONS:\n");
     println!("    -i iface  ethernet interface used for client identification (the first");
     println!("              configured network interface is used by default)");
@@ -1179,6 +1964,18 @@ pub fn usage(exit_code: i32) -> ! {
     println!("                        10240)");
     println!("    --log-file-rotations=n  number of backup files (i.e. rotations) for the");
     println!("                        log file (default value: 1)");
+    println!("    --log-buffer-size=n  number of the most recent log messages kept in");
+    println!("                        memory for remote diagnostics (default value: 1000;");
+    println!("                        zero disables the buffer)");
+    println!("    --trace-file=path   record all messages exchanged with the Arrow Service");
+    println!("                        into a given file (use the arrow-trace tool to");
+    println!("                        print the trace)");
+    println!("    --trace-file-size=n  size limit for the trace file (in bytes; default");
+    println!("                        value: 1048576)");
+    println!("    --trace-file-rotations=n  number of backup files (i.e. rotations) for");
+    println!("                        the trace file (default value: 1)");
+    println!("    --trace-payload     include message payloads in the trace (except for");
+    println!("                        credentials and session data)");
     if cfg!(feature = "discovery") {
         println!("    --rtsp-paths=path   alternative path to a file containing list of RTSP");
         println!("                        paths used on service discovery (default value:");
@@ -1187,10 +1984,116 @@ pub fn usage(exit_code: i32) -> ! {
         println!("                        paths used on service discovery (default value:");
         println!("                        /etc/arrow/mjpeg-paths)");
     }
+    println!("    --fallback-address=host[:port][,weight]  add a fallback Arrow Service");
+    println!("                        address; fallback addresses are used in the given");
+    println!("                        order when the previous one fails (can be used");
+    println!("                        multiple times)");
+    println!("    --proxy=URL         connect to the Arrow Service through a given proxy");
+    println!("                        (supported URL schemes: http, socks5; the https_proxy");
+    println!("                        and ALL_PROXY environment variables are used by");
+    println!("                        default)");
+    println!("    --rate-limit=n      limit bandwidth used by all service connections");
+    println!("                        (in bytes per second; control messages are not");
+    println!("                        affected)");
+    println!("    --service-rate-limit=ip:port,n  limit bandwidth used by connections to");
+    println!("                        services at a given address (in bytes per second;");
+    println!("                        can be used multiple times)");
+    println!("    --ping-period=n     period of sending PING messages to the Arrow Service");
+    println!("                        (in seconds; default value: 60)");
+    if cfg!(feature = "compression") {
+        println!("    --compress=types    compress session data of given service types if");
+        println!("                        supported by the Arrow Service (comma separated");
+        println!("                        list of: rtsp, mjpeg, http, tcp; use \"none\" to");
+        println!("                        disable the compression; default value: http,tcp)");
+    }
     println!("    --lock-file=path    make sure that there is only one instance of the");
     println!("                        process running; the file will contain also PID of the");
     println!("                        process");
+    println!("    --control-socket=path  accept local control commands on a given Unix");
+    println!("                        socket (one command per line; supported commands:");
+    println!("                        \"pair\" - enter the pairing mode, \"resume\" - resume");
+    println!("                        a suspended client)");
+    println!();
+    println!("    --pairing-policy=window,fast-phase,fast-retry,slow-retry[,after-window]");
+    println!("                        set the pairing window length, length of its first");
+    println!("                        phase and retry intervals used in the first phase and");
+    println!("                        in the rest of the window while the client waits for");
+    println!("                        pairing; the client is suspended after the window");
+    println!("                        ends unless a non-zero retry interval after the");
+    println!("                        window is given (all values in seconds; default");
+    println!("                        value: 1200,600,10,30)");
+    println!();
+    println!("SIGNALS:\n");
+    println!("    SIGUSR1   enter the pairing mode (and resume the client if it was");
+    println!("              suspended because the pairing window expired)");
+    println!("    SIGTERM   close all sessions and the Arrow Service connection");
+    println!("              gracefully and exit");
     println!();
 
     process::exit(exit_code);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_proxy() {
+        let no_proxy = parse_no_proxy(" .example.com, localhost:8080,, [::1]:80, 10.0.0.1");
+
+        assert!(is_no_proxy_host(&no_proxy, "example.com"));
+        assert!(is_no_proxy_host(&no_proxy, "arrow.EXAMPLE.com"));
+        assert!(is_no_proxy_host(&no_proxy, "localhost"));
+        assert!(is_no_proxy_host(&no_proxy, "::1"));
+        assert!(is_no_proxy_host(&no_proxy, "10.0.0.1"));
+
+        assert!(!is_no_proxy_host(&no_proxy, "badexample.com"));
+        assert!(!is_no_proxy_host(&no_proxy, "10.0.0.10"));
+        assert!(!is_no_proxy_host(&no_proxy, "angelcam.com"));
+
+        assert!(is_no_proxy_host(&parse_no_proxy("*"), "angelcam.com"));
+        assert!(!is_no_proxy_host(&parse_no_proxy(""), "angelcam.com"));
+    }
+
+    #[test]
+    fn test_ping_period() {
+        let mut builder = Config::builder();
+
+        builder.ping_period(Duration::from_secs(0));
+
+        assert_eq!(builder.ping_period, MIN_PING_PERIOD);
+
+        builder.ping_period(Duration::from_millis(1500));
+
+        assert_eq!(builder.ping_period, Duration::from_millis(1500));
+    }
+
+    #[test]
+    fn test_service_rate_limit() {
+        let mut parser = ConfigParser::new();
+
+        assert!(parser
+            .service_rate_limit("--service-rate-limit=10.0.0.1:554,1000")
+            .is_ok());
+        assert!(parser
+            .service_rate_limit("--service-rate-limit=[::1]:80,2000")
+            .is_ok());
+
+        assert!(parser
+            .service_rate_limit("--service-rate-limit=10.0.0.1:554")
+            .is_err());
+        assert!(parser
+            .service_rate_limit("--service-rate-limit=10.0.0.1,1000")
+            .is_err());
+        assert!(parser
+            .service_rate_limit("--service-rate-limit=10.0.0.1:554,-1")
+            .is_err());
+
+        let expected = vec![
+            (SocketAddr::from(([10, 0, 0, 1], 554)), 1000),
+            (SocketAddr::from(([0, 0, 0, 0, 0, 0, 0, 1], 80)), 2000),
+        ];
+
+        assert_eq!(parser.service_rate_limits, expected);
+    }
+}
@@ -14,35 +14,88 @@
 
 use std::fmt;
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use futures::channel::oneshot;
+use futures::future::{FutureExt, Shared};
 
 use uuid::Uuid;
 
+use crate::client::{ArrowServiceEndpoint, PairingPolicy, ReconnectPolicy};
 use crate::config::Config;
+use crate::net::arrow::{
+    ConnectionStats, ErrorKind, MessageTracer, SessionCloseReason, SessionHandle, SessionInfo,
+};
+use crate::net::proxy::Proxy;
 use crate::net::raw::ether::MacAddr;
 use crate::net::tls::TlsConnector;
 use crate::scanner::ScanResult;
-use crate::svc_table::{Service, SharedServiceTableRef};
+use crate::svc_table::{Service, ServiceTableError, ServiceType, SharedServiceTableRef};
+use crate::utils::logger::ring::LogBuffer;
 use crate::utils::logger::BoxLogger;
 use crate::utils::RuntimeError;
 
+/// Reason for suspending the Arrow Service connection.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SuspendReason {
+    /// The client has not been paired within the pairing window.
+    NotInPairingMode,
+    /// The Arrow Service does not support this version of the Arrow Protocol.
+    UnsupportedProtocolVersion,
+}
+
+impl SuspendReason {
+    /// Get string representation of the reason.
+    fn as_str(&self) -> &str {
+        match &self {
+            Self::NotInPairingMode => "pairing window timeout",
+            Self::UnsupportedProtocolVersion => "unsupported protocol version",
+        }
+    }
+}
+
+impl Display for SuspendReason {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        f.write_str(self.as_str())
+    }
+}
+
 /// Arrow service connection state.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum ConnectionState {
+    /// The client is establishing a (TLS) connection to the Arrow Service.
+    Connecting,
+    /// The connection has been established and the client is waiting for the
+    /// REGISTER ACK.
+    Handshaking,
+    /// The client has been registered by the Arrow Service.
     Connected,
-    Disconnected,
+    /// The client has been redirected to another Arrow Service.
+    Redirecting,
+    /// The client is not connected. The kind of the last connection error is
+    /// included (if any).
+    Disconnected(Option<ErrorKind>),
+    /// The client has been rejected by the Arrow Service.
     Unauthorized,
+    /// The client will not try to connect again.
+    Suspended(SuspendReason),
 }
 
 impl ConnectionState {
     /// Get string representation of the state.
     fn as_str(&self) -> &str {
         match &self {
+            Self::Connecting => "connecting",
+            Self::Handshaking => "handshaking",
             Self::Connected => "connected",
-            Self::Disconnected => "disconnected",
+            Self::Redirecting => "redirecting",
+            Self::Disconnected(_) => "disconnected",
             Self::Unauthorized => "unauthorized",
+            Self::Suspended(_) => "suspended",
         }
     }
 }
@@ -53,6 +106,10 @@ impl Display for ConnectionState {
     }
 }
 
+/// Type alias. The signal resolves once a graceful shutdown of the client
+/// has been requested.
+pub type ShutdownSignal = Shared<oneshot::Receiver<()>>;
+
 /// Listener for application events.
 pub trait ApplicationEventListener {
     /// Report new connection state.
@@ -60,27 +117,55 @@ pub trait ApplicationEventListener {
 
     /// Report new network scanner state (`true` means that the network scanner is running).
     fn network_scanner_state_changed(&mut self, _: bool) {}
+
+    /// Report address of the Arrow Service the client is connected to (`None` means that the
+    /// client is not connected).
+    fn active_arrow_service_changed(&mut self, _: Option<&str>) {}
+
+    /// Report a new service session.
+    fn session_opened(&mut self, _: &SessionInfo) {}
+
+    /// Report a closed service session together with the reason why it was closed.
+    fn session_closed(&mut self, _: &SessionInfo, _: SessionCloseReason) {}
 }
 
 /// Internal data of the application context.
 struct ApplicationContextData {
     logger: BoxLogger,
     config: Config,
+    started: Instant,
     scanning: bool,
+    scan_started: Option<Instant>,
+    last_scan: Option<(SystemTime, Duration)>,
     scan_result: ScanResult,
     connection_state: ConnectionState,
+    active_arrow_service: Option<String>,
+    connection_stats: ConnectionStats,
+    sessions: HashMap<u32, SessionHandle>,
+    shutdown: Option<oneshot::Sender<()>>,
+    shutdown_signal: ShutdownSignal,
     event_listeners: Vec<Box<dyn ApplicationEventListener + Send>>,
 }
 
 impl ApplicationContextData {
     /// Take a given application config and create application context data.
     fn new(config: Config) -> Self {
+        let (shutdown, shutdown_signal) = oneshot::channel();
+
         Self {
             logger: config.get_logger(),
             config,
+            started: Instant::now(),
             scanning: false,
+            scan_started: None,
+            last_scan: None,
             scan_result: ScanResult::new(),
-            connection_state: ConnectionState::Disconnected,
+            connection_state: ConnectionState::Disconnected(None),
+            active_arrow_service: None,
+            connection_stats: ConnectionStats::new(),
+            sessions: HashMap::new(),
+            shutdown: Some(shutdown),
+            shutdown_signal: shutdown_signal.shared(),
             event_listeners: Vec::new(),
         }
     }
@@ -100,9 +185,25 @@ impl ApplicationContextData {
         self.logger.clone()
     }
 
+    /// Get time elapsed since the application context was created.
+    fn get_uptime(&self) -> Duration {
+        self.started.elapsed()
+    }
+
     /// Set the state of the network scanner thread.
     fn set_scanning(&mut self, scanning: bool) {
         self.scanning = scanning;
+
+        if scanning {
+            self.scan_started = Some(Instant::now());
+        } else if let Some(started) = self.scan_started.take() {
+            self.last_scan = Some((SystemTime::now(), started.elapsed()));
+        }
+    }
+
+    /// Get time when the last network scan finished and its duration.
+    fn get_last_scan(&self) -> Option<(SystemTime, Duration)> {
+        self.last_scan
     }
 
     /// Check if the network scanner thread is running right now.
@@ -132,6 +233,43 @@ impl ApplicationContextData {
         self.config.update_connection_state(state);
     }
 
+    /// Get address of the active Arrow Service.
+    fn get_active_arrow_service(&self) -> Option<String> {
+        self.active_arrow_service.clone()
+    }
+
+    /// Set address of the active Arrow Service.
+    fn set_active_arrow_service(&mut self, addr: Option<String>) {
+        self.connection_stats.set_connected(addr.is_some());
+
+        self.active_arrow_service = addr;
+    }
+
+    /// Get connection statistics.
+    fn get_connection_stats(&self) -> &ConnectionStats {
+        &self.connection_stats
+    }
+
+    /// Get connection statistics.
+    fn get_connection_stats_mut(&mut self) -> &mut ConnectionStats {
+        &mut self.connection_stats
+    }
+
+    /// Get handles of all active sessions.
+    fn get_sessions(&self) -> Vec<SessionHandle> {
+        self.sessions.values().cloned().collect()
+    }
+
+    /// Add a given active session.
+    fn add_session(&mut self, session_id: u32, session: SessionHandle) {
+        self.sessions.insert(session_id, session);
+    }
+
+    /// Remove a given session.
+    fn remove_session(&mut self, session_id: u32) {
+        self.sessions.remove(&session_id);
+    }
+
     /// Add a new event listener.
     fn add_event_listener<T>(&mut self, listener: T)
     where
@@ -152,6 +290,25 @@ impl ApplicationContextData {
         std::mem::replace(&mut self.event_listeners, listeners);
     }
 
+    /// Get the shutdown signal.
+    fn get_shutdown_signal(&self) -> ShutdownSignal {
+        self.shutdown_signal.clone()
+    }
+
+    /// Check if a graceful shutdown has been requested.
+    fn is_shutting_down(&self) -> bool {
+        self.shutdown.is_none()
+    }
+
+    /// Request a graceful shutdown.
+    fn shutdown(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            // the signal receiver is owned by this object, so it cannot be
+            // gone
+            let _ = shutdown.send(());
+        }
+    }
+
     /// Get all event listeners.
     fn take_event_listeners(&mut self) -> Vec<Box<dyn ApplicationEventListener + Send>> {
         std::mem::replace(&mut self.event_listeners, Vec::new())
@@ -172,14 +329,14 @@ impl ApplicationContext {
         }
     }
 
-    /// Get address of the remote Arrow Service.
-    pub fn get_arrow_service_address(&self) -> String {
+    /// Get an ordered list of remote Arrow Service endpoints.
+    pub fn get_arrow_service_endpoints(&self) -> Vec<ArrowServiceEndpoint> {
         self.data
             .lock()
             .unwrap()
             .get_config()
-            .get_arrow_service_address()
-            .to_string()
+            .get_arrow_service_endpoints()
+            .to_vec()
     }
 
     /// Get Arrow Client UUID.
@@ -226,11 +383,90 @@ impl ApplicationContext {
         self.data.lock().unwrap().get_config().get_mjpeg_paths()
     }
 
+    /// Get policy for reconnecting to the Arrow Service.
+    pub fn get_reconnect_policy(&self) -> ReconnectPolicy {
+        self.data
+            .lock()
+            .unwrap()
+            .get_config()
+            .get_reconnect_policy()
+    }
+
+    /// Get policy for reconnecting to the Arrow Service while the client
+    /// waits for pairing.
+    pub fn get_pairing_policy(&self) -> PairingPolicy {
+        self.data.lock().unwrap().get_config().get_pairing_policy()
+    }
+
+    /// Get maximum number of concurrent service sessions.
+    pub fn get_max_sessions(&self) -> Option<usize> {
+        self.data.lock().unwrap().get_config().get_max_sessions()
+    }
+
+    /// Get session idle timeout.
+    pub fn get_session_idle_timeout(&self) -> Option<Duration> {
+        self.data
+            .lock()
+            .unwrap()
+            .get_config()
+            .get_session_idle_timeout()
+    }
+
+    /// Get period of sending PING messages to the Arrow Service.
+    pub fn get_ping_period(&self) -> Duration {
+        self.data.lock().unwrap().get_config().get_ping_period()
+    }
+
+    /// Get global bandwidth limit for service connections.
+    pub fn get_rate_limit(&self) -> Option<u32> {
+        self.data.lock().unwrap().get_config().get_rate_limit()
+    }
+
+    /// Get path of the control socket (if any).
+    pub fn get_control_socket(&self) -> Option<PathBuf> {
+        self.data
+            .lock()
+            .unwrap()
+            .get_config()
+            .get_control_socket()
+            .map(PathBuf::from)
+    }
+
     /// Get application logger.
     pub fn get_logger(&self) -> BoxLogger {
         self.data.lock().unwrap().get_logger()
     }
 
+    /// Get buffer containing the most recent log entries.
+    pub fn get_log_buffer(&self) -> LogBuffer {
+        self.data.lock().unwrap().get_config().get_log_buffer()
+    }
+
+    /// Get service types whose session data will be compressed.
+    pub fn get_compressed_service_types(&self) -> Arc<HashSet<ServiceType>> {
+        self.data
+            .lock()
+            .unwrap()
+            .get_config()
+            .get_compressed_service_types()
+    }
+
+    /// Get the Arrow message tracer (if any).
+    pub fn get_message_tracer(&self) -> Option<MessageTracer> {
+        self.data.lock().unwrap().get_config().get_message_tracer()
+    }
+
+    /// Get proxy for connecting to a given Arrow Service address (in the
+    /// "host:port" format).
+    pub fn get_proxy(&self, addr: &str) -> Option<Proxy> {
+        self.data
+            .lock()
+            .unwrap()
+            .get_config()
+            .get_proxy(addr)
+            .cloned()
+    }
+
     /// Get TLS connector for a given server hostname.
     pub fn get_tls_connector(&self) -> Result<TlsConnector, RuntimeError> {
         self.data
@@ -240,6 +476,11 @@ impl ApplicationContext {
             .get_tls_connector()
     }
 
+    /// Get time elapsed since the client was created.
+    pub fn get_uptime(&self) -> Duration {
+        self.data.lock().unwrap().get_uptime()
+    }
+
     /// Set the state of the network scanner thread.
     pub fn set_scanning(&mut self, scanning: bool) {
         let mut data = self.data.lock().unwrap();
@@ -267,6 +508,11 @@ impl ApplicationContext {
         self.data.lock().unwrap().is_scanning()
     }
 
+    /// Get time when the last network scan finished and its duration.
+    pub fn get_last_scan(&self) -> Option<(SystemTime, Duration)> {
+        self.data.lock().unwrap().get_last_scan()
+    }
+
     /// Get the last scan result.
     pub fn get_scan_result(&self) -> ScanResult {
         self.data.lock().unwrap().get_scan_result()
@@ -294,6 +540,47 @@ impl ApplicationContext {
             .update_service_table(services)
     }
 
+    /// Add a given remotely managed static service into the service table
+    /// and return its ID.
+    pub fn add_remote_service(&mut self, svc: Service) -> u16 {
+        self.data
+            .lock()
+            .unwrap()
+            .get_config_mut()
+            .add_remote_service(svc)
+    }
+
+    /// Replace service with a given ID.
+    pub fn update_remote_service(
+        &mut self,
+        id: u16,
+        svc: Service,
+    ) -> Result<(), ServiceTableError> {
+        self.data
+            .lock()
+            .unwrap()
+            .get_config_mut()
+            .update_remote_service(id, svc)
+    }
+
+    /// Enable or disable a given static service.
+    pub fn set_service_enabled(&mut self, id: u16, enabled: bool) -> Result<(), ServiceTableError> {
+        self.data
+            .lock()
+            .unwrap()
+            .get_config_mut()
+            .set_service_enabled(id, enabled)
+    }
+
+    /// Remove a given static service from the service table.
+    pub fn remove_service(&mut self, id: u16) -> Result<(), ServiceTableError> {
+        self.data
+            .lock()
+            .unwrap()
+            .get_config_mut()
+            .remove_service(id)
+    }
+
     /// Reset service table.
     pub fn reset_service_table(&mut self) {
         self.data
@@ -330,6 +617,146 @@ impl ApplicationContext {
         self.data.lock().unwrap().add_event_listeners(listeners);
     }
 
+    /// Get a signal that resolves once a graceful shutdown has been
+    /// requested.
+    pub fn get_shutdown_signal(&self) -> ShutdownSignal {
+        self.data.lock().unwrap().get_shutdown_signal()
+    }
+
+    /// Check if a graceful shutdown has been requested.
+    pub fn is_shutting_down(&self) -> bool {
+        self.data.lock().unwrap().is_shutting_down()
+    }
+
+    /// Request a graceful shutdown of all client tasks.
+    pub fn shutdown(&mut self) {
+        self.data.lock().unwrap().shutdown()
+    }
+
+    /// Get address of the Arrow Service the client is connected to.
+    pub fn get_active_arrow_service(&self) -> Option<String> {
+        self.data.lock().unwrap().get_active_arrow_service()
+    }
+
+    /// Set address of the Arrow Service the client is connected to.
+    pub fn set_active_arrow_service(&mut self, addr: Option<String>) {
+        let mut data = self.data.lock().unwrap();
+
+        if addr == data.get_active_arrow_service() {
+            return;
+        }
+
+        data.set_active_arrow_service(addr.clone());
+
+        let mut listeners = data.take_event_listeners();
+
+        // make sure that we are not holding the mutex
+        std::mem::drop(data);
+
+        for listener in &mut listeners {
+            listener.active_arrow_service_changed(addr.as_deref());
+        }
+
+        self.data.lock().unwrap().add_event_listeners(listeners);
+    }
+
+    /// Get Arrow Service connection statistics.
+    pub fn get_connection_stats(&self) -> ConnectionStats {
+        self.data.lock().unwrap().get_connection_stats().clone()
+    }
+
+    /// Report a new Arrow Service connection attempt.
+    pub fn connection_attempt(&mut self) {
+        self.data
+            .lock()
+            .unwrap()
+            .get_connection_stats_mut()
+            .connection_attempt()
+    }
+
+    /// Add a given round-trip time sample.
+    pub fn add_rtt_sample(&mut self, rtt: Duration) {
+        self.data
+            .lock()
+            .unwrap()
+            .get_connection_stats_mut()
+            .add_rtt_sample(rtt)
+    }
+
+    /// Update the reconnect backoff state.
+    pub fn set_backoff_state(&mut self, failures: u32, retry_delay: Option<Duration>) {
+        self.data
+            .lock()
+            .unwrap()
+            .get_connection_stats_mut()
+            .set_backoff(failures, retry_delay)
+    }
+
+    /// Add given numbers of bytes sent to and received from the Arrow
+    /// Service.
+    pub fn add_transferred_bytes(&mut self, sent: u64, received: u64) {
+        self.data
+            .lock()
+            .unwrap()
+            .get_connection_stats_mut()
+            .add_transferred_bytes(sent, received)
+    }
+
+    /// Get information about all active service sessions (ordered by session
+    /// ID).
+    pub fn get_sessions(&self) -> Vec<SessionInfo> {
+        let sessions = self.data.lock().unwrap().get_sessions();
+
+        // the session contexts are locked separately, so we should not be
+        // holding the application context mutex here
+        let mut res = sessions
+            .iter()
+            .map(|session| session.info())
+            .collect::<Vec<_>>();
+
+        res.sort_by_key(|info| info.session_id());
+
+        res
+    }
+
+    /// Register a new active service session and notify all event listeners.
+    pub fn session_opened(&mut self, session: SessionHandle) {
+        let info = session.info();
+
+        let mut data = self.data.lock().unwrap();
+
+        data.add_session(info.session_id(), session);
+
+        let mut listeners = data.take_event_listeners();
+
+        // make sure that we are not holding the mutex
+        std::mem::drop(data);
+
+        for listener in &mut listeners {
+            listener.session_opened(&info);
+        }
+
+        self.data.lock().unwrap().add_event_listeners(listeners);
+    }
+
+    /// Unregister a closed service session and notify all event listeners.
+    pub fn session_closed(&mut self, session: &SessionInfo, reason: SessionCloseReason) {
+        let mut data = self.data.lock().unwrap();
+
+        data.remove_session(session.session_id());
+
+        let mut listeners = data.take_event_listeners();
+
+        // make sure that we are not holding the mutex
+        std::mem::drop(data);
+
+        for listener in &mut listeners {
+            listener.session_closed(session, reason);
+        }
+
+        self.data.lock().unwrap().add_event_listeners(listeners);
+    }
+
     /// Add a new event listener.
     pub fn add_event_listener<T>(&mut self, listener: T)
     where
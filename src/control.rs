@@ -0,0 +1,176 @@
+// Copyright 2020 Angelcam, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Local control socket. The socket accepts simple text commands (one
+//! command per line) and it responds with either "OK" or "ERROR <reason>".
+
+use std::fs;
+use std::io;
+
+use std::fs::Permissions;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+
+use crate::client::{MainTaskChannel, MainTaskCommand};
+use crate::context::ApplicationContext;
+use crate::utils::logger::Logger;
+
+/// Parse a given control command.
+fn parse_command(cmd: &str) -> Option<MainTaskCommand> {
+    match cmd {
+        "pair" => Some(MainTaskCommand::EnterPairingMode),
+        "resume" => Some(MainTaskCommand::Resume),
+        _ => None,
+    }
+}
+
+/// Handle a given control connection.
+async fn handle_connection(
+    mut stream: UnixStream,
+    channel: MainTaskChannel,
+) -> Result<(), io::Error> {
+    let (reader, mut writer) = stream.split();
+
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        let cmd = line.trim();
+
+        if cmd.is_empty() {
+            continue;
+        }
+
+        let response = if let Some(cmd) = parse_command(cmd) {
+            channel.send(cmd);
+
+            String::from("OK\n")
+        } else {
+            format!("ERROR unknown command: {}\n", cmd)
+        };
+
+        writer.write_all(response.as_bytes()).await?;
+    }
+
+    Ok(())
+}
+
+/// Remove a socket file left behind by a previous instance (if any). Files
+/// other than sockets are never removed.
+fn remove_stale_socket(path: &Path) -> Result<(), io::Error> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => fs::remove_file(path),
+        Ok(_) => Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "the file exists and it is not a socket",
+        )),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Listen for control commands on a given Unix socket and pass them to the
+/// Arrow client main task.
+pub async fn serve(app_context: ApplicationContext, path: PathBuf, channel: MainTaskChannel) {
+    let mut logger = app_context.get_logger();
+
+    let listener = remove_stale_socket(&path)
+        .and_then(|_| UnixListener::bind(&path))
+        .and_then(|listener| {
+            // only the owner is allowed to control the client
+            fs::set_permissions(&path, Permissions::from_mode(0o600))?;
+
+            Ok(listener)
+        });
+
+    let mut listener = match listener {
+        Ok(listener) => listener,
+        Err(err) => {
+            log_warn!(
+                &mut logger,
+                "unable to bind the control socket {}: {}",
+                path.to_string_lossy(),
+                err
+            );
+
+            return;
+        }
+    };
+
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let mut logger = logger.clone();
+                let channel = channel.clone();
+
+                tokio::spawn(async move {
+                    if let Err(err) = handle_connection(stream, channel).await {
+                        log_debug!(&mut logger, "control connection error: {}", err);
+                    }
+                });
+            }
+            Err(err) => {
+                log_warn!(
+                    &mut logger,
+                    "unable to accept a control connection: {}",
+                    err
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(
+            parse_command("pair"),
+            Some(MainTaskCommand::EnterPairingMode)
+        );
+        assert_eq!(parse_command("resume"), Some(MainTaskCommand::Resume));
+        assert_eq!(parse_command("foo"), None);
+    }
+
+    #[test]
+    fn test_remove_stale_socket() {
+        let dir = std::env::temp_dir().join(format!("arrow-control-test-{}", std::process::id()));
+
+        fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("control.sock");
+
+        // there is nothing to remove
+        assert!(remove_stale_socket(&path).is_ok());
+
+        // regular files must be kept
+        fs::write(&path, "data").unwrap();
+
+        assert!(remove_stale_socket(&path).is_err());
+        assert!(path.exists());
+
+        fs::remove_file(&path).unwrap();
+
+        std::os::unix::net::UnixListener::bind(&path).unwrap();
+
+        assert!(remove_stale_socket(&path).is_ok());
+        assert!(!path.exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
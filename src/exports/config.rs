@@ -16,9 +16,14 @@
 
 use std::slice;
 
-use libc::c_int;
+use std::ffi::CStr;
+use std::str::FromStr;
+use std::time::Duration;
 
-use crate::config::{Config, ConfigBuilder};
+use libc::{c_char, c_double, c_int, size_t};
+
+use crate::config::{Config, ConfigBuilder, MessageTracer, PairingPolicy, Proxy, ReconnectPolicy};
+use crate::exports::{cstr_to_str, optional_cstr_to_str};
 use crate::net::raw::ether::MacAddr;
 use crate::utils::logger::BoxLogger;
 
@@ -83,3 +88,186 @@ pub unsafe extern "C" fn ac__config__set_discovery(config: *mut ConfigBuilder, e
 pub unsafe extern "C" fn ac__config__set_verbose(config: *mut ConfigBuilder, enabled: c_int) {
     (&mut *config).verbose(enabled != 0);
 }
+
+/// Set weight of the primary Arrow Service address.
+#[no_mangle]
+pub unsafe extern "C" fn ac__config__set_arrow_service_weight(
+    config: *mut ConfigBuilder,
+    weight: u32,
+) {
+    (&mut *config).arrow_service_weight(weight);
+}
+
+/// Add a fallback Arrow Service address with a given weight.
+#[no_mangle]
+pub unsafe extern "C" fn ac__config__add_arrow_service_address(
+    config: *mut ConfigBuilder,
+    addr: *const c_char,
+    weight: u32,
+) {
+    let addr = CStr::from_ptr(addr);
+
+    (&mut *config).add_arrow_service_address(addr.to_string_lossy(), weight);
+}
+
+/// Set proxy for connecting to the Arrow Service. The proxy is expected to be
+/// given as a URL (supported schemes are "http" and "socks5") or NULL. The
+/// function returns zero on success and a negative value if the URL is not
+/// valid.
+#[no_mangle]
+pub unsafe extern "C" fn ac__config__set_proxy(
+    config: *mut ConfigBuilder,
+    proxy: *const c_char,
+) -> c_int {
+    let proxy = if proxy.is_null() {
+        None
+    } else {
+        let proxy = CStr::from_ptr(proxy).to_string_lossy();
+
+        match Proxy::from_str(&proxy) {
+            Ok(proxy) => Some(proxy),
+            Err(_) => return -1,
+        }
+    };
+
+    (&mut *config).proxy(proxy);
+
+    0
+}
+
+/// Set policy for reconnecting to the Arrow Service. All durations are in
+/// milliseconds. The jitter is expected to be a number between 0 and 1.
+#[no_mangle]
+pub unsafe extern "C" fn ac__config__set_reconnect_policy(
+    config: *mut ConfigBuilder,
+    initial_delay: u32,
+    max_delay: u32,
+    jitter: c_double,
+    reset_after: u32,
+) {
+    let mut policy = ReconnectPolicy::new();
+
+    policy
+        .initial_delay(Duration::from_millis(u64::from(initial_delay)))
+        .max_delay(Duration::from_millis(u64::from(max_delay)))
+        .jitter(jitter)
+        .reset_after(Duration::from_millis(u64::from(reset_after)));
+
+    (&mut *config).reconnect_policy(policy);
+}
+
+/// Set policy for reconnecting to the Arrow Service while the client waits
+/// for pairing. All durations are in milliseconds. Zero retry interval after
+/// the pairing window means that the client will be suspended. The fast and
+/// slow retry intervals must not be zero.
+#[no_mangle]
+pub unsafe extern "C" fn ac__config__set_pairing_policy(
+    config: *mut ConfigBuilder,
+    window: u32,
+    fast_phase: u32,
+    fast_retry_interval: u32,
+    slow_retry_interval: u32,
+    retry_after_window: u32,
+) {
+    let retry_after_window = if retry_after_window == 0 {
+        None
+    } else {
+        Some(Duration::from_millis(u64::from(retry_after_window)))
+    };
+
+    let mut policy = PairingPolicy::new();
+
+    policy
+        .window(Duration::from_millis(u64::from(window)))
+        .fast_phase(Duration::from_millis(u64::from(fast_phase)))
+        .fast_retry_interval(Duration::from_millis(u64::from(fast_retry_interval)))
+        .slow_retry_interval(Duration::from_millis(u64::from(slow_retry_interval)))
+        .retry_after_window(retry_after_window);
+
+    (&mut *config).pairing_policy(policy);
+}
+
+/// Set maximum number of concurrent service sessions. Zero means unlimited.
+#[no_mangle]
+pub unsafe extern "C" fn ac__config__set_max_sessions(config: *mut ConfigBuilder, max: u32) {
+    let max = if max == 0 { None } else { Some(max as usize) };
+
+    (&mut *config).max_sessions(max);
+}
+
+/// Set session idle timeout in milliseconds. Zero means no timeout.
+#[no_mangle]
+pub unsafe extern "C" fn ac__config__set_session_idle_timeout(
+    config: *mut ConfigBuilder,
+    timeout: u32,
+) {
+    let timeout = if timeout == 0 {
+        None
+    } else {
+        Some(Duration::from_millis(u64::from(timeout)))
+    };
+
+    (&mut *config).session_idle_timeout(timeout);
+}
+
+/// Set global bandwidth limit in bytes per second. Zero means no limit.
+#[no_mangle]
+pub unsafe extern "C" fn ac__config__set_rate_limit(config: *mut ConfigBuilder, limit: u32) {
+    let limit = if limit == 0 { None } else { Some(limit) };
+
+    (&mut *config).rate_limit(limit);
+}
+
+/// Set period of sending PING messages to the Arrow Service (in milliseconds).
+/// Periods shorter than one second are rounded up to one second.
+#[no_mangle]
+pub unsafe extern "C" fn ac__config__set_ping_period(config: *mut ConfigBuilder, period: u32) {
+    (&mut *config).ping_period(Duration::from_millis(period as u64));
+}
+
+/// Set number of the most recent log entries kept in memory. Zero disables the
+/// in-memory log.
+#[no_mangle]
+pub unsafe extern "C" fn ac__config__set_log_buffer_size(config: *mut ConfigBuilder, size: u32) {
+    (&mut *config).log_buffer_size(size as usize);
+}
+
+/// Record all messages exchanged with the Arrow Service into a given file. The
+/// file will be rotated once it reaches a given size limit. Message payloads
+/// are recorded only if the `payload` flag is non-zero (payloads of REGISTER
+/// messages and session data are never recorded). NULL path disables the
+/// tracing (default). The function returns zero on success and a negative
+/// value if the trace file cannot be open.
+#[no_mangle]
+pub unsafe extern "C" fn ac__config__set_message_tracer(
+    config: *mut ConfigBuilder,
+    path: *const c_char,
+    limit: size_t,
+    rotations: size_t,
+    payload: c_int,
+) -> c_int {
+    let tracer = if path.is_null() {
+        None
+    } else {
+        let path = cstr_to_str(path);
+
+        match MessageTracer::new(path, limit as _, rotations as _, payload != 0) {
+            Ok(tracer) => Some(tracer),
+            Err(_) => return -1,
+        }
+    };
+
+    (&mut *config).message_tracer(tracer);
+
+    0
+}
+
+/// Set path of a Unix socket accepting local control commands. NULL means no
+/// control socket (default).
+#[no_mangle]
+pub unsafe extern "C" fn ac__config__set_control_socket(
+    config: *mut ConfigBuilder,
+    path: *const c_char,
+) {
+    (&mut *config).control_socket(optional_cstr_to_str(path));
+}
@@ -17,6 +17,8 @@
 pub mod config;
 pub mod logger;
 pub mod mem;
+pub mod session;
+pub mod stats;
 pub mod storage;
 pub mod svc_table;
 
@@ -25,8 +27,9 @@ use std::slice;
 use std::str;
 use std::thread;
 
-use std::ffi::CStr;
+use std::ffi::{CStr, CString};
 use std::thread::JoinHandle;
+use std::time::Duration;
 
 use libc::{c_char, c_int, c_void};
 
@@ -34,9 +37,14 @@ use crate::runtime;
 
 use crate::client::{ArrowClient, ArrowClientTask};
 use crate::config::ConfigBuilder;
+use crate::exports::session::{NativeSession, NativeSessionList};
 use crate::exports::storage::DynStorage;
 use crate::exports::svc_table::NativeServiceTable;
-use crate::{ArrowClientEventListener, ConnectionState};
+use crate::utils::logger::{BoxLogger, Logger};
+use crate::{
+    ArrowClientEventListener, ConnectionState, ConnectionStats, ErrorKind, SessionCloseReason,
+    SessionInfo, SuspendReason,
+};
 
 /// Helper function.
 unsafe fn optional_cstr_to_str<'a>(s: *const c_char) -> Option<&'a str> {
@@ -59,9 +67,48 @@ unsafe fn cstr_to_str<'a>(s: *const c_char) -> &'a str {
 /// Helper function.
 fn connection_state_to_c_int(state: ConnectionState) -> c_int {
     match state {
-        ConnectionState::Disconnected => 0,
+        ConnectionState::Disconnected(_) => 0,
         ConnectionState::Connected => 1,
         ConnectionState::Unauthorized => 2,
+        ConnectionState::Connecting => 3,
+        ConnectionState::Handshaking => 4,
+        ConnectionState::Redirecting => 5,
+        ConnectionState::Suspended(_) => 6,
+    }
+}
+
+/// Helper function. The detail is the kind of the last connection error for
+/// the disconnected state and the suspend reason for the suspended state.
+fn connection_state_detail_to_c_int(state: ConnectionState) -> c_int {
+    match state {
+        ConnectionState::Disconnected(Some(kind)) => match kind {
+            ErrorKind::ConnectionError => 1,
+            ErrorKind::UnsupportedProtocolVersion => 2,
+            ErrorKind::Unauthorized => 3,
+            ErrorKind::ArrowServerError => 4,
+            ErrorKind::Other => 5,
+        },
+        ConnectionState::Suspended(reason) => match reason {
+            SuspendReason::NotInPairingMode => 0,
+            SuspendReason::UnsupportedProtocolVersion => 1,
+        },
+        _ => 0,
+    }
+}
+
+/// Helper function.
+fn session_close_reason_to_c_int(reason: SessionCloseReason) -> c_int {
+    match reason {
+        SessionCloseReason::Closed => 0,
+        SessionCloseReason::Reset => 1,
+        SessionCloseReason::Refused => 2,
+        SessionCloseReason::Timeout => 3,
+        SessionCloseReason::BufferOverflow => 4,
+        SessionCloseReason::IdleTimeout => 5,
+        SessionCloseReason::Hangup(_) => 6,
+        SessionCloseReason::Disconnected => 7,
+        SessionCloseReason::Error => 8,
+        SessionCloseReason::Shutdown => 9,
     }
 }
 
@@ -89,6 +136,37 @@ impl ArrowClientEventListener for ConnectionStateListener {
 
 unsafe impl Send for ConnectionStateListener {}
 
+/// Type alias.
+type ConnectionStateDetailCallback =
+    unsafe extern "C" fn(opaque: *mut c_void, state: c_int, detail: c_int);
+
+/// Helper struct.
+struct ConnectionStateDetailListener {
+    callback: ConnectionStateDetailCallback,
+    opaque: *mut c_void,
+}
+
+impl ConnectionStateDetailListener {
+    /// Create a new connection state listener.
+    fn new(opaque: *mut c_void, callback: ConnectionStateDetailCallback) -> Self {
+        Self { opaque, callback }
+    }
+}
+
+impl ArrowClientEventListener for ConnectionStateDetailListener {
+    fn connection_state_changed(&mut self, state: ConnectionState) {
+        unsafe {
+            (self.callback)(
+                self.opaque,
+                connection_state_to_c_int(state),
+                connection_state_detail_to_c_int(state),
+            )
+        }
+    }
+}
+
+unsafe impl Send for ConnectionStateDetailListener {}
+
 /// Type alias.
 type NetworkScannerStateCallback = unsafe extern "C" fn(opaque: *mut c_void, scanning: c_int);
 
@@ -113,6 +191,119 @@ impl ArrowClientEventListener for NetworkScannerStateListener {
 
 unsafe impl Send for NetworkScannerStateListener {}
 
+/// Type alias.
+type ActiveArrowServiceCallback = unsafe extern "C" fn(opaque: *mut c_void, addr: *const c_char);
+
+/// Helper struct.
+struct ActiveArrowServiceListener {
+    callback: ActiveArrowServiceCallback,
+    opaque: *mut c_void,
+    logger: BoxLogger,
+}
+
+impl ActiveArrowServiceListener {
+    /// Create a new active Arrow Service listener.
+    fn new(opaque: *mut c_void, callback: ActiveArrowServiceCallback, logger: BoxLogger) -> Self {
+        Self {
+            opaque,
+            callback,
+            logger,
+        }
+    }
+}
+
+impl ArrowClientEventListener for ActiveArrowServiceListener {
+    fn active_arrow_service_changed(&mut self, addr: Option<&str>) {
+        // the address cannot be passed as a C string if it contains a NUL
+        // character, the callback is not invoked in such case
+        let addr = match addr.map(CString::new).transpose() {
+            Ok(addr) => addr,
+            Err(_) => {
+                log_warn!(
+                    self.logger,
+                    "unable to report the active Arrow Service, the address contains a NUL character"
+                );
+
+                return;
+            }
+        };
+
+        let ptr = addr
+            .as_ref()
+            .map(|addr| addr.as_ptr())
+            .unwrap_or_else(ptr::null);
+
+        unsafe { (self.callback)(self.opaque, ptr) }
+    }
+}
+
+unsafe impl Send for ActiveArrowServiceListener {}
+
+/// Type alias.
+type SessionOpenedCallback =
+    unsafe extern "C" fn(opaque: *mut c_void, session: *const NativeSession);
+
+/// Helper struct.
+struct SessionOpenedListener {
+    callback: SessionOpenedCallback,
+    opaque: *mut c_void,
+}
+
+impl SessionOpenedListener {
+    /// Create a new session opened listener.
+    fn new(opaque: *mut c_void, callback: SessionOpenedCallback) -> Self {
+        Self { opaque, callback }
+    }
+}
+
+impl ArrowClientEventListener for SessionOpenedListener {
+    fn session_opened(&mut self, session: &SessionInfo) {
+        let session = NativeSession::from(session.clone());
+
+        unsafe { (self.callback)(self.opaque, &session) }
+    }
+}
+
+unsafe impl Send for SessionOpenedListener {}
+
+/// Type alias.
+type SessionClosedCallback = unsafe extern "C" fn(
+    opaque: *mut c_void,
+    session: *const NativeSession,
+    reason: c_int,
+    error_code: u32,
+);
+
+/// Helper struct.
+struct SessionClosedListener {
+    callback: SessionClosedCallback,
+    opaque: *mut c_void,
+}
+
+impl SessionClosedListener {
+    /// Create a new session closed listener.
+    fn new(opaque: *mut c_void, callback: SessionClosedCallback) -> Self {
+        Self { opaque, callback }
+    }
+}
+
+impl ArrowClientEventListener for SessionClosedListener {
+    fn session_closed(&mut self, session: &SessionInfo, reason: SessionCloseReason) {
+        let session = NativeSession::from(session.clone());
+
+        unsafe {
+            (self.callback)(
+                self.opaque,
+                &session,
+                session_close_reason_to_c_int(reason),
+                reason.error_code(),
+            )
+        }
+    }
+}
+
+unsafe impl Send for SessionClosedListener {}
+
 /// Helper struct.
 pub struct NativeArrowClient {
     client: ArrowClient,
@@ -186,6 +377,18 @@ pub unsafe extern "C" fn ac__arrow_client__close(client: *mut NativeArrowClient)
     (*client).client.close();
 }
 
+/// Shut down a given Arrow client gracefully. All active sessions will be
+/// closed, the Arrow Service will be notified about them and the connection
+/// will be closed. The function blocks until the shutdown completes or until
+/// a given timeout (in milliseconds) expires. The client is closed in both
+/// cases. The function must not be called from any of the client callbacks.
+#[no_mangle]
+pub unsafe extern "C" fn ac__arrow_client__shutdown(client: *mut NativeArrowClient, timeout: u32) {
+    let client = &mut (*client).client;
+
+    client.shutdown_blocking(Duration::from_millis(timeout as u64));
+}
+
 /// Add a given connection state callback.
 #[no_mangle]
 pub unsafe extern "C" fn ac__arrow_client__add_connection_state_callback(
@@ -198,6 +401,20 @@ pub unsafe extern "C" fn ac__arrow_client__add_connection_state_callback(
         .add_event_listener(ConnectionStateListener::new(opaque, callback))
 }
 
+/// Add a given connection state callback. Unlike the simple connection state
+/// callback, this one also receives the kind of the last connection error or
+/// the suspend reason.
+#[no_mangle]
+pub unsafe extern "C" fn ac__arrow_client__add_connection_state_detail_callback(
+    client: *mut NativeArrowClient,
+    callback: ConnectionStateDetailCallback,
+    opaque: *mut c_void,
+) {
+    (*client)
+        .client
+        .add_event_listener(ConnectionStateDetailListener::new(opaque, callback))
+}
+
 /// Add a given network scanner state callback.
 #[no_mangle]
 pub unsafe extern "C" fn ac__arrow_client__add_network_scanner_state_callback(
@@ -210,6 +427,44 @@ pub unsafe extern "C" fn ac__arrow_client__add_network_scanner_state_callback(
         .add_event_listener(NetworkScannerStateListener::new(opaque, callback))
 }
 
+/// Add a given active Arrow Service callback.
+#[no_mangle]
+pub unsafe extern "C" fn ac__arrow_client__add_active_arrow_service_callback(
+    client: *mut NativeArrowClient,
+    callback: ActiveArrowServiceCallback,
+    opaque: *mut c_void,
+) {
+    let logger = (*client).client.get_logger();
+
+    (*client)
+        .client
+        .add_event_listener(ActiveArrowServiceListener::new(opaque, callback, logger))
+}
+
+/// Add a given session opened callback.
+#[no_mangle]
+pub unsafe extern "C" fn ac__arrow_client__add_session_opened_callback(
+    client: *mut NativeArrowClient,
+    callback: SessionOpenedCallback,
+    opaque: *mut c_void,
+) {
+    (*client)
+        .client
+        .add_event_listener(SessionOpenedListener::new(opaque, callback))
+}
+
+/// Add a given session closed callback.
+#[no_mangle]
+pub unsafe extern "C" fn ac__arrow_client__add_session_closed_callback(
+    client: *mut NativeArrowClient,
+    callback: SessionClosedCallback,
+    opaque: *mut c_void,
+) {
+    (*client)
+        .client
+        .add_event_listener(SessionClosedListener::new(opaque, callback))
+}
+
 /// Get Arrow client UUID. The given buffer must have enough space to store at least 16 bytes.
 #[no_mangle]
 pub unsafe extern "C" fn ac__arrow_client__get_uuid(
@@ -246,6 +501,24 @@ pub unsafe extern "C" fn ac__arrow_client__get_service_table(
     Box::into_raw(Box::new(NativeServiceTable::from(table)))
 }
 
+/// Get all active sessions.
+#[no_mangle]
+pub unsafe extern "C" fn ac__arrow_client__get_sessions(
+    client: *const NativeArrowClient,
+) -> *mut NativeSessionList {
+    let sessions = (*client).client.get_sessions();
+
+    Box::into_raw(Box::new(NativeSessionList::from(sessions)))
+}
+
+/// Get Arrow Service connection statistics.
+#[no_mangle]
+pub unsafe extern "C" fn ac__arrow_client__get_connection_stats(
+    client: *const NativeArrowClient,
+) -> *mut ConnectionStats {
+    Box::into_raw(Box::new((*client).client.get_connection_stats()))
+}
+
 /// Scan the local network.
 #[no_mangle]
 pub unsafe extern "C" fn ac__arrow_client__scan_network(client: *mut NativeArrowClient) {
@@ -258,6 +531,18 @@ pub unsafe extern "C" fn ac__arrow_client__rescan_network(client: *mut NativeArr
     (*client).client.rescan_network();
 }
 
+/// Reset the pairing window and resume the client if it has been suspended.
+#[no_mangle]
+pub unsafe extern "C" fn ac__arrow_client__enter_pairing_mode(client: *mut NativeArrowClient) {
+    (*client).client.enter_pairing_mode();
+}
+
+/// Resume the client if it has been suspended.
+#[no_mangle]
+pub unsafe extern "C" fn ac__arrow_client__resume(client: *mut NativeArrowClient) {
+    (*client).client.resume();
+}
+
 /// Free a given join handle.
 #[no_mangle]
 pub unsafe extern "C" fn ac__join_handle__free(handle: *mut JoinHandle<()>) {
@@ -0,0 +1,151 @@
+// Copyright 2020 Angelcam, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![allow(clippy::missing_safety_doc)]
+
+use std::slice;
+
+use std::net::IpAddr;
+use std::time::UNIX_EPOCH;
+
+use libc::size_t;
+
+use crate::net::utils::IpAddrEx;
+use crate::SessionInfo;
+
+/// Helper struct.
+pub struct NativeSessionList {
+    sessions: Vec<NativeSession>,
+}
+
+impl<T> From<T> for NativeSessionList
+where
+    T: IntoIterator<Item = SessionInfo>,
+{
+    fn from(sessions: T) -> Self {
+        let sessions = sessions.into_iter().map(NativeSession::from).collect();
+
+        Self { sessions }
+    }
+}
+
+/// Helper struct.
+pub struct NativeSession {
+    info: SessionInfo,
+}
+
+impl From<SessionInfo> for NativeSession {
+    fn from(info: SessionInfo) -> Self {
+        Self { info }
+    }
+}
+
+/// Free the session list.
+#[no_mangle]
+pub unsafe extern "C" fn ac__session_list__free(list: *mut NativeSessionList) {
+    std::mem::drop(Box::from_raw(list));
+}
+
+/// Get number of sessions in the list.
+#[no_mangle]
+pub unsafe extern "C" fn ac__session_list__get_session_count(
+    list: *const NativeSessionList,
+) -> size_t {
+    (*list).sessions.len() as _
+}
+
+/// Get session at a given index.
+#[no_mangle]
+pub unsafe extern "C" fn ac__session_list__get_session(
+    list: *const NativeSessionList,
+    index: size_t,
+) -> *const NativeSession {
+    let list = &*list;
+
+    &list.sessions[index]
+}
+
+/// Get session ID.
+#[no_mangle]
+pub unsafe extern "C" fn ac__session__get_id(session: *const NativeSession) -> u32 {
+    (*session).info.session_id()
+}
+
+/// Get ID of the service the session is connected to.
+#[no_mangle]
+pub unsafe extern "C" fn ac__session__get_service_id(session: *const NativeSession) -> u16 {
+    (*session).info.service_id()
+}
+
+/// Get version of the remote IP address.
+#[no_mangle]
+pub unsafe extern "C" fn ac__session__get_ip_version(session: *const NativeSession) -> u8 {
+    (*session).info.address().ip().version()
+}
+
+/// Get remote IP address. The given buffer must have enough space to store at least 4 bytes for
+/// IPv4 address or 16 bytes for IPv6 address. Version of the IP address is returned.
+#[no_mangle]
+pub unsafe extern "C" fn ac__session__get_ip_address(
+    session: *const NativeSession,
+    buffer: *mut u8,
+) -> u8 {
+    let addr = (*session).info.address().ip();
+
+    match addr {
+        IpAddr::V4(addr) => {
+            let addr = addr.octets();
+            let buffer = slice::from_raw_parts_mut(buffer, addr.len());
+
+            buffer.copy_from_slice(&addr);
+        }
+        IpAddr::V6(addr) => {
+            let addr = addr.octets();
+            let buffer = slice::from_raw_parts_mut(buffer, addr.len());
+
+            buffer.copy_from_slice(&addr);
+        }
+    }
+
+    addr.version()
+}
+
+/// Get remote port.
+#[no_mangle]
+pub unsafe extern "C" fn ac__session__get_port(session: *const NativeSession) -> u16 {
+    (*session).info.address().port()
+}
+
+/// Get session start time as a UNIX timestamp in milliseconds.
+#[no_mangle]
+pub unsafe extern "C" fn ac__session__get_start_time(session: *const NativeSession) -> u64 {
+    (*session)
+        .info
+        .start_time()
+        .duration_since(UNIX_EPOCH)
+        .map(|t| t.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Get number of bytes sent to the remote service.
+#[no_mangle]
+pub unsafe extern "C" fn ac__session__get_bytes_sent(session: *const NativeSession) -> u64 {
+    (*session).info.bytes_sent()
+}
+
+/// Get number of bytes received from the remote service.
+#[no_mangle]
+pub unsafe extern "C" fn ac__session__get_bytes_received(session: *const NativeSession) -> u64 {
+    (*session).info.bytes_received()
+}
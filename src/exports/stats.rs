@@ -0,0 +1,83 @@
+// Copyright 2020 Angelcam, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![allow(clippy::missing_safety_doc)]
+
+use std::time::Duration;
+
+use crate::ConnectionStats;
+
+/// Convert a given optional duration into microseconds (-1 means unknown).
+fn duration_to_micros(duration: Option<Duration>) -> i64 {
+    duration.map(|d| d.as_micros() as i64).unwrap_or(-1)
+}
+
+/// Free the connection statistics.
+#[no_mangle]
+pub unsafe extern "C" fn ac__connection_stats__free(stats: *mut ConnectionStats) {
+    std::mem::drop(Box::from_raw(stats));
+}
+
+/// Get the last measured round-trip time in microseconds (-1 if unknown).
+#[no_mangle]
+pub unsafe extern "C" fn ac__connection_stats__get_rtt(stats: *const ConnectionStats) -> i64 {
+    duration_to_micros((*stats).rtt())
+}
+
+/// Get smoothed round-trip time in microseconds (-1 if unknown).
+#[no_mangle]
+pub unsafe extern "C" fn ac__connection_stats__get_smoothed_rtt(
+    stats: *const ConnectionStats,
+) -> i64 {
+    duration_to_micros((*stats).smoothed_rtt())
+}
+
+/// Get round-trip time variation in microseconds (-1 if unknown).
+#[no_mangle]
+pub unsafe extern "C" fn ac__connection_stats__get_rtt_jitter(
+    stats: *const ConnectionStats,
+) -> i64 {
+    duration_to_micros((*stats).rtt_jitter())
+}
+
+/// Get number of reconnects.
+#[no_mangle]
+pub unsafe extern "C" fn ac__connection_stats__get_reconnects(
+    stats: *const ConnectionStats,
+) -> u64 {
+    (*stats).reconnects()
+}
+
+/// Get uptime of the current connection in microseconds (-1 if the client is
+/// not connected).
+#[no_mangle]
+pub unsafe extern "C" fn ac__connection_stats__get_uptime(stats: *const ConnectionStats) -> i64 {
+    duration_to_micros((*stats).uptime())
+}
+
+/// Get total number of bytes sent to the Arrow Service.
+#[no_mangle]
+pub unsafe extern "C" fn ac__connection_stats__get_bytes_sent(
+    stats: *const ConnectionStats,
+) -> u64 {
+    (*stats).bytes_sent()
+}
+
+/// Get total number of bytes received from the Arrow Service.
+#[no_mangle]
+pub unsafe extern "C" fn ac__connection_stats__get_bytes_received(
+    stats: *const ConnectionStats,
+) -> u64 {
+    (*stats).bytes_received()
+}
@@ -30,8 +30,8 @@ use crate::exports::storage::DynStorage;
 use crate::storage::Storage;
 use crate::utils::json::{FromJson, ToJson};
 
-use crate::exports::connection_state_to_c_int;
 use crate::exports::mem::ac__free;
+use crate::exports::{connection_state_detail_to_c_int, connection_state_to_c_int};
 
 /// Type alias.
 type SaveConfiguration =
@@ -48,6 +48,10 @@ type LoadCACertificates =
 /// Type alias.
 type SaveConnectionState = unsafe extern "C" fn(opaque: *mut c_void, state: c_int) -> c_int;
 
+/// Type alias.
+type SaveConnectionStateDetail =
+    unsafe extern "C" fn(opaque: *mut c_void, state: c_int, detail: c_int) -> c_int;
+
 /// Type alias.
 type LoadPaths = unsafe extern "C" fn(
     opaque: *mut c_void,
@@ -62,6 +66,7 @@ pub struct CustomStorage {
     load_configuration: Option<LoadConfiguration>,
     load_ca_certificates: Option<LoadCACertificates>,
     save_connection_state: Option<SaveConnectionState>,
+    save_connection_state_detail: Option<SaveConnectionStateDetail>,
     load_rtsp_paths: Option<LoadPaths>,
     load_mjpeg_paths: Option<LoadPaths>,
 }
@@ -75,6 +80,7 @@ impl CustomStorage {
             load_configuration: None,
             load_ca_certificates: None,
             save_connection_state: None,
+            save_connection_state_detail: None,
             load_rtsp_paths: None,
             load_mjpeg_paths: None,
         }
@@ -147,12 +153,22 @@ impl Storage for CustomStorage {
     }
 
     fn save_connection_state(&mut self, state: ConnectionState) -> Result<(), io::Error> {
-        if let Some(func) = self.save_connection_state {
-            let res = unsafe { func(self.opaque, connection_state_to_c_int(state)) };
-
-            if res != 0 {
-                return Err(io::Error::from_raw_os_error(res));
+        let res = if let Some(func) = self.save_connection_state_detail {
+            unsafe {
+                func(
+                    self.opaque,
+                    connection_state_to_c_int(state),
+                    connection_state_detail_to_c_int(state),
+                )
             }
+        } else if let Some(func) = self.save_connection_state {
+            unsafe { func(self.opaque, connection_state_to_c_int(state)) }
+        } else {
+            0
+        };
+
+        if res != 0 {
+            return Err(io::Error::from_raw_os_error(res));
         }
 
         Ok(())
@@ -269,6 +285,17 @@ pub unsafe extern "C" fn ac__custom_storage_builder__set_save_connection_state_f
     (*builder).save_connection_state = Some(func);
 }
 
+/// Set function for saving client connection state together with the state
+/// detail. The function takes precedence over the one set using
+/// `ac__custom_storage_builder__set_save_connection_state_func()`.
+#[no_mangle]
+pub unsafe extern "C" fn ac__custom_storage_builder__set_save_connection_state_detail_func(
+    builder: *mut CustomStorage,
+    func: SaveConnectionStateDetail,
+) {
+    (*builder).save_connection_state_detail = Some(func);
+}
+
 /// Set function for loading RTSP paths. The function must allocate the paths
 /// using `ac__malloc()`.
 #[no_mangle]
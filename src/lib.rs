@@ -34,6 +34,9 @@ pub mod client;
 #[doc(hidden)]
 pub mod cmd_handler;
 
+#[cfg(not(target_os = "windows"))]
+mod control;
+
 pub mod config;
 
 #[doc(hidden)]
@@ -58,8 +61,10 @@ pub mod storage;
 pub mod svc_table;
 
 pub use client::{ArrowClient, ArrowClientTask};
+pub use client::{ArrowStream, ArrowTransport, ArrowTransportFuture};
 pub use context::ApplicationEventListener as ArrowClientEventListener;
-pub use context::ConnectionState;
+pub use context::{ConnectionState, SuspendReason};
+pub use net::arrow::{ConnectionStats, ErrorKind, SessionCloseReason, SessionInfo};
 
 pub mod logger {
     pub use crate::utils::logger::file::FileLogger;
@@ -14,6 +14,7 @@
 
 use std::error::Error;
 use std::fmt::Debug;
+use std::time::Duration;
 
 use arrow_client::runtime;
 
@@ -22,6 +23,11 @@ use arrow_client::config::Config;
 
 use arrow_client::config::usage;
 
+/// Maximum time for closing all sessions and the Arrow Service connection
+/// when the process is terminated.
+#[cfg(not(target_os = "windows"))]
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// Unwrap a given result (if possible) or print the error message and exit
 /// the process printing application usage.
 fn result_or_usage<T, E>(res: Result<T, E>) -> T
@@ -37,14 +43,57 @@ where
     }
 }
 
+/// Signals handled by the application.
+#[cfg(not(target_os = "windows"))]
+enum Signal {
+    EnterPairingMode,
+    Shutdown,
+}
+
+/// Enter the pairing mode every time the process receives SIGUSR1 and shut
+/// down the client gracefully when the process receives SIGTERM.
+#[cfg(not(target_os = "windows"))]
+async fn handle_signals(mut client: ArrowClient) {
+    use futures::stream::StreamExt;
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let pairing = signal(SignalKind::user_defined1());
+    let terminate = signal(SignalKind::terminate());
+
+    if let (Ok(pairing), Ok(terminate)) = (pairing, terminate) {
+        let mut signals = futures::stream::select(
+            pairing.map(|_| Signal::EnterPairingMode),
+            terminate.map(|_| Signal::Shutdown),
+        );
+
+        while let Some(signal) = signals.next().await {
+            match signal {
+                Signal::EnterPairingMode => client.enter_pairing_mode(),
+                Signal::Shutdown => return client.shutdown(SHUTDOWN_TIMEOUT).await,
+            }
+        }
+    }
+
+    // forget the client, we want to run the application indefinitely
+    std::mem::forget(client);
+}
+
+/// Dummy signal handler (there are no user-defined signals on Windows).
+#[cfg(target_os = "windows")]
+async fn handle_signals(client: ArrowClient) {
+    // forget the client, we want to run the application indefinitely
+    std::mem::forget(client);
+}
+
 /// Arrow Client main function.
 fn main() {
     let config = result_or_usage(Config::from_args(std::env::args()));
 
     let (client, task) = ArrowClient::new(config);
 
-    // forget the client, we want to run the application indefinitely
-    std::mem::forget(client);
+    runtime::run(async move {
+        tokio::spawn(handle_signals(client));
 
-    runtime::run(task);
+        task.await
+    });
 }
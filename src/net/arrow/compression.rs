@@ -0,0 +1,249 @@
+// Copyright 2020 Angelcam, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compression of session data.
+//!
+//! Every direction of a session uses a single raw deflate stream. Each
+//! message payload is terminated by a sync flush, so it can be decompressed
+//! as soon as it arrives (provided that all previous payloads of the same
+//! session have been decompressed).
+
+use bytes::Bytes;
+
+#[cfg(feature = "compression")]
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+
+use crate::net::arrow::error::ConnectionError;
+
+/// Maximum size of a single decompressed payload.
+#[cfg(feature = "compression")]
+const MAX_DECOMPRESSED_SIZE: usize = 1024 * 1024;
+
+/// Minimum amount of free space in the output buffer before calling the
+/// (de)compressor.
+#[cfg(feature = "compression")]
+const MIN_SPARE_CAPACITY: usize = 1024;
+
+/// Get the maximum size of a payload that is guaranteed to fit into a given
+/// number of bytes once compressed. Incompressible data get slightly larger
+/// (deflate block headers and the sync flush marker).
+pub fn max_compressible_size(max_len: usize) -> usize {
+    // this is more than the deflate worst case plus the flush marker
+    let overhead = (max_len >> 10) + 32;
+
+    max_len.saturating_sub(overhead)
+}
+
+/// Compressor of session data sent to the Arrow Service.
+#[cfg(feature = "compression")]
+pub struct PayloadCompressor {
+    inner: Compress,
+}
+
+#[cfg(feature = "compression")]
+impl PayloadCompressor {
+    /// Create a new compressor. The method returns `None` if the client was
+    /// built without compression support.
+    pub fn new() -> Option<Self> {
+        let res = Self {
+            inner: Compress::new(Compression::default(), false),
+        };
+
+        Some(res)
+    }
+
+    /// Compress a given payload.
+    pub fn compress(&mut self, data: &[u8]) -> Bytes {
+        let start = self.inner.total_in();
+
+        let mut output = Vec::with_capacity(data.len() / 2 + MIN_SPARE_CAPACITY);
+
+        loop {
+            if output.capacity() - output.len() < MIN_SPARE_CAPACITY {
+                output.reserve(data.len().max(MIN_SPARE_CAPACITY));
+            }
+
+            let consumed = (self.inner.total_in() - start) as usize;
+
+            self.inner
+                .compress_vec(&data[consumed..], &mut output, FlushCompress::Sync)
+                .expect("unable to compress session data");
+
+            let consumed = (self.inner.total_in() - start) as usize;
+
+            // the flush is complete once all input has been consumed and
+            // there is still some space left in the output buffer
+            if consumed == data.len() && output.len() < output.capacity() {
+                return Bytes::from(output);
+            }
+        }
+    }
+}
+
+/// Decompressor of session data received from the Arrow Service.
+#[cfg(feature = "compression")]
+pub struct PayloadDecompressor {
+    inner: Decompress,
+}
+
+#[cfg(feature = "compression")]
+impl PayloadDecompressor {
+    /// Create a new decompressor. The method returns `None` if the client was
+    /// built without compression support.
+    pub fn new() -> Option<Self> {
+        let res = Self {
+            inner: Decompress::new(false),
+        };
+
+        Some(res)
+    }
+
+    /// Decompress a given payload.
+    pub fn decompress(&mut self, data: &[u8]) -> Result<Bytes, ConnectionError> {
+        let start = self.inner.total_in();
+
+        let mut output = Vec::with_capacity(data.len() * 4 + MIN_SPARE_CAPACITY);
+
+        loop {
+            if output.capacity() - output.len() < MIN_SPARE_CAPACITY {
+                output.reserve(output.capacity());
+            }
+
+            let consumed = (self.inner.total_in() - start) as usize;
+            let produced = output.len();
+
+            let status = self
+                .inner
+                .decompress_vec(&data[consumed..], &mut output, FlushDecompress::Sync)
+                .map_err(|err| {
+                    ConnectionError::new(format!("unable to decompress session data: {}", err))
+                })?;
+
+            let progress = self.inner.total_in() - start > consumed as u64;
+            let progress = progress || output.len() > produced;
+
+            let consumed = (self.inner.total_in() - start) as usize;
+
+            // the payload is complete once all input has been consumed and
+            // there is no more pending output
+            let complete =
+                consumed == data.len() && (output.len() < output.capacity() || !progress);
+
+            if output.len() > MAX_DECOMPRESSED_SIZE {
+                return Err(ConnectionError::new("decompressed session data too large"));
+            } else if complete || status == Status::StreamEnd {
+                return Ok(Bytes::from(output));
+            } else if !progress {
+                return Err(ConnectionError::new("malformed compressed session data"));
+            }
+        }
+    }
+}
+
+/// Compressor of session data sent to the Arrow Service.
+#[cfg(not(feature = "compression"))]
+pub enum PayloadCompressor {}
+
+#[cfg(not(feature = "compression"))]
+impl PayloadCompressor {
+    /// Create a new compressor. The method returns `None` if the client was
+    /// built without compression support.
+    pub fn new() -> Option<Self> {
+        None
+    }
+
+    /// Compress a given payload.
+    pub fn compress(&mut self, _: &[u8]) -> Bytes {
+        match *self {}
+    }
+}
+
+/// Decompressor of session data received from the Arrow Service.
+#[cfg(not(feature = "compression"))]
+pub enum PayloadDecompressor {}
+
+#[cfg(not(feature = "compression"))]
+impl PayloadDecompressor {
+    /// Create a new decompressor. The method returns `None` if the client was
+    /// built without compression support.
+    pub fn new() -> Option<Self> {
+        None
+    }
+
+    /// Decompress a given payload.
+    pub fn decompress(&mut self, _: &[u8]) -> Result<Bytes, ConnectionError> {
+        match *self {}
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "compression")]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_max_compressible_size() {
+        let mut compressor = PayloadCompressor::new().unwrap();
+
+        // pseudo-random data that cannot be compressed
+        let random = (0..100_000u32)
+            .map(|n| (n.wrapping_mul(2_654_435_761) >> 24) as u8)
+            .collect::<Vec<_>>();
+
+        for &max_len in &[64, 1500, 4096, 65536] {
+            let len = max_compressible_size(max_len);
+
+            let compressed = compressor.compress(&random[..len]);
+
+            assert!(compressed.len() <= max_len);
+        }
+
+        assert_eq!(max_compressible_size(16), 0);
+    }
+
+    #[test]
+    fn test_compression() {
+        let mut compressor = PayloadCompressor::new().unwrap();
+        let mut decompressor = PayloadDecompressor::new().unwrap();
+
+        let html = b"<html><body><p>Hello, World!</p></body></html>".repeat(100);
+
+        // pseudo-random data that cannot be compressed
+        let random = (0..100_000u32)
+            .map(|n| (n.wrapping_mul(2_654_435_761) >> 24) as u8)
+            .collect::<Vec<_>>();
+
+        for data in &[&html[..], &random[..], &html[..], b"x"] {
+            let compressed = compressor.compress(data);
+
+            let decompressed = decompressor.decompress(&compressed).unwrap();
+
+            assert_eq!(&decompressed[..], *data);
+        }
+
+        // the second copy of the HTML data will be compressed using the
+        // history of the stream
+        let mut compressor = PayloadCompressor::new().unwrap();
+
+        let first = compressor.compress(&html);
+        let second = compressor.compress(&html);
+
+        assert!(first.len() < html.len() / 10);
+        assert!(second.len() < first.len());
+
+        let mut decompressor = PayloadDecompressor::new().unwrap();
+
+        assert!(decompressor.decompress(b"\xff\xff\xff\xff").is_err());
+    }
+}
@@ -38,6 +38,25 @@ pub enum ErrorKind {
     Other,
 }
 
+impl ErrorKind {
+    /// Get string representation of the error kind.
+    fn as_str(&self) -> &str {
+        match &self {
+            Self::ConnectionError => "connection error",
+            Self::UnsupportedProtocolVersion => "unsupported protocol version",
+            Self::Unauthorized => "unauthorized",
+            Self::ArrowServerError => "Arrow Server error",
+            Self::Other => "other error",
+        }
+    }
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut Formatter) -> result::Result<(), fmt::Error> {
+        f.write_str(self.as_str())
+    }
+}
+
 /// Arrow Client error.
 #[derive(Debug, Clone)]
 pub struct ArrowError {
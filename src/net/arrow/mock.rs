@@ -0,0 +1,835 @@
+// Copyright 2020 Angelcam, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Scriptable mock of the Arrow Service for end-to-end tests.
+//!
+//! The mock listens on a random local port, accepts TLS connections using a
+//! certificate issued by a test CA generated at runtime and it lets tests
+//! drive the Arrow Control Protocol step by step from a separate thread.
+
+use std::io;
+use std::thread;
+
+use std::collections::{HashMap, VecDeque};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::{Duration, Instant};
+
+use bytes::{Bytes, BytesMut};
+
+use openssl::asn1::Asn1Time;
+use openssl::bn::{BigNum, MsbOption};
+use openssl::ec::{EcGroup, EcKey};
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::{PKey, Private};
+use openssl::ssl::{SslAcceptor, SslConnectorBuilder, SslMethod, SslStream};
+use openssl::x509::extension::BasicConstraints;
+use openssl::x509::{X509Builder, X509NameBuilder, X509};
+
+use crate::config::PersistentConfig;
+use crate::net::arrow::proto::codec::{Decode, Encode, FromBytes};
+use crate::net::arrow::proto::msg::control::{
+    AckMessage, ClientHealth, ControlMessage, ControlMessageHeader, ControlMessageType,
+    EmptyMessage, GetLogsMessage, HupMessage, LogsMessage, ProbeResultMessage, RedirectMessage,
+    RegisterMessage, ServiceIdMessage, ServiceMessage, SimpleServiceTable, StatusMessage,
+    UpdateMessage, WindowUpdateMessage, CAPABILITY_FLOW_CONTROL, LOGS_FLAG_LAST,
+    SESSION_WINDOW_SIZE,
+};
+use crate::net::arrow::proto::msg::ArrowMessage;
+use crate::scanner::probe::ProbeResult;
+use crate::storage::Storage;
+use crate::svc_table::Service;
+use crate::utils::logger::ring::LogEntry;
+use crate::utils::logger::Severity;
+
+pub use crate::net::arrow::proto::msg::control::{
+    EC_CONNECTION_ERROR, EC_INTERNAL_SERVER_ERROR, EC_INVALID_SERVICE, EC_NO_ERROR,
+    EC_NO_SUCH_SERVICE, EC_UNAUTHORIZED, EC_UNSUPPORTED_METHOD, EC_UNSUPPORTED_PROTOCOL_VERSION,
+};
+
+/// Timeout for all blocking operations of the mock.
+const MOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Test certificate authority together with a server certificate issued by
+/// the authority.
+pub struct TestCa {
+    ca_cert: X509,
+    server_cert: X509,
+    server_key: PKey<Private>,
+}
+
+impl TestCa {
+    /// Generate a new test CA and a server certificate.
+    pub fn new() -> Self {
+        let ca_key = Self::generate_key();
+        let ca_cert = Self::issue_certificate("Arrow Test CA", &ca_key, None, true);
+
+        let server_key = Self::generate_key();
+        let server_cert =
+            Self::issue_certificate("localhost", &server_key, Some((&ca_cert, &ca_key)), false);
+
+        Self {
+            ca_cert,
+            server_cert,
+            server_key,
+        }
+    }
+
+    /// Get a storage trusting only this CA.
+    pub fn storage(&self) -> TestStorage {
+        TestStorage {
+            ca_cert: self.ca_cert.clone(),
+        }
+    }
+
+    /// Generate a new private key.
+    fn generate_key() -> PKey<Private> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let key = EcKey::generate(&group).unwrap();
+
+        PKey::from_ec_key(key).unwrap()
+    }
+
+    /// Issue a new certificate for a given key. The certificate will be
+    /// self-signed if no issuer is given.
+    fn issue_certificate(
+        cn: &str,
+        key: &PKey<Private>,
+        issuer: Option<(&X509, &PKey<Private>)>,
+        ca: bool,
+    ) -> X509 {
+        let mut name = X509NameBuilder::new().unwrap();
+
+        name.append_entry_by_nid(Nid::COMMONNAME, cn).unwrap();
+
+        let name = name.build();
+
+        let mut serial = BigNum::new().unwrap();
+
+        serial.rand(64, MsbOption::MAYBE_ZERO, false).unwrap();
+
+        let serial = serial.to_asn1_integer().unwrap();
+
+        let mut builder = X509Builder::new().unwrap();
+
+        builder.set_version(2).unwrap();
+        builder.set_serial_number(&serial).unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_pubkey(key).unwrap();
+        builder
+            .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        builder
+            .set_not_after(&Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+
+        if ca {
+            let constraints = BasicConstraints::new().critical().ca().build().unwrap();
+
+            builder.append_extension(constraints).unwrap();
+        }
+
+        let (issuer_name, issuer_key) = match issuer {
+            Some((cert, key)) => (cert.subject_name(), key),
+            None => (name.as_ref(), key),
+        };
+
+        builder.set_issuer_name(issuer_name).unwrap();
+        builder.sign(issuer_key, MessageDigest::sha256()).unwrap();
+
+        builder.build()
+    }
+}
+
+impl Default for TestCa {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Client storage that keeps everything in memory and trusts only the test
+/// CA.
+pub struct TestStorage {
+    ca_cert: X509,
+}
+
+impl Storage for TestStorage {
+    fn save_configuration(&mut self, _: &PersistentConfig) -> Result<(), io::Error> {
+        Ok(())
+    }
+
+    fn load_configuration(&mut self) -> Result<PersistentConfig, io::Error> {
+        Ok(PersistentConfig::new())
+    }
+
+    fn load_ca_certificates(&mut self, builder: &mut SslConnectorBuilder) -> Result<(), io::Error> {
+        builder
+            .cert_store_mut()
+            .add_cert(self.ca_cert.clone())
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))
+    }
+}
+
+/// Mock Arrow Service.
+pub struct MockArrowService {
+    listener: TcpListener,
+    acceptor: SslAcceptor,
+}
+
+impl MockArrowService {
+    /// Create a new mock Arrow Service listening on a random local port.
+    pub fn new(ca: &TestCa) -> Self {
+        let mut acceptor = SslAcceptor::mozilla_intermediate(SslMethod::tls()).unwrap();
+
+        acceptor.set_private_key(&ca.server_key).unwrap();
+        acceptor.set_certificate(&ca.server_cert).unwrap();
+        acceptor.add_extra_chain_cert(ca.ca_cert.clone()).unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        listener.set_nonblocking(true).unwrap();
+
+        Self {
+            listener,
+            acceptor: acceptor.build(),
+        }
+    }
+
+    /// Get address of the service in the "host:port" format.
+    pub fn address(&self) -> String {
+        self.listener.local_addr().unwrap().to_string()
+    }
+
+    /// Wait for a new client connection and perform the TLS handshake.
+    pub fn accept(&self) -> MockConnection {
+        let start = Instant::now();
+
+        let stream = loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => break stream,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                    if start.elapsed() >= MOCK_TIMEOUT {
+                        panic!("no client connection");
+                    }
+
+                    thread::sleep(Duration::from_millis(10));
+                }
+                Err(err) => panic!("unable to accept a client connection: {}", err),
+            }
+        };
+
+        stream.set_nonblocking(false).unwrap();
+        stream.set_read_timeout(Some(MOCK_TIMEOUT)).unwrap();
+
+        let stream = self.acceptor.accept(stream).unwrap();
+
+        MockConnection::new(stream)
+    }
+}
+
+/// Single client connection to the mock Arrow Service.
+pub struct MockConnection {
+    stream: SslStream<TcpStream>,
+    buffer: BytesMut,
+    control: VecDeque<ArrowMessage>,
+    data: VecDeque<ArrowMessage>,
+    msg_id: u16,
+    version: Option<u8>,
+    reply_version: Option<u8>,
+    windows: Option<HashMap<u32, usize>>,
+}
+
+impl MockConnection {
+    /// Create a new connection.
+    fn new(stream: SslStream<TcpStream>) -> Self {
+        Self {
+            stream,
+            buffer: BytesMut::new(),
+            control: VecDeque::new(),
+            data: VecDeque::new(),
+            msg_id: 0x8000,
+            version: None,
+            reply_version: None,
+            windows: None,
+        }
+    }
+
+    /// Get version of the Arrow protocol used by the client (i.e. the
+    /// version of the last message received from the client).
+    pub fn version(&self) -> u8 {
+        self.version.expect("no message received from the client")
+    }
+
+    /// Use a given version of the Arrow protocol for all messages sent to
+    /// the client (i.e. announce the version to the client).
+    pub fn use_version(&mut self, version: u8) {
+        self.reply_version = Some(version);
+    }
+
+    /// Get the next Control Protocol message ID.
+    fn next_id(&mut self) -> u16 {
+        let res = self.msg_id;
+
+        self.msg_id = self.msg_id.wrapping_add(1);
+
+        res
+    }
+
+    /// Read the next Arrow Message from the client.
+    fn read_message(&mut self) -> ArrowMessage {
+        let mut buffer = [0u8; 4096];
+
+        loop {
+            if let Some(msg) = ArrowMessage::decode(&mut self.buffer).unwrap() {
+                self.version = Some(msg.header().version);
+
+                return msg;
+            }
+
+            let len = self.stream.read(&mut buffer).unwrap();
+
+            if len == 0 {
+                panic!("connection closed by the client");
+            }
+
+            self.buffer.extend_from_slice(&buffer[..len]);
+        }
+    }
+
+    /// Read Arrow Messages until there is at least one message in a given
+    /// queue.
+    fn fill_queue(&mut self, control: bool) {
+        loop {
+            let queue = if control { &self.control } else { &self.data };
+
+            if !queue.is_empty() {
+                return;
+            }
+
+            let msg = self.read_message();
+
+            self.enqueue(msg);
+        }
+    }
+
+    /// Put a given Arrow Message into the corresponding queue. WINDOW_UPDATE
+    /// messages are processed immediately if the session flow control is
+    /// enabled.
+    fn enqueue(&mut self, msg: ArrowMessage) {
+        if msg.header().service != 0 {
+            self.data.push_back(msg);
+        } else if let Some(update) = self.window_update(&msg) {
+            let session_id = update.session_id;
+            let increment = update.increment;
+
+            if let Some(windows) = self.windows.as_mut() {
+                *windows.entry(session_id).or_insert(SESSION_WINDOW_SIZE) += increment as usize;
+            }
+        } else {
+            self.control.push_back(msg);
+        }
+    }
+
+    /// Decode a given WINDOW_UPDATE message. `None` is returned if the
+    /// message is not a WINDOW_UPDATE message or if the session flow control
+    /// is not enabled.
+    fn window_update(&self, msg: &ArrowMessage) -> Option<WindowUpdateMessage> {
+        self.windows.as_ref()?;
+
+        let msg = ControlMessage::decode(msg.header().version, msg.payload()).ok()??;
+
+        if msg.header().message_type() != ControlMessageType::WINDOW_UPDATE {
+            return None;
+        }
+
+        let update = msg.body::<WindowUpdateMessage>()?;
+
+        Some(WindowUpdateMessage::new(
+            update.session_id,
+            update.increment,
+        ))
+    }
+
+    /// Receive the next Arrow Message (either a control message or session
+    /// data) in the order it was sent by the client. There must be no
+    /// messages left in the internal queues.
+    pub fn recv_any(&mut self) -> ArrowMessage {
+        assert!(self.control.is_empty(), "unread control messages");
+        assert!(self.data.is_empty(), "unread session data");
+
+        self.read_message()
+    }
+
+    /// Send a given Arrow Message to the client. Unless set otherwise, the
+    /// message will use the same version of the Arrow protocol as the
+    /// client.
+    pub fn send(&mut self, mut msg: ArrowMessage) {
+        let mut buffer = BytesMut::new();
+
+        if let Some(version) = self.reply_version.or(self.version) {
+            msg.set_version(version);
+        }
+
+        msg.encode(&mut buffer);
+
+        self.stream.write_all(&buffer).unwrap();
+        self.stream.flush().unwrap();
+    }
+
+    /// Send a given Control Protocol message to the client.
+    pub fn send_control(&mut self, msg: ControlMessage) {
+        self.send(ArrowMessage::from(msg))
+    }
+
+    /// Receive the next Control Protocol message header and the encoded
+    /// message body. This is useful for messages that the mock does not know
+    /// how to decode.
+    pub fn recv_control_raw(&mut self) -> (ControlMessageHeader, Vec<u8>) {
+        self.fill_queue(true);
+
+        let msg = self.control.pop_front().unwrap();
+        let payload = msg.payload();
+
+        let hsize = std::mem::size_of::<ControlMessageHeader>();
+
+        let header = ControlMessageHeader::from_bytes(&payload[..hsize])
+            .unwrap()
+            .unwrap();
+
+        (header, payload[hsize..].to_vec())
+    }
+
+    /// Receive the next Control Protocol message.
+    pub fn recv_control(&mut self) -> ControlMessage {
+        self.fill_queue(true);
+
+        let msg = self.control.pop_front().unwrap();
+
+        ControlMessage::decode(msg.header().version, msg.payload())
+            .unwrap()
+            .unwrap()
+    }
+
+    /// Receive the next Control Protocol message and make sure it is of a
+    /// given type.
+    pub fn expect_control(&mut self, expected: ControlMessageType) -> ControlMessage {
+        let msg = self.recv_control();
+
+        assert_eq!(msg.header().message_type(), expected);
+
+        msg
+    }
+
+    /// Receive the next ACK message and return its error code.
+    pub fn expect_ack(&mut self, msg_id: u16) -> u32 {
+        let msg = self.expect_control(ControlMessageType::ACK);
+
+        let header = msg.header();
+        let ack_id = header.msg_id;
+
+        assert_eq!(ack_id, msg_id);
+
+        msg.body::<AckMessage>().unwrap().err
+    }
+
+    /// Receive a REGISTER message, respond with an ACK containing a given
+    /// error code and return the service table sent by the client.
+    pub fn expect_register(&mut self, error_code: u32) -> SimpleServiceTable {
+        let msg = self.expect_control(ControlMessageType::REGISTER);
+
+        let svc_table = msg.body::<RegisterMessage>().unwrap().svc_table.clone();
+
+        self.send_control(ControlMessage::ack(msg.header().msg_id, error_code));
+
+        svc_table
+    }
+
+    /// Receive a REGISTER message and close the connection without
+    /// responding. The method returns version of the Arrow protocol used by
+    /// the client.
+    pub fn close_on_register(mut self) -> u8 {
+        self.expect_control(ControlMessageType::REGISTER);
+
+        self.version()
+    }
+
+    /// Receive a REGISTER message, respond with an ACK accepting the client
+    /// capabilities from a given set and return the service table sent by
+    /// the client.
+    pub fn expect_register_with_capabilities(&mut self, capabilities: u32) -> SimpleServiceTable {
+        let msg = self.expect_control(ControlMessageType::REGISTER);
+
+        let register = msg.body::<RegisterMessage>().unwrap();
+
+        let accepted = register.capabilities().unwrap_or(0) & capabilities;
+
+        if (accepted & CAPABILITY_FLOW_CONTROL) != 0 {
+            self.windows = Some(HashMap::new());
+        }
+
+        let ack = AckMessage::new(EC_NO_ERROR).with_capabilities(accepted);
+
+        self.send_control(ControlMessage::new(
+            msg.header().msg_id,
+            ControlMessageType::ACK,
+            ack,
+        ));
+
+        register.svc_table.clone()
+    }
+
+    /// Receive an UPDATE message and return the service table sent by the
+    /// client.
+    pub fn expect_update(&mut self) -> SimpleServiceTable {
+        let msg = self.expect_control(ControlMessageType::UPDATE);
+
+        msg.body::<UpdateMessage>().unwrap().svc_table.clone()
+    }
+
+    /// Send a PING message and wait for the corresponding ACK.
+    pub fn ping(&mut self) {
+        let msg_id = self.send_ping();
+
+        assert_eq!(self.expect_ack(msg_id), 0);
+    }
+
+    /// Send a PING message without waiting for the corresponding ACK and
+    /// return the message ID.
+    pub fn send_ping(&mut self) -> u16 {
+        let msg_id = self.next_id();
+
+        self.send_control(ControlMessage::ping(msg_id));
+
+        msg_id
+    }
+
+    /// Send a GET_STATUS message and return the status flags and the number
+    /// of active sessions reported by the client.
+    pub fn get_status(&mut self) -> (u32, u32) {
+        let msg = self.request_status();
+        let status = msg.body::<StatusMessage>().unwrap();
+
+        (status.status_flags, status.active_sessions)
+    }
+
+    /// Send a GET_STATUS message and return the client health details
+    /// reported by the client (if any).
+    pub fn get_health(&mut self) -> Option<ClientHealth> {
+        let msg = self.request_status();
+        let status = msg.body::<StatusMessage>().unwrap();
+
+        status.health.clone()
+    }
+
+    /// Send a GET_STATUS message and wait for the corresponding STATUS
+    /// message.
+    fn request_status(&mut self) -> ControlMessage {
+        let msg_id = self.next_id();
+
+        self.send_control(ControlMessage::new(
+            msg_id,
+            ControlMessageType::GET_STATUS,
+            EmptyMessage,
+        ));
+
+        let msg = self.expect_control(ControlMessageType::STATUS);
+        let status = msg.body::<StatusMessage>().unwrap();
+
+        assert_eq!(status.request_id, msg_id);
+
+        msg
+    }
+
+    /// Send a GET_SCAN_REPORT message and return the encoded SCAN_REPORT
+    /// body (without the request ID).
+    pub fn get_scan_report(&mut self) -> Vec<u8> {
+        let msg_id = self.next_id();
+
+        self.send_control(ControlMessage::new(
+            msg_id,
+            ControlMessageType::GET_SCAN_REPORT,
+            EmptyMessage,
+        ));
+
+        let (header, body) = self.recv_control_raw();
+
+        assert_eq!(header.message_type(), ControlMessageType::SCAN_REPORT);
+        assert!(body.len() >= 2);
+
+        let request_id = u16::from_be_bytes([body[0], body[1]]);
+
+        assert_eq!(request_id, msg_id);
+
+        body[2..].to_vec()
+    }
+
+    /// Send a GET_LOGS message and return all log entries from the
+    /// corresponding LOGS messages.
+    pub fn get_logs(&mut self, max_entries: u16, min_severity: Severity) -> Vec<LogEntry> {
+        let msg_id = self.next_id();
+
+        let msg = GetLogsMessage {
+            max_entries,
+            min_severity: min_severity as u8,
+        };
+
+        self.send_control(ControlMessage::new(
+            msg_id,
+            ControlMessageType::GET_LOGS,
+            msg,
+        ));
+
+        let mut res = Vec::new();
+
+        loop {
+            let msg = self.expect_control(ControlMessageType::LOGS);
+            let logs = msg.body::<LogsMessage>().unwrap();
+
+            assert_eq!(logs.request_id, msg_id);
+
+            res.extend(logs.entries.iter().cloned());
+
+            if (logs.flags & LOGS_FLAG_LAST) != 0 {
+                return res;
+            }
+        }
+    }
+
+    /// Send a PROBE_SERVICE message and return the probe result or the ACK
+    /// error code if the probe has been rejected.
+    pub fn probe_service(&mut self, service: Service) -> Result<ProbeResult, u32> {
+        let msg_id = self.send_probe_service(service);
+
+        let (request_id, res) = self.recv_probe_response();
+
+        assert_eq!(request_id, msg_id);
+
+        res
+    }
+
+    /// Send a PROBE_SERVICE message and return its ID.
+    pub fn send_probe_service(&mut self, service: Service) -> u16 {
+        let msg_id = self.next_id();
+
+        self.send_control(ControlMessage::new(
+            msg_id,
+            ControlMessageType::PROBE_SERVICE,
+            ServiceMessage::new(0, service),
+        ));
+
+        msg_id
+    }
+
+    /// Receive a response to a PROBE_SERVICE message and return the request
+    /// ID together with the probe result or the ACK error code if the probe
+    /// has been rejected.
+    pub fn recv_probe_response(&mut self) -> (u16, Result<ProbeResult, u32>) {
+        let msg = self.recv_control();
+
+        let header = msg.header();
+
+        match header.message_type() {
+            ControlMessageType::ACK => {
+                let ack = msg.body::<AckMessage>().unwrap();
+
+                (header.msg_id, Err(ack.err))
+            }
+            ControlMessageType::PROBE_RESULT => {
+                let probe = msg.body::<ProbeResultMessage>().unwrap();
+
+                (probe.request_id, Ok(probe.result.clone()))
+            }
+            mtype => panic!("unexpected control message: {:?}", mtype),
+        }
+    }
+
+    /// Send a SCAN_NETWORK message.
+    pub fn scan_network(&mut self) {
+        let msg_id = self.next_id();
+
+        self.send_control(ControlMessage::new(
+            msg_id,
+            ControlMessageType::SCAN_NETWORK,
+            EmptyMessage,
+        ));
+    }
+
+    /// Send a REDIRECT message.
+    pub fn redirect(&mut self, target: &str) {
+        let msg_id = self.next_id();
+
+        let redirect = RedirectMessage {
+            target: target.to_string(),
+        };
+
+        self.send_control(ControlMessage::new(
+            msg_id,
+            ControlMessageType::REDIRECT,
+            redirect,
+        ));
+    }
+
+    /// Send an ADD_SERVICE message and return the ACK error code.
+    pub fn add_service(&mut self, service: Service) -> u32 {
+        self.update_service(0, service, ControlMessageType::ADD_SERVICE)
+    }
+
+    /// Send an UPDATE_SERVICE message and return the ACK error code.
+    pub fn replace_service(&mut self, svc_id: u16, service: Service) -> u32 {
+        self.update_service(svc_id, service, ControlMessageType::UPDATE_SERVICE)
+    }
+
+    /// Send an ENABLE_SERVICE message and return the ACK error code.
+    pub fn enable_service(&mut self, svc_id: u16) -> u32 {
+        self.modify_service(svc_id, ControlMessageType::ENABLE_SERVICE)
+    }
+
+    /// Send a DISABLE_SERVICE message and return the ACK error code.
+    pub fn disable_service(&mut self, svc_id: u16) -> u32 {
+        self.modify_service(svc_id, ControlMessageType::DISABLE_SERVICE)
+    }
+
+    /// Send a REMOVE_SERVICE message and return the ACK error code.
+    pub fn remove_service(&mut self, svc_id: u16) -> u32 {
+        self.modify_service(svc_id, ControlMessageType::REMOVE_SERVICE)
+    }
+
+    /// Send a given service message and return the ACK error code.
+    fn update_service(&mut self, svc_id: u16, service: Service, mtype: ControlMessageType) -> u32 {
+        let msg_id = self.next_id();
+
+        let msg = ServiceMessage::new(svc_id, service);
+
+        self.send_control(ControlMessage::new(msg_id, mtype, msg));
+
+        self.expect_ack(msg_id)
+    }
+
+    /// Send a given service ID message and return the ACK error code.
+    fn modify_service(&mut self, svc_id: u16, mtype: ControlMessageType) -> u32 {
+        let msg_id = self.next_id();
+
+        let msg = ServiceIdMessage { svc_id };
+
+        self.send_control(ControlMessage::new(msg_id, mtype, msg));
+
+        self.expect_ack(msg_id)
+    }
+
+    /// Send a HUP message for a given session.
+    pub fn hup(&mut self, session_id: u32, error_code: u32) {
+        let msg_id = self.next_id();
+
+        self.send_control(ControlMessage::hup(msg_id, session_id, error_code));
+    }
+
+    /// Receive a HUP message for a given session and return its error code.
+    pub fn expect_hup(&mut self, session_id: u32) -> u32 {
+        let msg = self.expect_control(ControlMessageType::HUP);
+        let hup = msg.body::<HupMessage>().unwrap();
+
+        let hup_session_id = hup.session_id;
+
+        assert_eq!(hup_session_id, session_id);
+
+        hup.error_code
+    }
+
+    /// Wait until the client closes the connection. The connection must be
+    /// closed gracefully and there must be no unread control messages.
+    pub fn expect_close(&mut self) {
+        let mut buffer = [0u8; 4096];
+
+        loop {
+            if let Some(msg) = ArrowMessage::decode(&mut self.buffer).unwrap() {
+                let service = msg.header().service;
+
+                if self.window_update(&msg).is_none() {
+                    assert_ne!(service, 0, "unexpected control message");
+                }
+
+                continue;
+            }
+
+            let len = self.stream.read(&mut buffer).unwrap();
+
+            if len == 0 {
+                break;
+            }
+
+            self.buffer.extend_from_slice(&buffer[..len]);
+        }
+
+        assert!(self.control.is_empty());
+    }
+
+    /// Get the remaining size of the window of a given session. The window
+    /// is unlimited if the session flow control is not enabled.
+    pub fn window(&self, session_id: u32) -> usize {
+        self.windows
+            .as_ref()
+            .map(|windows| {
+                windows
+                    .get(&session_id)
+                    .copied()
+                    .unwrap_or(SESSION_WINDOW_SIZE)
+            })
+            .unwrap_or(usize::MAX)
+    }
+
+    /// Process messages from the client until the window of a given session
+    /// allows sending a given amount of data.
+    pub fn wait_for_window(&mut self, session_id: u32, len: usize) {
+        while self.window(session_id) < len {
+            let msg = self.read_message();
+
+            self.enqueue(msg);
+        }
+    }
+
+    /// Send given data to a given service within a given session. A new
+    /// session will be opened by the client if it does not exist yet. The
+    /// data must fit into the session window.
+    pub fn send_session_data(&mut self, service_id: u16, session_id: u32, data: &[u8]) {
+        if let Some(windows) = self.windows.as_mut() {
+            let window = windows.entry(session_id).or_insert(SESSION_WINDOW_SIZE);
+
+            assert!(data.len() <= *window, "session window exceeded");
+
+            *window -= data.len();
+        }
+
+        self.send(ArrowMessage::new(
+            service_id,
+            session_id,
+            Bytes::from(data.to_vec()),
+        ))
+    }
+
+    /// Receive the next session data message and return the session ID
+    /// together with the data.
+    pub fn recv_session_data(&mut self) -> (u32, Vec<u8>) {
+        let msg = self.recv_session_message();
+
+        (msg.header().session, msg.payload().to_vec())
+    }
+
+    /// Receive the next session data message.
+    pub fn recv_session_message(&mut self) -> ArrowMessage {
+        self.fill_queue(false);
+
+        self.data.pop_front().unwrap()
+    }
+}
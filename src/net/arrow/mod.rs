@@ -12,42 +12,64 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod compression;
 mod error;
 mod proto;
+
+#[cfg(test)]
+pub mod mock;
+
 mod session;
+mod stats;
+mod trace;
+mod transport;
 
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::pin::Pin;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
+use futures::future::{BoxFuture, FutureExt};
 use futures::sink::SinkExt;
-use futures::stream::{Stream, StreamExt};
+use futures::stream::{FuturesUnordered, Stream, StreamExt};
 use futures::task::{Context, Poll, Waker};
 
+use tokio::io::{AsyncRead, AsyncWrite};
+
 use tokio_util::codec::Decoder;
 
 use crate::cmd_handler::{Command, CommandChannel};
-use crate::context::ApplicationContext;
-use crate::net::arrow::proto::codec::{ArrowCodec, FromBytes};
+use crate::context::{ApplicationContext, ConnectionState, ShutdownSignal};
+use crate::net::arrow::proto::codec::ArrowCodec;
 use crate::net::arrow::proto::msg::control::ControlMessageFactory;
 use crate::net::arrow::proto::msg::control::{
-    AckMessage, ControlMessage, ControlMessageType, HupMessage, RedirectMessage,
-    SimpleServiceTable, EC_INTERNAL_SERVER_ERROR, EC_NO_ERROR, EC_UNAUTHORIZED,
+    AckMessage, ClientHealth, ControlMessage, ControlMessageType, GetLogsMessage, HupMessage,
+    RedirectMessage, ServiceIdMessage, ServiceMessage, SessionCounters, SimpleServiceTable,
+    CAPABILITY_COMPRESSION, CAPABILITY_FLOW_CONTROL, CLIENT_CAPABILITIES, EC_INTERNAL_SERVER_ERROR,
+    EC_INVALID_SERVICE, EC_NO_ERROR, EC_NO_SUCH_SERVICE, EC_UNAUTHORIZED, EC_UNSUPPORTED_METHOD,
     EC_UNSUPPORTED_PROTOCOL_VERSION, STATUS_FLAG_SCAN,
 };
 use crate::net::arrow::proto::msg::ArrowMessage;
 use crate::net::arrow::session::SessionManager;
 use crate::net::raw::ether::MacAddr;
-use crate::svc_table::SharedServiceTableRef;
+use crate::scanner::probe::{self, ProbeResult};
+use crate::svc_table::{Service, ServiceTableError, ServiceType, SharedServiceTableRef};
+use crate::utils;
 use crate::utils::logger::{BoxLogger, Logger};
 
 pub use self::error::{ArrowError, ErrorKind};
+pub use self::proto::{
+    DEFAULT_ARROW_PROTOCOL_VERSION, MAX_ARROW_PROTOCOL_VERSION, MIN_ARROW_PROTOCOL_VERSION,
+};
+pub use self::session::{SessionCloseReason, SessionHandle, SessionInfo};
+pub use self::stats::ConnectionStats;
+pub use self::trace::{MessageTracer, TraceDirection, TraceRecord};
+pub use self::transport::{ArrowStream, ArrowTransport, ArrowTransportFuture, DefaultTransport};
 
 const ACK_TIMEOUT: Duration = Duration::from_secs(20);
 const CONNECTION_TIMEOUT: Duration = Duration::from_secs(20);
-const PING_PERIOD: Duration = Duration::from_secs(60);
 const UPDATE_CHECK_PERIOD: Duration = Duration::from_secs(5);
+const MAX_CONCURRENT_PROBES: usize = 8;
 
 /// Arrow Protocol states.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
@@ -56,24 +78,37 @@ enum ProtocolState {
     Established,
 }
 
-/// Helper struct for expected ACK messages.
-struct ExpectedAck {
-    timestamp: Instant,
-    message_id: u16,
+/// Type alias. The callback is invoked with the corresponding ACK message
+/// once it arrives or with `None` if the ACK does not arrive in time. An
+/// error returned from the callback will terminate the connection.
+type AckCallback = Box<
+    dyn FnOnce(&mut ArrowClientContext, Option<&ControlMessage>) -> Result<(), ArrowError> + Send,
+>;
+
+/// Helper struct for control messages waiting for an ACK.
+struct PendingAck {
+    deadline: Instant,
+    callback: AckCallback,
 }
 
-impl ExpectedAck {
-    /// Create a new ACK message expectation.
-    fn new(message_id: u16) -> Self {
+impl PendingAck {
+    /// Create a new ACK message expectation with a given timeout and a given
+    /// callback.
+    fn new<F>(timeout: Duration, callback: F) -> Self
+    where
+        F: FnOnce(&mut ArrowClientContext, Option<&ControlMessage>) -> Result<(), ArrowError>
+            + Send
+            + 'static,
+    {
         Self {
-            timestamp: Instant::now(),
-            message_id,
+            deadline: Instant::now() + timeout,
+            callback: Box::new(callback),
         }
     }
 
     /// Check if it's too late for the ACK.
     fn timeout(&self) -> bool {
-        self.timestamp.elapsed() >= ACK_TIMEOUT
+        Instant::now() >= self.deadline
     }
 }
 
@@ -82,31 +117,49 @@ struct ArrowClientContext {
     logger: BoxLogger,
     app_context: ApplicationContext,
     cmd_channel: CommandChannel,
+    addr: String,
     svc_table: SharedServiceTableRef,
     cmsg_factory: ControlMessageFactory,
+    peer_version: u8,
     sessions: SessionManager,
     messages: VecDeque<ArrowMessage>,
-    expected_acks: VecDeque<ExpectedAck>,
+    pending_acks: HashMap<u16, PendingAck>,
+    probes: FuturesUnordered<BoxFuture<'static, (u16, Option<ProbeResult>)>>,
+    queued_probes: VecDeque<(u16, Service)>,
     state: ProtocolState,
     task: Option<Waker>,
     redirect: Option<String>,
     closed: bool,
+    shutting_down: bool,
+    paused_since: Option<Instant>,
+    ping_period: Duration,
     last_ping: Instant,
     last_update_chck: Instant,
     last_stable_ver: usize,
+    bytes_sent: u64,
+    bytes_received: u64,
+    tracer: Option<MessageTracer>,
 }
 
 impl ArrowClientContext {
-    /// Create a new Arrow Client.
-    fn new(app_context: ApplicationContext, cmd_channel: CommandChannel) -> Self {
+    /// Create a new Arrow Client using a given version of the Arrow
+    /// protocol.
+    fn new(
+        app_context: ApplicationContext,
+        cmd_channel: CommandChannel,
+        addr: &str,
+        version: u8,
+    ) -> Self {
         let logger = app_context.get_logger();
         let svc_table = app_context.get_service_table();
 
         let mac = app_context.get_arrow_mac_address();
         let uuid = app_context.get_arrow_uuid();
         let passwd = app_context.get_arrow_password();
+        let ping_period = app_context.get_ping_period();
+        let tracer = app_context.get_message_tracer();
 
-        let cmsg_factory = ControlMessageFactory::new();
+        let cmsg_factory = ControlMessageFactory::new(version);
         let session_manager = SessionManager::new(app_context.clone(), cmsg_factory.clone());
 
         let now = Instant::now();
@@ -115,20 +168,34 @@ impl ArrowClientContext {
             logger,
             app_context,
             cmd_channel,
+            addr: addr.to_string(),
             svc_table,
             cmsg_factory,
+            peer_version: version,
             sessions: session_manager,
             messages: VecDeque::new(),
-            expected_acks: VecDeque::new(),
+            pending_acks: HashMap::new(),
+            probes: FuturesUnordered::new(),
+            queued_probes: VecDeque::new(),
             state: ProtocolState::Handshake,
             task: None,
             redirect: None,
             closed: false,
+            shutting_down: false,
+            paused_since: None,
+            ping_period,
             last_ping: now,
             last_update_chck: now,
             last_stable_ver: 0,
+            bytes_sent: 0,
+            bytes_received: 0,
+            tracer,
         };
 
+        client
+            .app_context
+            .set_connection_state(ConnectionState::Handshaking);
+
         client.send_register_message(mac, uuid.as_bytes().clone(), passwd.as_bytes().clone());
 
         client
@@ -139,23 +206,148 @@ impl ArrowClientContext {
         self.redirect.as_ref().cloned()
     }
 
+    /// Get the latest version of the Arrow protocol used by the remote Arrow
+    /// Service.
+    fn get_peer_version(&self) -> u8 {
+        self.peer_version
+    }
+
     /// Check if the client has been closed.
     fn is_closed(&self) -> bool {
         self.closed || self.redirect.is_some()
     }
 
+    /// Close all sessions, flush all pending control messages (including HUPs
+    /// for the closed sessions) and close the connection.
+    fn shutdown(&mut self) {
+        if self.shutting_down || self.is_closed() {
+            return;
+        }
+
+        log_info!(self.logger, "closing the Arrow Service connection...");
+
+        self.shutting_down = true;
+
+        self.sessions.shutdown();
+
+        // notify the task consuming Arrow Messages
+        if let Some(task) = self.task.take() {
+            task.wake();
+        }
+    }
+
     /// Check if there is an ACK timeout.
     fn ack_timeout(&self) -> bool {
-        match self.expected_acks.front() {
-            Some(expected_ack) => expected_ack.timeout(),
-            None => false,
+        // no ACKs can be received while the connection is paused
+        if self.paused_since.is_some() {
+            return false;
+        }
+
+        self.pending_acks.values().any(|pending| pending.timeout())
+    }
+
+    /// Invoke callbacks of all control messages that have not been
+    /// acknowledged in time.
+    fn process_ack_timeouts(&mut self) -> Result<(), ArrowError> {
+        if self.paused_since.is_some() {
+            return Ok(());
+        }
+
+        let expired = self
+            .pending_acks
+            .iter()
+            .filter(|(_, pending)| pending.timeout())
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+
+        for msg_id in expired {
+            if let Some(pending) = self.pending_acks.remove(&msg_id) {
+                log_debug!(self.logger, "ACK timeout (message ID: {:04x})", msg_id);
+
+                (pending.callback)(self, None)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check if reading from the Arrow Service connection should be paused
+    /// because a service is not able to consume its data (see
+    /// `SessionManager::poll_congested()`). Deadlines of all pending ACKs
+    /// are postponed by the time the connection was paused.
+    fn poll_paused(&mut self, cx: &mut Context) -> bool {
+        let paused = self.sessions.poll_congested(cx);
+
+        match (paused, self.paused_since) {
+            (true, None) => {
+                log_debug!(
+                    self.logger,
+                    "a service is not able to consume its data, pausing the Arrow Service connection..."
+                );
+
+                self.paused_since = Some(Instant::now());
+            }
+            (false, Some(since)) => {
+                log_debug!(self.logger, "resuming the Arrow Service connection...");
+
+                let delay = since.elapsed();
+
+                for pending in self.pending_acks.values_mut() {
+                    pending.deadline += delay;
+                }
+
+                self.paused_since = None;
+            }
+            _ => (),
+        }
+
+        paused
+    }
+
+    /// Pass the numbers of transferred bytes to the application context.
+    fn flush_transferred_bytes(&mut self) {
+        if self.bytes_sent > 0 || self.bytes_received > 0 {
+            self.app_context
+                .add_transferred_bytes(self.bytes_sent, self.bytes_received);
+
+            self.bytes_sent = 0;
+            self.bytes_received = 0;
+        }
+    }
+
+    /// Record a given message using the message tracer (if any). Tracing
+    /// will be disabled for the rest of the connection if the trace cannot
+    /// be written.
+    fn trace_message(&mut self, direction: TraceDirection, msg: &ArrowMessage) {
+        if let Some(tracer) = self.tracer.as_ref() {
+            if let Err(err) = tracer.trace(direction, msg) {
+                log_warn!(
+                    self.logger,
+                    "unable to write the message trace, tracing disabled ({})",
+                    err
+                );
+
+                self.tracer = None;
+            }
+        }
+    }
+
+    /// Flush all buffered trace records (if any) into the trace file.
+    fn flush_trace(&mut self) {
+        if let Some(tracer) = self.tracer.as_ref() {
+            if let Err(err) = tracer.flush() {
+                log_warn!(self.logger, "unable to write the message trace ({})", err);
+            }
         }
     }
 
     /// Trigger all periodical tasks.
     fn time_event(&mut self) {
+        self.flush_transferred_bytes();
+
         if self.state == ProtocolState::Established {
-            if self.last_ping.elapsed() >= PING_PERIOD {
+            // there would be no response while the connection is paused
+            if self.paused_since.is_none() && self.last_ping.elapsed() >= self.ping_period {
                 self.send_ping_message();
             }
 
@@ -163,6 +355,8 @@ impl ArrowClientContext {
                 self.check_for_updates();
             }
 
+            self.sessions.check_timeouts();
+
             // notify the task consuming Arrow Messages about an ACK timeout
             if self.ack_timeout() {
                 if let Some(task) = self.task.take() {
@@ -192,12 +386,22 @@ impl ArrowClientContext {
     }
 
     /// Insert a given Control Protocol message into the output message queue
-    /// and register an expected ACK.
-    fn send_unconfirmed_control_message(&mut self, msg: ControlMessage) {
+    /// and register an expected ACK. The given callback will be invoked once
+    /// the ACK arrives or once the timeout expires.
+    fn send_unconfirmed_control_message<F>(
+        &mut self,
+        msg: ControlMessage,
+        timeout: Duration,
+        callback: F,
+    ) where
+        F: FnOnce(&mut ArrowClientContext, Option<&ControlMessage>) -> Result<(), ArrowError>
+            + Send
+            + 'static,
+    {
         let header = msg.header();
 
-        self.expected_acks
-            .push_back(ExpectedAck::new(header.msg_id));
+        self.pending_acks
+            .insert(header.msg_id, PendingAck::new(timeout, callback));
 
         self.send_control_message(msg);
     }
@@ -212,7 +416,12 @@ impl ArrowClientContext {
 
         self.last_stable_ver = self.svc_table.version();
 
-        self.send_unconfirmed_control_message(msg);
+        self.send_unconfirmed_control_message(msg, ACK_TIMEOUT, |client, ack| match ack {
+            Some(ack) => client.process_handshake_ack(ack),
+            None => Err(ArrowError::connection_error(
+                "Arrow Service connection timeout",
+            )),
+        });
     }
 
     /// Send UPDATE message.
@@ -234,9 +443,24 @@ impl ArrowClientContext {
 
         let msg = self.cmsg_factory.ping();
 
-        self.send_unconfirmed_control_message(msg);
+        let sent = Instant::now();
 
-        self.last_ping = Instant::now();
+        self.send_unconfirmed_control_message(msg, ACK_TIMEOUT, move |client, ack| match ack {
+            Some(_) => {
+                let rtt = sent.elapsed();
+
+                log_debug!(client.logger, "PING RTT: {} ms", rtt.as_millis());
+
+                client.app_context.add_rtt_sample(rtt);
+
+                Ok(())
+            }
+            None => Err(ArrowError::connection_error(
+                "Arrow Service connection timeout",
+            )),
+        });
+
+        self.last_ping = sent;
     }
 
     /// Process a given Arrow Message.
@@ -248,6 +472,10 @@ impl ArrowClientContext {
 
         let header = msg.header();
 
+        // the Arrow Service announces a newer version of the Arrow protocol
+        // by using it in its messages
+        self.peer_version = self.peer_version.max(header.version);
+
         if header.service == 0 {
             self.process_control_protocol_message(msg)?;
         } else {
@@ -259,7 +487,9 @@ impl ArrowClientContext {
 
     /// Process a given Control Protocol message.
     fn process_control_protocol_message(&mut self, msg: ArrowMessage) -> Result<(), ArrowError> {
-        let msg = ControlMessage::from_bytes(msg.payload())?
+        let version = msg.header().version;
+
+        let msg = ControlMessage::decode(version, msg.payload())?
             .expect("unable to decode an Arrow Control Protocol message");
 
         let header = msg.header();
@@ -277,8 +507,15 @@ impl ArrowClientContext {
             ControlMessageType::REDIRECT => self.process_redirect_message(msg),
             ControlMessageType::GET_STATUS => self.process_get_status_message(msg),
             ControlMessageType::GET_SCAN_REPORT => self.process_get_scan_report_message(msg),
+            ControlMessageType::GET_LOGS => self.process_get_logs_message(msg),
+            ControlMessageType::PROBE_SERVICE => self.process_probe_service_message(msg),
             ControlMessageType::RESET_SVC_TABLE => self.process_command(Command::ResetServiceTable),
             ControlMessageType::SCAN_NETWORK => self.process_command(Command::ScanNetwork),
+            ControlMessageType::ADD_SERVICE
+            | ControlMessageType::UPDATE_SERVICE
+            | ControlMessageType::ENABLE_SERVICE
+            | ControlMessageType::DISABLE_SERVICE
+            | ControlMessageType::REMOVE_SERVICE => self.process_service_message(msg),
             ControlMessageType::UNKNOWN => {
                 Err(ArrowError::other("unknow control message received"))
             }
@@ -289,33 +526,64 @@ impl ArrowClientContext {
         }
     }
 
-    /// Process a given ACK message.
+    /// Process a given ACK message. ACKs can arrive in any order.
     fn process_ack_message(&mut self, msg: ControlMessage) -> Result<(), ArrowError> {
         let header = msg.header();
 
-        if let Some(expected_ack) = self.expected_acks.pop_front() {
-            if header.msg_id == expected_ack.message_id {
-                if self.state == ProtocolState::Handshake {
-                    self.process_handshake_ack(msg)
-                } else {
-                    Ok(())
-                }
-            } else {
-                Err(ArrowError::other("unexpected ACK message ID"))
-            }
+        let msg_id = header.msg_id;
+
+        if let Some(pending) = self.pending_acks.remove(&msg_id) {
+            (pending.callback)(self, Some(&msg))
         } else {
-            Err(ArrowError::other("no ACK message expected"))
+            // the ACK might have already timed out
+            log_warn!(
+                self.logger,
+                "unexpected ACK message received (message ID: {:04x})",
+                msg_id
+            );
+
+            Ok(())
         }
     }
 
     /// Process handshake ACK.
-    fn process_handshake_ack(&mut self, msg: ControlMessage) -> Result<(), ArrowError> {
+    fn process_handshake_ack(&mut self, msg: &ControlMessage) -> Result<(), ArrowError> {
         let ack = msg.body::<AckMessage>().expect("ACK message expected");
 
         if ack.err == EC_NO_ERROR {
             // switch the protocol state into normal operation
             self.state = ProtocolState::Established;
 
+            log_debug!(
+                self.logger,
+                "connected using Arrow Protocol v{}",
+                self.cmsg_factory.version()
+            );
+
+            // the Arrow Service can accept only capabilities offered by us
+            let capabilities = ack.capabilities.unwrap_or(0) & CLIENT_CAPABILITIES;
+
+            let compression = (capabilities & CAPABILITY_COMPRESSION) != 0;
+
+            if compression {
+                log_debug!(self.logger, "session compression accepted");
+            }
+
+            self.sessions.set_compression(compression);
+
+            let flow_control = (capabilities & CAPABILITY_FLOW_CONTROL) != 0;
+
+            if flow_control {
+                log_debug!(self.logger, "session flow control accepted");
+            }
+
+            self.sessions.set_flow_control(flow_control);
+
+            self.app_context
+                .set_connection_state(ConnectionState::Connected);
+            self.app_context
+                .set_active_arrow_service(Some(self.addr.clone()));
+
             // report a fake redirect in case of the diagnostic mode
             if self.app_context.get_diagnostic_mode() {
                 self.redirect = Some(String::new());
@@ -413,15 +681,61 @@ impl ArrowClientContext {
 
         log_debug!(self.logger, "sending a STATUS message...");
 
-        let msg = self
-            .cmsg_factory
-            .status(header.msg_id, status_flags, self.sessions.len() as u32);
+        let health = self.client_health();
+
+        let msg = self.cmsg_factory.status(
+            header.msg_id,
+            status_flags,
+            self.sessions.len() as u32,
+            health,
+        );
 
         self.send_control_message(msg);
 
         Ok(())
     }
 
+    /// Collect client health details for the STATUS message.
+    fn client_health(&self) -> ClientHealth {
+        let mut service_counts = BTreeMap::new();
+
+        for (_, svc) in self.svc_table.visible() {
+            let count = service_counts
+                .entry(svc.service_type().code())
+                .or_insert(0u16);
+
+            *count = count.saturating_add(1);
+        }
+
+        let sessions = self
+            .app_context
+            .get_sessions()
+            .iter()
+            .map(|session| SessionCounters {
+                session_id: session.session_id(),
+                service_id: session.service_id(),
+                bytes_sent: session.bytes_sent(),
+                bytes_received: session.bytes_received(),
+            })
+            .collect();
+
+        let stats = self.app_context.get_connection_stats();
+
+        ClientHealth {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            uptime: self.app_context.get_uptime(),
+            last_scan: self.app_context.get_last_scan(),
+            service_counts,
+            sessions,
+            memory_usage: utils::memory_usage(),
+            log_level: Some(self.logger.get_level()),
+            log_entries: self.app_context.get_log_buffer().len() as u32,
+            reconnects: stats.reconnects() as u32,
+            backoff_failures: stats.backoff_failures(),
+            retry_delay: stats.retry_delay(),
+        }
+    }
+
     /// Process a given GET_SCAN_REPORT message.
     fn process_get_scan_report_message(&mut self, msg: ControlMessage) -> Result<(), ArrowError> {
         if self.state != ProtocolState::Established {
@@ -446,6 +760,203 @@ impl ArrowClientContext {
         Ok(())
     }
 
+    /// Process a given GET_LOGS message.
+    fn process_get_logs_message(&mut self, msg: ControlMessage) -> Result<(), ArrowError> {
+        if self.state != ProtocolState::Established {
+            return Err(ArrowError::other(
+                "cannot handle GET_LOGS message in the Handshake state",
+            ));
+        }
+
+        let header = msg.header();
+
+        let body = msg
+            .body::<GetLogsMessage>()
+            .expect("GET_LOGS message expected");
+
+        let max_entries = body.max_entries as usize;
+
+        let entries = self
+            .app_context
+            .get_log_buffer()
+            .last(max_entries, body.severity());
+
+        log_debug!(self.logger, "sending LOGS messages...");
+
+        for msg in self.cmsg_factory.logs(header.msg_id, entries) {
+            self.send_control_message(msg);
+        }
+
+        Ok(())
+    }
+
+    /// Process a given PROBE_SERVICE message. The probe runs in the
+    /// background and the PROBE_RESULT message is sent once it completes.
+    /// If there are too many probes running, the probe is queued and it will
+    /// be started once one of the running probes finishes.
+    fn process_probe_service_message(&mut self, msg: ControlMessage) -> Result<(), ArrowError> {
+        if self.state != ProtocolState::Established {
+            return Err(ArrowError::other(
+                "cannot handle PROBE_SERVICE message in the Handshake state",
+            ));
+        }
+
+        let header = msg.header();
+
+        let request_id = header.msg_id;
+
+        let service = msg
+            .body::<ServiceMessage>()
+            .expect("PROBE_SERVICE message expected")
+            .service()
+            .clone();
+
+        if self.probes.len() >= MAX_CONCURRENT_PROBES {
+            log_debug!(
+                self.logger,
+                "too many service probes, queuing PROBE_SERVICE"
+            );
+
+            self.queued_probes.push_back((request_id, service));
+        } else {
+            self.start_probe(request_id, service);
+        }
+
+        Ok(())
+    }
+
+    /// Start probing a given service in the background.
+    fn start_probe(&mut self, request_id: u16, service: Service) {
+        log_debug!(self.logger, "probing service {:?}...", service);
+
+        let probe =
+            probe::probe_service(self.logger.clone(), service).map(move |res| (request_id, res));
+
+        self.probes.push(probe.boxed());
+
+        // notify the task consuming Arrow Messages so that the probe gets
+        // polled
+        if let Some(task) = self.task.take() {
+            task.wake();
+        }
+    }
+
+    /// Create a response for a given finished service probe and start the
+    /// next queued probe (if any).
+    fn probe_finished(&mut self, request_id: u16, result: Option<ProbeResult>) -> ArrowMessage {
+        if let Some((request_id, service)) = self.queued_probes.pop_front() {
+            self.start_probe(request_id, service);
+        }
+
+        let msg = if let Some(result) = result {
+            log_debug!(self.logger, "sending a PROBE_RESULT message...");
+
+            self.cmsg_factory.probe_result(request_id, result)
+        } else {
+            log_warn!(self.logger, "service probing is not supported");
+
+            self.cmsg_factory.ack(request_id, EC_UNSUPPORTED_METHOD)
+        };
+
+        ArrowMessage::from(msg)
+    }
+
+    /// Process a given service table modification message (ADD_SERVICE,
+    /// UPDATE_SERVICE, ENABLE_SERVICE, DISABLE_SERVICE or REMOVE_SERVICE).
+    fn process_service_message(&mut self, msg: ControlMessage) -> Result<(), ArrowError> {
+        if self.state != ProtocolState::Established {
+            return Err(ArrowError::other(
+                "cannot handle service table messages in the Handshake state",
+            ));
+        }
+
+        let header = msg.header();
+
+        let mtype = header.message_type();
+
+        let res = match mtype {
+            ControlMessageType::ADD_SERVICE => {
+                let body = msg
+                    .body::<ServiceMessage>()
+                    .expect("ADD_SERVICE message expected");
+
+                let service = body.service();
+
+                if is_remotely_manageable(service) {
+                    self.app_context.add_remote_service(service.clone());
+
+                    Ok(())
+                } else {
+                    Err(EC_INVALID_SERVICE)
+                }
+            }
+            ControlMessageType::UPDATE_SERVICE => {
+                let body = msg
+                    .body::<ServiceMessage>()
+                    .expect("UPDATE_SERVICE message expected");
+
+                let service = body.service();
+
+                if is_remotely_manageable(service) {
+                    self.app_context
+                        .update_remote_service(body.svc_id(), service.clone())
+                        .map_err(service_table_error_code)
+                } else {
+                    Err(EC_INVALID_SERVICE)
+                }
+            }
+            _ => {
+                let svc_id = msg
+                    .body::<ServiceIdMessage>()
+                    .expect("service ID message expected")
+                    .svc_id;
+
+                let res = match mtype {
+                    ControlMessageType::ENABLE_SERVICE => {
+                        self.app_context.set_service_enabled(svc_id, true)
+                    }
+                    ControlMessageType::DISABLE_SERVICE => {
+                        self.app_context.set_service_enabled(svc_id, false)
+                    }
+                    _ => self.app_context.remove_service(svc_id),
+                };
+
+                res.map_err(service_table_error_code)
+            }
+        };
+
+        let error_code = match res {
+            Ok(()) => {
+                log_info!(
+                    self.logger,
+                    "service table modified by the Arrow Service ({:?})",
+                    mtype
+                );
+
+                EC_NO_ERROR
+            }
+            Err(code) => {
+                log_warn!(
+                    self.logger,
+                    "unable to process {:?} message (error code: {:08x})",
+                    mtype,
+                    code
+                );
+
+                code
+            }
+        };
+
+        let ack = self.cmsg_factory.ack(header.msg_id, error_code);
+
+        self.send_control_message(ack);
+
+        // let the Arrow Service know about the changes immediately
+        self.check_for_updates();
+
+        Ok(())
+    }
+
     /// Send a given command using the underlaying command channel.
     fn process_command(&mut self, cmd: Command) -> Result<(), ArrowError> {
         if self.state != ProtocolState::Established {
@@ -474,20 +985,42 @@ impl ArrowClientContext {
     }
 }
 
+/// Check if a given service can be added or updated remotely.
+fn is_remotely_manageable(service: &Service) -> bool {
+    matches!(
+        service.service_type(),
+        ServiceType::RTSP | ServiceType::MJPEG | ServiceType::HTTP | ServiceType::TCP
+    )
+}
+
+/// Get ACK error code for a given service table error.
+fn service_table_error_code(err: ServiceTableError) -> u32 {
+    match err {
+        ServiceTableError::NoSuchService => EC_NO_SUCH_SERVICE,
+        ServiceTableError::NotStatic | ServiceTableError::Conflict => EC_INVALID_SERVICE,
+    }
+}
+
 impl Stream for ArrowClientContext {
     type Item = Result<ArrowMessage, ArrowError>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
-        if self.ack_timeout() {
-            return Poll::Ready(Some(Err(ArrowError::connection_error(
-                "Arrow Service connection timeout",
-            ))));
+        if let Err(err) = self.process_ack_timeouts() {
+            return Poll::Ready(Some(Err(err)));
         } else if self.is_closed() {
             return Poll::Ready(None);
         } else if let Some(msg) = self.messages.pop_front() {
+            // control messages always go first
             return Poll::Ready(Some(Ok(msg)));
         }
 
+        if let Poll::Ready(Some((request_id, result))) = self.probes.poll_next_unpin(cx) {
+            return Poll::Ready(Some(Ok(self.probe_finished(request_id, result))));
+        }
+
+        // the session manager yields its own control messages (i.e. HUPs)
+        // first and then session data split into bounded chunks (one chunk
+        // per session in a round-robin fashion)
         match self.sessions.poll_next_unpin(cx) {
             Poll::Ready(Some(Ok(msg))) => return Poll::Ready(Some(Ok(msg))),
             Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
@@ -495,6 +1028,13 @@ impl Stream for ArrowClientContext {
             Poll::Pending => (),
         }
 
+        // all remaining messages have been flushed
+        if self.shutting_down {
+            self.closed = true;
+
+            return Poll::Ready(None);
+        }
+
         self.task = Some(cx.waker().clone());
 
         Poll::Pending
@@ -504,12 +1044,21 @@ impl Stream for ArrowClientContext {
 struct ArrowClient<S> {
     context: Arc<Mutex<ArrowClientContext>>,
     stream: S,
+    shutdown: Option<ShutdownSignal>,
 }
 
 impl<S> ArrowClient<S> {
     /// Create a new instance of Arrow Client.
-    fn new(app_context: ApplicationContext, cmd_channel: CommandChannel, stream: S) -> Self {
-        let context = ArrowClientContext::new(app_context, cmd_channel);
+    fn new(
+        app_context: ApplicationContext,
+        cmd_channel: CommandChannel,
+        addr: &str,
+        version: u8,
+        stream: S,
+    ) -> Self {
+        let shutdown = app_context.get_shutdown_signal();
+
+        let context = ArrowClientContext::new(app_context, cmd_channel, addr, version);
 
         let context = Arc::new(Mutex::new(context));
 
@@ -526,13 +1075,28 @@ impl<S> ArrowClient<S> {
             }
         });
 
-        Self { context, stream }
+        Self {
+            context,
+            stream,
+            shutdown: Some(shutdown),
+        }
     }
 
     /// Get redirect address (if any).
     fn get_redirect(&self) -> Option<String> {
         self.context.lock().unwrap().get_redirect()
     }
+
+    /// Get the latest version of the Arrow protocol used by the remote Arrow
+    /// Service.
+    fn get_peer_version(&self) -> u8 {
+        self.context.lock().unwrap().get_peer_version()
+    }
+
+    /// Flush all buffered trace records (if any) into the trace file.
+    fn flush_trace(&self) {
+        self.context.lock().unwrap().flush_trace()
+    }
 }
 
 impl<S> Drop for ArrowClient<S> {
@@ -542,6 +1106,8 @@ impl<S> Drop for ArrowClient<S> {
         // we must mark the context as closed so that the interval task gets terminated even in
         // case of connection/communication error
         context.closed = true;
+
+        context.flush_transferred_bytes();
     }
 }
 
@@ -556,42 +1122,66 @@ where
 
         let mut context = ctx.lock().unwrap();
 
-        // try to feed the context at least once on every poll
-        match self.stream.poll_next_unpin(cx) {
-            Poll::Ready(Some(Ok(msg))) => context.process_arrow_message(msg)?,
-            Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
-            Poll::Ready(None) => context.closed = true,
-            Poll::Pending => (),
+        if let Some(shutdown) = self.shutdown.as_mut() {
+            if shutdown.poll_unpin(cx).is_ready() {
+                self.shutdown = None;
+
+                context.shutdown();
+            }
         }
 
         loop {
-            // try to poll the context...
+            // try to feed the context (note that reading from the Arrow
+            // Service connection is paused only if a service is not able to
+            // consume its data and the Arrow Service does not support the
+            // session flow control)...
+            let fed = if context.poll_paused(cx) {
+                false
+            } else {
+                match self.stream.poll_next_unpin(cx) {
+                    Poll::Ready(Some(Ok(msg))) => {
+                        context.bytes_received += msg.size() as u64;
+                        context.trace_message(TraceDirection::Received, &msg);
+                        context.process_arrow_message(msg)?;
+                        true
+                    }
+                    Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
+                    Poll::Ready(None) => {
+                        context.closed = true;
+                        true
+                    }
+                    Poll::Pending => false,
+                }
+            };
+
+            // ... then try to poll the context
             if let Poll::Ready(ready) = context.poll_next_unpin(cx) {
+                if let Some(Ok(msg)) = &ready {
+                    context.bytes_sent += msg.size() as u64;
+                    context.trace_message(TraceDirection::Sent, msg);
+                }
+
                 return Poll::Ready(ready);
             }
 
-            // ... if it's pending, try to feed it again
-            match self.stream.poll_next_unpin(cx) {
-                Poll::Ready(Some(Ok(msg))) => context.process_arrow_message(msg)?,
-                Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
-                Poll::Ready(None) => context.closed = true,
-                Poll::Pending => return Poll::Pending,
+            if !fed {
+                return Poll::Pending;
             }
         }
     }
 }
 
-/// Connect Arrow Client to a given address and return either a redirect address or an error.
+/// Connect Arrow Client to a given address using a given transport and a given version of the
+/// Arrow protocol and return either a redirect address or an error. The version will be updated
+/// if the remote Arrow Service announces a newer supported version of the Arrow protocol.
 pub async fn connect(
     app_context: ApplicationContext,
     cmd_channel: CommandChannel,
+    transport: &dyn ArrowTransport,
     addr: &str,
+    version: &mut u8,
 ) -> Result<String, ArrowError> {
-    let tls_connector = app_context
-        .get_tls_connector()
-        .map_err(|err| ArrowError::other(format!("unable to get TLS context: {}", err)))?;
-
-    let stream = tokio::time::timeout(CONNECTION_TIMEOUT, tls_connector.connect(addr))
+    let stream = tokio::time::timeout(CONNECTION_TIMEOUT, transport.connect(addr))
         .await
         .map_err(|_| {
             ArrowError::connection_error(format!(
@@ -606,17 +1196,1214 @@ pub async fn connect(
             ))
         })?;
 
-    let framed = ArrowCodec.framed(stream);
+    run(app_context, cmd_channel, addr, version, stream).await
+}
+
+/// Run Arrow Client over a given connected stream and return either a redirect address or an
+/// error. The version will be updated if the remote Arrow Service announces a newer supported
+/// version of the Arrow protocol.
+async fn run<S>(
+    app_context: ApplicationContext,
+    cmd_channel: CommandChannel,
+    addr: &str,
+    version: &mut u8,
+    stream: S,
+) -> Result<String, ArrowError>
+where
+    S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    let framed = ArrowCodec::new(*version).framed(stream);
 
     let (mut sink, stream) = framed.split();
 
-    let mut arrow_client = ArrowClient::new(app_context, cmd_channel, stream);
+    let mut arrow_client = ArrowClient::new(app_context, cmd_channel, addr, *version, stream);
+
+    let res = sink.send_all(&mut arrow_client).await;
+
+    arrow_client.flush_trace();
+
+    if res.is_ok() {
+        // close the connection gracefully (e.g. send TLS close notify)
+        let _ = sink.close().await;
+    }
+
+    let res = res.and_then(|_| {
+        arrow_client
+            .get_redirect()
+            .ok_or_else(|| ArrowError::connection_error("connection to Arrow Service lost"))
+    });
+
+    *version = arrow_client.get_peer_version();
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::net::arrow::proto::codec::FromBytes;
+
+    use std::thread;
+
+    use std::io::{Read, Write};
+    use std::net::{SocketAddr, TcpListener};
+    use std::sync::mpsc;
+
+    use crate::cmd_handler;
+    use crate::config::{Config, ConfigBuilder};
+    use crate::context::ApplicationEventListener;
+    use crate::net::arrow::mock::{MockArrowService, MockConnection, TestCa};
+    use crate::net::arrow::proto::msg::control::{
+        EC_CONNECTION_ERROR, EC_TOO_MANY_SESSIONS, SESSION_WINDOW_SIZE,
+    };
+    use crate::net::arrow::session::MAX_CHUNK_SIZE;
+    use crate::runtime;
+    use crate::svc_table::{Service, ServiceTable};
+    use crate::utils::json::FromJson;
+    use crate::utils::logger::Severity;
+
+    /// Run Arrow Client against a mock Arrow Service driven by a given
+    /// script and return the client result.
+    fn run_client<F>(services: Vec<Service>, script: F) -> Result<String, ArrowError>
+    where
+        F: FnOnce(&mut MockConnection, ApplicationContext) + Send + 'static,
+    {
+        run_client_with_config(
+            |builder| {
+                builder.services(services);
+            },
+            script,
+        )
+    }
+
+    /// Run Arrow Client with a custom configuration against a mock Arrow
+    /// Service driven by a given script and return the client result.
+    fn run_client_with_config<C, F>(configure: C, script: F) -> Result<String, ArrowError>
+    where
+        C: FnOnce(&mut ConfigBuilder),
+        F: FnOnce(&mut MockConnection, ApplicationContext) + Send + 'static,
+    {
+        let ca = TestCa::new();
+        let service = MockArrowService::new(&ca);
+        let addr = service.address();
+
+        let mut builder = Config::builder();
+
+        builder.mac_address(Some(MacAddr::new(0, 1, 2, 3, 4, 5)));
+
+        configure(&mut builder);
+
+        let config = builder.build(ca.storage(), addr.as_str()).unwrap();
+
+        let app_context = ApplicationContext::new(config);
+
+        let script_context = app_context.clone();
+
+        let handle = thread::spawn(move || {
+            let mut connection = service.accept();
+
+            script(&mut connection, script_context);
+        });
+
+        let mut res = None;
+
+        runtime::run(async {
+            let (cmd_channel, cmd_handler) = cmd_handler::new(app_context.clone());
+
+            tokio::spawn(cmd_handler);
 
-    let send = sink.send_all(&mut arrow_client);
+            let transport = DefaultTransport::new(app_context.clone());
+
+            let mut version = MAX_ARROW_PROTOCOL_VERSION;
+
+            res = Some(connect(app_context, cmd_channel, &transport, &addr, &mut version).await);
+        });
+
+        handle.join().expect("mock Arrow Service script failed");
+
+        res.unwrap()
+    }
+
+    /// Create a new client context and return it together with ID of the
+    /// REGISTER message.
+    fn create_client_context() -> (ArrowClientContext, u16) {
+        let ca = TestCa::new();
+
+        let mut builder = Config::builder();
+
+        builder.mac_address(Some(MacAddr::new(0, 1, 2, 3, 4, 5)));
+
+        let config = builder.build(ca.storage(), "arrow.local:8900").unwrap();
+
+        let app_context = ApplicationContext::new(config);
+
+        let (cmd_channel, _) = cmd_handler::new(app_context.clone());
+
+        let mut context = ArrowClientContext::new(
+            app_context,
+            cmd_channel,
+            "arrow.local:8900",
+            MAX_ARROW_PROTOCOL_VERSION,
+        );
 
-    send.await?;
+        let register = context.messages.pop_front().unwrap();
+        let register = ControlMessage::from_bytes(register.payload())
+            .unwrap()
+            .unwrap();
 
-    arrow_client
-        .get_redirect()
-        .ok_or_else(|| ArrowError::connection_error("connection to Arrow Service lost"))
+        let register_id = register.header().msg_id;
+
+        (context, register_id)
+    }
+
+    /// Event listener collecting opened and closed sessions.
+    struct SessionListener {
+        opened: Arc<Mutex<Vec<SessionInfo>>>,
+        closed: Arc<Mutex<Vec<(SessionInfo, SessionCloseReason)>>>,
+    }
+
+    impl ApplicationEventListener for SessionListener {
+        fn session_opened(&mut self, session: &SessionInfo) {
+            self.opened.lock().unwrap().push(session.clone());
+        }
+
+        fn session_closed(&mut self, session: &SessionInfo, reason: SessionCloseReason) {
+            self.closed.lock().unwrap().push((session.clone(), reason));
+        }
+    }
+
+    /// Start a local TCP echo service accepting a single connection.
+    fn start_echo_service() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+
+            let mut buffer = [0u8; 256];
+
+            loop {
+                match stream.read(&mut buffer) {
+                    Ok(0) | Err(_) => break,
+                    Ok(len) => stream.write_all(&buffer[..len]).unwrap(),
+                }
+            }
+        });
+
+        addr
+    }
+
+    #[cfg(feature = "discovery")]
+    /// Start a local HTTP service that does not respond on its own. All
+    /// accepted connections are passed to the caller who can respond using
+    /// `respond_http()` once needed.
+    fn start_held_http_service() -> (SocketAddr, mpsc::Receiver<std::net::TcpStream>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let (tx, rx) = mpsc::channel();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if tx.send(stream.unwrap()).is_err() {
+                    break;
+                }
+            }
+        });
+
+        (addr, rx)
+    }
+
+    #[cfg(feature = "discovery")]
+    /// Read an HTTP request from a given connection and respond with an
+    /// empty 200 response.
+    fn respond_http(mut stream: std::net::TcpStream) {
+        let mut request = Vec::new();
+        let mut buffer = [0u8; 256];
+
+        while !request.ends_with(b"\r\n\r\n") {
+            match stream.read(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(len) => request.extend_from_slice(&buffer[..len]),
+            }
+        }
+
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+    }
+
+    /// Create test data of a given size. The data follow a pattern that
+    /// makes any reordering detectable.
+    fn create_flood_data(size: usize) -> Vec<u8> {
+        (0..size).map(|i| (i % 251) as u8).collect()
+    }
+
+    /// Start a local TCP service that sends a given amount of data to the
+    /// first client and then waits until the client disconnects.
+    fn start_flood_service(size: usize) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+
+            stream.write_all(&create_flood_data(size)).unwrap();
+
+            let mut buffer = [0u8; 256];
+
+            while let Ok(len) = stream.read(&mut buffer) {
+                if len == 0 {
+                    break;
+                }
+            }
+        });
+
+        addr
+    }
+
+    /// Start a local TCP service that accepts a single connection but does
+    /// not read any data from it until the returned sender gets dropped. All
+    /// data are consumed and discarded after that.
+    fn start_stalled_service() -> (SocketAddr, mpsc::Sender<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let (tx, rx) = mpsc::channel();
+
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+
+            let _ = rx.recv();
+            let _ = std::io::copy(&mut stream, &mut std::io::sink());
+        });
+
+        (addr, tx)
+    }
+
+    #[test]
+    fn test_pending_acks() {
+        let (mut context, register_id) = create_client_context();
+
+        let acks = Arc::new(Mutex::new(Vec::new()));
+
+        let mut msg_ids = Vec::new();
+
+        for _ in 0..2 {
+            let msg = context.cmsg_factory.ping();
+
+            msg_ids.push(msg.header().msg_id);
+
+            let acks = acks.clone();
+
+            context.send_unconfirmed_control_message(msg, ACK_TIMEOUT, move |_, ack| {
+                let msg_id = ack.unwrap().header().msg_id;
+
+                acks.lock().unwrap().push(msg_id);
+
+                Ok(())
+            });
+        }
+
+        // the ACKs may arrive in any order
+        let order = [msg_ids[1], register_id, msg_ids[0]];
+
+        for &msg_id in order.iter() {
+            let ack = ArrowMessage::from(ControlMessage::ack(msg_id, EC_NO_ERROR));
+
+            context.process_arrow_message(ack).unwrap();
+        }
+
+        assert_eq!(context.state, ProtocolState::Established);
+        assert_eq!(*acks.lock().unwrap(), vec![msg_ids[1], msg_ids[0]]);
+
+        // unknown ACKs are ignored
+        let ack = ArrowMessage::from(ControlMessage::ack(0xffff, EC_NO_ERROR));
+
+        assert!(context.process_arrow_message(ack).is_ok());
+        assert!(context.pending_acks.is_empty());
+    }
+
+    #[test]
+    fn test_ack_timeout() {
+        let (mut context, _) = create_client_context();
+
+        let timeouts = Arc::new(Mutex::new(0));
+
+        let msg = context.cmsg_factory.ping();
+
+        let callback_timeouts = timeouts.clone();
+
+        // a timeout that does not terminate the connection
+        context.send_unconfirmed_control_message(msg, Duration::from_secs(0), move |_, ack| {
+            assert!(ack.is_none());
+
+            *callback_timeouts.lock().unwrap() += 1;
+
+            Ok(())
+        });
+
+        assert!(context.ack_timeout());
+        assert!(context.process_ack_timeouts().is_ok());
+        assert_eq!(*timeouts.lock().unwrap(), 1);
+
+        // the REGISTER timeout terminates the connection
+        context.pending_acks.values_mut().for_each(|pending| {
+            pending.deadline = Instant::now();
+        });
+
+        assert!(context.process_ack_timeouts().is_err());
+    }
+
+    #[test]
+    fn test_paused_ack_timeout() {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        let (mut context, _) = create_client_context();
+
+        let pause = Duration::from_secs(10);
+
+        context.paused_since = Some(Instant::now() - pause);
+
+        context.pending_acks.values_mut().for_each(|pending| {
+            pending.deadline = Instant::now();
+        });
+
+        // ACKs cannot time out while the connection is paused
+        assert!(!context.ack_timeout());
+        assert!(context.process_ack_timeouts().is_ok());
+
+        // there is no congested session, so the connection gets resumed and
+        // the deadlines get postponed
+        assert!(!context.poll_paused(&mut cx));
+        assert!(context.paused_since.is_none());
+        assert!(!context.ack_timeout());
+
+        context.pending_acks.values_mut().for_each(|pending| {
+            pending.deadline -= pause;
+        });
+
+        assert!(context.ack_timeout());
+    }
+
+    #[test]
+    fn test_connection_stats() {
+        let (mut context, register_id) = create_client_context();
+
+        let ack = ArrowMessage::from(ControlMessage::ack(register_id, EC_NO_ERROR));
+
+        context.process_arrow_message(ack).unwrap();
+
+        let stats = context.app_context.get_connection_stats();
+
+        assert!(stats.uptime().is_some());
+        assert!(stats.rtt().is_none());
+
+        context.send_ping_message();
+
+        let ping = context.messages.pop_front().unwrap();
+
+        context.bytes_sent += ping.size() as u64;
+
+        let ping = ControlMessage::from_bytes(ping.payload()).unwrap().unwrap();
+
+        let ack = ArrowMessage::from(ControlMessage::ack(ping.header().msg_id, EC_NO_ERROR));
+
+        context.bytes_received += ack.size() as u64;
+        context.process_arrow_message(ack).unwrap();
+        context.flush_transferred_bytes();
+
+        let stats = context.app_context.get_connection_stats();
+
+        assert!(stats.rtt().is_some());
+        assert_eq!(stats.rtt(), stats.smoothed_rtt());
+        assert!(stats.bytes_sent() > 0);
+        assert!(stats.bytes_received() > 0);
+    }
+
+    #[test]
+    fn test_register_unauthorized() {
+        let res = run_client(Vec::new(), |connection, _| {
+            connection.expect_register(EC_UNAUTHORIZED);
+        });
+
+        assert_eq!(res.unwrap_err().kind(), ErrorKind::Unauthorized);
+    }
+
+    #[test]
+    fn test_register_internal_server_error() {
+        let res = run_client(Vec::new(), |connection, _| {
+            connection.expect_register(EC_INTERNAL_SERVER_ERROR);
+        });
+
+        assert_eq!(res.unwrap_err().kind(), ErrorKind::ArrowServerError);
+    }
+
+    #[test]
+    fn test_redirect() {
+        let res = run_client(Vec::new(), |connection, context| {
+            connection.expect_register(EC_NO_ERROR);
+            connection.ping();
+
+            assert!(context.get_active_arrow_service().is_some());
+
+            connection.redirect("arrow.local:8900");
+        });
+
+        assert_eq!(res.unwrap(), "arrow.local:8900");
+    }
+
+    #[test]
+    fn test_status_and_scan() {
+        let res = run_client(Vec::new(), |connection, _| {
+            connection.expect_register(EC_NO_ERROR);
+
+            assert_eq!(connection.get_status(), (0, 0));
+
+            connection.scan_network();
+
+            // an empty scan report contains just the empty host table and the
+            // terminating element of the service table
+            assert!(!connection.get_scan_report().is_empty());
+
+            connection.redirect("arrow.local:8900");
+        });
+
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn test_client_health() {
+        let echo_addr = start_echo_service();
+
+        let mac = MacAddr::new(0, 1, 2, 3, 4, 5);
+        let service = Service::tcp(mac, echo_addr);
+        let expected = service.to_service_identifier();
+
+        let res = run_client(vec![service], move |connection, _| {
+            let svc_table = connection.expect_register(EC_NO_ERROR);
+
+            let service_id = svc_table.get_id(&expected).unwrap();
+
+            connection.send_session_data(service_id, 1, b"hello");
+
+            assert_eq!(connection.recv_session_data(), (1, b"hello".to_vec()));
+
+            let health = connection.get_health().unwrap();
+
+            assert_eq!(health.version, env!("CARGO_PKG_VERSION"));
+            assert_eq!(
+                health.service_counts.get(&ServiceType::TCP.code()),
+                Some(&1)
+            );
+            assert_eq!(health.sessions.len(), 1);
+            assert_eq!(health.sessions[0].session_id, 1);
+            assert_eq!(health.sessions[0].service_id, service_id);
+            assert_eq!(health.sessions[0].bytes_received, 5);
+            assert!(health.last_scan.is_none());
+            assert!(health.log_level.is_some());
+
+            connection.hup(1, EC_NO_ERROR);
+            connection.redirect("arrow.local:8900");
+        });
+
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn test_message_trace() {
+        let dir = std::env::temp_dir().join(format!("arrow-client-trace-{}", std::process::id()));
+
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("trace");
+
+        let tracer = MessageTracer::new(path.clone(), 1 << 20, 0, true).unwrap();
+
+        let configure = move |builder: &mut ConfigBuilder| {
+            builder.message_tracer(Some(tracer));
+        };
+
+        let res = run_client_with_config(configure, |connection, _| {
+            connection.expect_register(EC_NO_ERROR);
+            connection.ping();
+            connection.redirect("arrow.local:8900");
+        });
+
+        assert!(res.is_ok());
+
+        let content = std::fs::read_to_string(&path).unwrap();
+
+        std::fs::remove_dir_all(&dir).ok();
+
+        let records = content
+            .lines()
+            .map(|line| TraceRecord::from_json(json::parse(line).unwrap()).unwrap())
+            .collect::<Vec<_>>();
+
+        // the client credentials must not be recorded
+        assert!(records[0].payload.is_none());
+        assert!(records[1].payload.is_some());
+
+        let records = records
+            .into_iter()
+            .map(|record| (record.direction, record.msg_type.unwrap()))
+            .collect::<Vec<_>>();
+
+        let expected = [
+            (TraceDirection::Sent, "REGISTER"),
+            (TraceDirection::Received, "ACK"),
+            (TraceDirection::Received, "PING"),
+            (TraceDirection::Sent, "ACK"),
+            (TraceDirection::Received, "REDIRECT"),
+        ];
+
+        assert_eq!(records.len(), expected.len());
+
+        for (record, expected) in records.iter().zip(expected.iter()) {
+            assert_eq!(record.0, expected.0);
+            assert_eq!(record.1, expected.1);
+        }
+    }
+
+    #[test]
+    fn test_session() {
+        let echo_addr = start_echo_service();
+
+        let mac = MacAddr::new(0, 1, 2, 3, 4, 5);
+        let service = Service::tcp(mac, echo_addr);
+        let expected = service.to_service_identifier();
+
+        let opened = Arc::new(Mutex::new(Vec::new()));
+        let closed = Arc::new(Mutex::new(Vec::new()));
+
+        let listener = SessionListener {
+            opened: opened.clone(),
+            closed: closed.clone(),
+        };
+
+        let res = run_client(vec![service], move |connection, mut context| {
+            context.add_event_listener(listener);
+
+            let svc_table = connection.expect_register(EC_NO_ERROR);
+
+            let service_id = svc_table.get_id(&expected).unwrap();
+
+            connection.send_session_data(service_id, 1, b"hello");
+
+            assert_eq!(connection.recv_session_data(), (1, b"hello".to_vec()));
+            assert_eq!(connection.get_status(), (0, 1));
+
+            let sessions = context.get_sessions();
+
+            assert_eq!(sessions.len(), 1);
+            assert_eq!(sessions[0].session_id(), 1);
+            assert_eq!(sessions[0].service_id(), service_id);
+            assert_eq!(sessions[0].address(), echo_addr);
+            assert_eq!(sessions[0].bytes_received(), 5);
+
+            connection.hup(1, EC_NO_ERROR);
+
+            assert_eq!(connection.get_status(), (0, 0));
+            assert!(context.get_sessions().is_empty());
+
+            connection.redirect("arrow.local:8900");
+        });
+
+        assert!(res.is_ok());
+
+        let opened = opened.lock().unwrap();
+        let closed = closed.lock().unwrap();
+
+        assert_eq!(opened.len(), 1);
+        assert_eq!(opened[0].session_id(), 1);
+        assert_eq!(opened[0].address(), echo_addr);
+
+        assert_eq!(closed.len(), 1);
+
+        let (info, reason) = &closed[0];
+
+        assert_eq!(info.session_id(), 1);
+        assert_eq!(info.address(), echo_addr);
+        assert_eq!(info.bytes_sent(), 5);
+        assert_eq!(info.bytes_received(), 5);
+        assert_eq!(*reason, SessionCloseReason::Hangup(EC_NO_ERROR));
+    }
+
+    #[test]
+    fn test_session_compression() {
+        use crate::net::arrow::proto::msg::ARROW_MESSAGE_FLAG_COMPRESSED;
+
+        let mac = MacAddr::new(0, 1, 2, 3, 4, 5);
+        let http = Service::http(mac, start_echo_service());
+        let tcp = Service::tcp(mac, start_echo_service());
+        let http_expected = http.to_service_identifier();
+        let tcp_expected = tcp.to_service_identifier();
+
+        let configure = move |builder: &mut ConfigBuilder| {
+            builder
+                .services(vec![http, tcp])
+                .compressed_service_types(vec![ServiceType::HTTP]);
+        };
+
+        let res = run_client_with_config(configure, move |connection, _| {
+            let svc_table = connection.expect_register_with_capabilities(CAPABILITY_COMPRESSION);
+
+            let http_id = svc_table.get_id(&http_expected).unwrap();
+            let tcp_id = svc_table.get_id(&tcp_expected).unwrap();
+
+            // TCP sessions are not compressed in this configuration
+            connection.send_session_data(tcp_id, 1, b"hello");
+
+            let msg = connection.recv_session_message();
+
+            assert!(!msg.is_compressed());
+            assert_eq!(msg.payload(), b"hello");
+
+            connection.send_session_data(http_id, 2, b"hello");
+
+            let msg = connection.recv_session_message();
+
+            let session_id = msg.header().session;
+
+            assert_eq!(session_id, 2);
+            assert_eq!(msg.is_compressed(), cfg!(feature = "compression"));
+
+            #[cfg(feature = "compression")]
+            {
+                use crate::net::arrow::compression::{PayloadCompressor, PayloadDecompressor};
+
+                let mut compressor = PayloadCompressor::new().unwrap();
+                let mut decompressor = PayloadDecompressor::new().unwrap();
+
+                let data = decompressor.decompress(msg.payload()).unwrap();
+
+                assert_eq!(&data[..], b"hello");
+
+                let msg = ArrowMessage::new(http_id, 2, compressor.compress(b"world"))
+                    .with_flags(ARROW_MESSAGE_FLAG_COMPRESSED);
+
+                connection.send(msg);
+
+                let msg = connection.recv_session_message();
+
+                let data = decompressor.decompress(msg.payload()).unwrap();
+
+                assert_eq!(&data[..], b"world");
+            }
+
+            // compressed data are not accepted by sessions without
+            // compression
+            let msg = ArrowMessage::new(tcp_id, 1, bytes::Bytes::from_static(b"hello"))
+                .with_flags(ARROW_MESSAGE_FLAG_COMPRESSED);
+
+            connection.send(msg);
+
+            assert_eq!(connection.expect_hup(1), EC_CONNECTION_ERROR);
+
+            connection.redirect("arrow.local:8900");
+        });
+
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn test_session_connection_error() {
+        // get an address where nobody listens
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let mac = MacAddr::new(0, 1, 2, 3, 4, 5);
+        let service = Service::tcp(mac, addr);
+        let expected = service.to_service_identifier();
+
+        let res = run_client(vec![service], move |connection, _| {
+            let svc_table = connection.expect_register(EC_NO_ERROR);
+
+            let service_id = svc_table.get_id(&expected).unwrap();
+
+            connection.send_session_data(service_id, 7, b"hello");
+
+            assert_eq!(connection.expect_hup(7), EC_CONNECTION_ERROR);
+
+            connection.redirect("arrow.local:8900");
+        });
+
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn test_shutdown() {
+        let echo_addr = start_echo_service();
+
+        let mac = MacAddr::new(0, 1, 2, 3, 4, 5);
+        let service = Service::tcp(mac, echo_addr);
+        let expected = service.to_service_identifier();
+
+        let closed = Arc::new(Mutex::new(Vec::new()));
+
+        let listener = SessionListener {
+            opened: Arc::new(Mutex::new(Vec::new())),
+            closed: closed.clone(),
+        };
+
+        let res = run_client(vec![service], move |connection, mut context| {
+            context.add_event_listener(listener);
+
+            let svc_table = connection.expect_register(EC_NO_ERROR);
+
+            let service_id = svc_table.get_id(&expected).unwrap();
+
+            connection.send_session_data(service_id, 1, b"hello");
+
+            assert_eq!(connection.recv_session_data(), (1, b"hello".to_vec()));
+
+            context.shutdown();
+
+            assert_eq!(connection.expect_hup(1), EC_NO_ERROR);
+
+            connection.expect_close();
+        });
+
+        assert!(res.is_err());
+
+        let closed = closed.lock().unwrap();
+
+        assert_eq!(closed.len(), 1);
+        assert_eq!(closed[0].1, SessionCloseReason::Shutdown);
+    }
+
+    #[test]
+    fn test_session_chunks() {
+        const SIZE: usize = 256 * 1024;
+
+        let flood_addr = start_flood_service(SIZE);
+
+        let mac = MacAddr::new(0, 1, 2, 3, 4, 5);
+        let service = Service::tcp(mac, flood_addr);
+        let expected = service.to_service_identifier();
+
+        let res = run_client(vec![service], move |connection, _| {
+            let svc_table = connection.expect_register(EC_NO_ERROR);
+
+            let service_id = svc_table.get_id(&expected).unwrap();
+
+            connection.send_session_data(service_id, 1, b"hello");
+
+            // control messages must get through while the session is busy
+            connection.ping();
+
+            let mut received = 0;
+
+            while received < SIZE {
+                let (session_id, data) = connection.recv_session_data();
+
+                assert_eq!(session_id, 1);
+                assert!(data.len() <= MAX_CHUNK_SIZE);
+
+                received += data.len();
+            }
+
+            assert_eq!(received, SIZE);
+
+            connection.redirect("arrow.local:8900");
+        });
+
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn test_session_chunk_order() {
+        const SIZE: usize = 256 * 1024;
+
+        let flood_addr = start_flood_service(SIZE);
+
+        let mac = MacAddr::new(0, 1, 2, 3, 4, 5);
+        let service = Service::tcp(mac, flood_addr);
+        let expected = service.to_service_identifier();
+
+        let res = run_client(vec![service], move |connection, _| {
+            let svc_table = connection.expect_register(EC_NO_ERROR);
+
+            let service_id = svc_table.get_id(&expected).unwrap();
+
+            connection.send_session_data(service_id, 1, b"hello");
+
+            let mut received = Vec::new();
+            let mut pings = VecDeque::new();
+            let mut chunks = 0;
+            let mut interleaved = false;
+
+            // keep sending PINGs while receiving the session data, the ACKs
+            // will get interleaved with the data chunks
+            while received.len() < SIZE || !pings.is_empty() {
+                if received.len() < SIZE && chunks % 8 == 0 {
+                    pings.push_back(connection.send_ping());
+                }
+
+                let msg = connection.recv_any();
+
+                if msg.header().service == 0 {
+                    let msg = ControlMessage::from_bytes(msg.payload()).unwrap().unwrap();
+
+                    let header = msg.header();
+                    let msg_id = header.msg_id;
+
+                    assert_eq!(header.message_type(), ControlMessageType::ACK);
+                    assert_eq!(Some(msg_id), pings.pop_front());
+
+                    interleaved |= received.len() < SIZE;
+                } else {
+                    let session_id = msg.header().session;
+
+                    assert_eq!(session_id, 1);
+                    assert!(msg.payload().len() <= MAX_CHUNK_SIZE);
+
+                    received.extend_from_slice(msg.payload());
+
+                    chunks += 1;
+                }
+            }
+
+            assert!(interleaved);
+            assert!(received == create_flood_data(SIZE));
+
+            connection.redirect("arrow.local:8900");
+        });
+
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn test_stalled_session() {
+        let (stalled_addr, stalled_guard) = start_stalled_service();
+
+        let mac = MacAddr::new(0, 1, 2, 3, 4, 5);
+        let stalled = Service::tcp(mac, stalled_addr);
+        let echo = Service::tcp(mac, start_echo_service());
+        let stalled_expected = stalled.to_service_identifier();
+        let echo_expected = echo.to_service_identifier();
+
+        let res = run_client(vec![stalled, echo], move |connection, _| {
+            let svc_table = connection.expect_register_with_capabilities(CAPABILITY_FLOW_CONTROL);
+
+            let stalled_id = svc_table.get_id(&stalled_expected).unwrap();
+            let echo_id = svc_table.get_id(&echo_expected).unwrap();
+
+            // use up the whole window of the stalled session
+            let chunk = vec![0u8; 64 * 1024];
+
+            for _ in 0..(SESSION_WINDOW_SIZE / chunk.len()) {
+                connection.send_session_data(stalled_id, 1, &chunk);
+            }
+
+            // the other session must not be affected, it must get more data
+            // than its window as the echo service consumes them
+            let data = create_flood_data(4 * SESSION_WINDOW_SIZE);
+
+            for chunk in data.chunks(64 * 1024) {
+                connection.wait_for_window(2, chunk.len());
+                connection.send_session_data(echo_id, 2, chunk);
+            }
+
+            let mut received = Vec::new();
+
+            while received.len() < data.len() {
+                let (session_id, chunk) = connection.recv_session_data();
+
+                assert_eq!(session_id, 2);
+
+                received.extend_from_slice(&chunk);
+            }
+
+            assert!(received == data);
+
+            // neither the control messages
+            connection.ping();
+
+            // the stalled session must not be closed
+            assert_eq!(connection.get_status(), (0, 2));
+
+            connection.redirect("arrow.local:8900");
+
+            drop(stalled_guard);
+        });
+
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn test_stalled_session_without_flow_control() {
+        const SIZE: usize = 8 * 1024 * 1024;
+
+        let (stalled_addr, stalled_guard) = start_stalled_service();
+
+        let mac = MacAddr::new(0, 1, 2, 3, 4, 5);
+        let stalled = Service::tcp(mac, stalled_addr);
+        let echo = Service::tcp(mac, start_echo_service());
+        let stalled_expected = stalled.to_service_identifier();
+        let echo_expected = echo.to_service_identifier();
+
+        let res = run_client(vec![stalled, echo], move |connection, _| {
+            let svc_table = connection.expect_register(EC_NO_ERROR);
+
+            let stalled_id = svc_table.get_id(&stalled_expected).unwrap();
+            let echo_id = svc_table.get_id(&echo_expected).unwrap();
+
+            // the client stops reading from the connection once the session
+            // gets congested, the service will start consuming the data
+            // after a while
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(500));
+
+                drop(stalled_guard);
+            });
+
+            // it's more than the socket buffers can take
+            let chunk = vec![0u8; 64 * 1024];
+
+            for _ in 0..(SIZE / chunk.len()) {
+                connection.send_session_data(stalled_id, 1, &chunk);
+            }
+
+            // the client must continue once the service catches up and the
+            // session must not be closed
+            connection.send_session_data(echo_id, 2, b"hello");
+
+            assert_eq!(connection.recv_session_data(), (2, b"hello".to_vec()));
+
+            connection.ping();
+
+            assert_eq!(connection.get_status(), (0, 2));
+
+            connection.redirect("arrow.local:8900");
+        });
+
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn test_max_sessions() {
+        let echo_addr = start_echo_service();
+
+        let mac = MacAddr::new(0, 1, 2, 3, 4, 5);
+        let service = Service::tcp(mac, echo_addr);
+        let expected = service.to_service_identifier();
+
+        let configure = |builder: &mut ConfigBuilder| {
+            builder.add_service(service).max_sessions(Some(1));
+        };
+
+        let res = run_client_with_config(configure, move |connection, _| {
+            let svc_table = connection.expect_register(EC_NO_ERROR);
+
+            let service_id = svc_table.get_id(&expected).unwrap();
+
+            connection.send_session_data(service_id, 1, b"hello");
+
+            assert_eq!(connection.recv_session_data(), (1, b"hello".to_vec()));
+
+            connection.send_session_data(service_id, 2, b"hello");
+
+            assert_eq!(connection.expect_hup(2), EC_TOO_MANY_SESSIONS);
+            assert_eq!(connection.get_status(), (0, 1));
+
+            connection.redirect("arrow.local:8900");
+        });
+
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn test_update() {
+        let mac = MacAddr::new(0, 1, 2, 3, 4, 5);
+        let addr = SocketAddr::from(([10, 0, 0, 1], 80));
+        let service = Service::http(mac, addr);
+        let expected = service.to_service_identifier();
+
+        let res = run_client(Vec::new(), move |connection, mut context| {
+            let svc_table = connection.expect_register(EC_NO_ERROR);
+
+            assert!(svc_table.get_id(&expected).is_none());
+
+            context.update_service_table(vec![service]);
+
+            let svc_table = connection.expect_update();
+
+            assert!(svc_table.get_id(&expected).is_some());
+
+            connection.redirect("arrow.local:8900");
+        });
+
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn test_service_management() {
+        let mac = MacAddr::new(0, 1, 2, 3, 4, 5);
+        let addr = SocketAddr::from(([10, 0, 0, 2], 554));
+        let service = Service::rtsp(mac, addr, "/1".to_string());
+        let fixed = Service::rtsp(mac, addr, "/2".to_string());
+        let locked = Service::locked_rtsp(mac, addr, None);
+
+        let res = run_client(Vec::new(), move |connection, _| {
+            connection.expect_register(EC_NO_ERROR);
+
+            assert_eq!(connection.add_service(service.clone()), EC_NO_ERROR);
+
+            let svc_table = connection.expect_update();
+
+            let id = svc_table
+                .get_id(&service.to_service_identifier())
+                .expect("the service has not been added");
+
+            assert_eq!(connection.replace_service(id, fixed.clone()), EC_NO_ERROR);
+            assert_eq!(connection.expect_update().get(id), Some(fixed));
+
+            assert_eq!(connection.disable_service(id), EC_NO_ERROR);
+            assert_eq!(connection.expect_update().get(id), None);
+
+            assert_eq!(connection.enable_service(id), EC_NO_ERROR);
+            assert!(connection.expect_update().get(id).is_some());
+
+            assert_eq!(connection.remove_service(id), EC_NO_ERROR);
+            assert_eq!(connection.expect_update().get(id), None);
+
+            assert_eq!(connection.remove_service(id), EC_NO_SUCH_SERVICE);
+            assert_eq!(connection.add_service(locked), EC_INVALID_SERVICE);
+
+            connection.redirect("arrow.local:8900");
+        });
+
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn test_get_logs() {
+        let res = run_client(Vec::new(), |connection, context| {
+            connection.expect_register(EC_NO_ERROR);
+
+            let mut logger = context.get_logger();
+
+            log_warn!(logger, "first warning");
+            log_debug!(logger, "debug message");
+            log_warn!(logger, "second warning");
+
+            let entries = connection.get_logs(10, Severity::WARN);
+
+            let messages = entries
+                .iter()
+                .map(|entry| entry.message())
+                .collect::<Vec<_>>();
+
+            assert_eq!(messages, vec!["first warning", "second warning"]);
+
+            let entries = connection.get_logs(1, Severity::WARN);
+
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].message(), "second warning");
+            assert_eq!(entries[0].file(), file!());
+
+            assert!(connection.get_logs(0, Severity::DEBUG).is_empty());
+
+            connection.redirect("arrow.local:8900");
+        });
+
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn test_probe_service() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let mac = MacAddr::new(0, 1, 2, 3, 4, 5);
+        let addr = listener.local_addr().unwrap();
+        let service = Service::tcp(mac, addr);
+
+        let res = run_client(Vec::new(), move |connection, _| {
+            connection.expect_register(EC_NO_ERROR);
+
+            let res = connection.probe_service(service);
+
+            if cfg!(feature = "discovery") {
+                let res = res.expect("the probe has been rejected");
+
+                assert!(res.reachable);
+                assert!(res.latency.is_some());
+                assert!(res.stream_type.is_none());
+            } else {
+                assert_eq!(res, Err(EC_UNSUPPORTED_METHOD));
+            }
+
+            drop(listener);
+
+            connection.redirect("arrow.local:8900");
+        });
+
+        assert!(res.is_ok());
+    }
+
+    #[cfg(feature = "discovery")]
+    #[test]
+    fn test_probe_service_limit() {
+        // the HTTP service holds all probes open until the test responds
+        let (addr, connections) = start_held_http_service();
+
+        let mac = MacAddr::new(0, 1, 2, 3, 4, 5);
+        let service = Service::http(mac, addr);
+
+        let res = run_client(Vec::new(), move |connection, _| {
+            connection.expect_register(EC_NO_ERROR);
+
+            let mut probes = Vec::new();
+
+            for _ in 0..MAX_CONCURRENT_PROBES {
+                probes.push(connection.send_probe_service(service.clone()));
+            }
+
+            let queued = connection.send_probe_service(service);
+
+            let mut held = Vec::new();
+
+            for _ in 0..MAX_CONCURRENT_PROBES {
+                held.push(connections.recv().unwrap());
+            }
+
+            // the queued probe must not start until a running one finishes
+            assert!(connections.try_recv().is_err());
+
+            respond_http(held.remove(0));
+
+            let (request_id, res) = connection.recv_probe_response();
+
+            assert!(probes.contains(&request_id));
+            assert!(res.expect("the probe has been rejected").reachable);
+
+            respond_http(connections.recv().unwrap());
+
+            let (request_id, res) = connection.recv_probe_response();
+
+            assert_eq!(request_id, queued);
+            assert!(res.expect("the probe has been rejected").reachable);
+
+            for stream in held {
+                respond_http(stream);
+            }
+
+            for _ in 1..MAX_CONCURRENT_PROBES {
+                let (request_id, res) = connection.recv_probe_response();
+
+                assert!(probes.contains(&request_id));
+                assert!(res.expect("the probe has been rejected").reachable);
+            }
+
+            connection.redirect("arrow.local:8900");
+        });
+
+        assert!(res.is_ok());
+    }
 }
@@ -44,8 +44,18 @@ pub trait Decode: Sized {
     fn decode(buf: &mut BytesMut) -> Result<Option<Self>, DecodeError>;
 }
 
-/// ArrowMessage codec used in tokio.
-pub struct ArrowCodec;
+/// ArrowMessage codec used in tokio. All encoded messages are stamped with
+/// a given version of the Arrow protocol.
+pub struct ArrowCodec {
+    version: u8,
+}
+
+impl ArrowCodec {
+    /// Create a new codec for a given version of the Arrow protocol.
+    pub fn new(version: u8) -> Self {
+        Self { version }
+    }
+}
 
 impl Decoder for ArrowCodec {
     type Item = ArrowMessage;
@@ -59,7 +69,8 @@ impl Decoder for ArrowCodec {
 impl Encoder<ArrowMessage> for ArrowCodec {
     type Error = ArrowError;
 
-    fn encode(&mut self, item: ArrowMessage, dst: &mut BytesMut) -> Result<(), Self::Error> {
+    fn encode(&mut self, mut item: ArrowMessage, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        item.set_version(self.version);
         item.encode(dst);
         Ok(())
     }
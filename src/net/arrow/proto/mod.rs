@@ -16,5 +16,21 @@ pub mod codec;
 pub mod error;
 pub mod msg;
 
-/// Currently supported version of the Arrow protocol.
-pub const ARROW_PROTOCOL_VERSION: u8 = 1;
+/// The oldest supported version of the Arrow protocol.
+pub const MIN_ARROW_PROTOCOL_VERSION: u8 = 1;
+
+/// The latest supported version of the Arrow protocol. Version 2 differs
+/// from version 1 in the REGISTER message (client capabilities) and in the
+/// STATUS message (client health details). It is never sent to an Arrow
+/// Service that has not used it first.
+pub const MAX_ARROW_PROTOCOL_VERSION: u8 = 2;
+
+/// Version of the Arrow protocol used for connecting to an Arrow Service for
+/// the first time. Newer versions are used only after the Arrow Service
+/// announces them.
+pub const DEFAULT_ARROW_PROTOCOL_VERSION: u8 = 1;
+
+/// Check if a given version of the Arrow protocol is supported.
+pub fn is_supported_version(version: u8) -> bool {
+    (MIN_ARROW_PROTOCOL_VERSION..=MAX_ARROW_PROTOCOL_VERSION).contains(&version)
+}
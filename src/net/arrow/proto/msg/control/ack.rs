@@ -12,43 +12,54 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::mem;
-
 use bytes::BytesMut;
 
-use crate::utils;
-
 use crate::net::arrow::proto::codec::{Encode, FromBytes};
 use crate::net::arrow::proto::error::DecodeError;
 use crate::net::arrow::proto::msg::control::ControlMessageBody;
 use crate::net::arrow::proto::msg::MessageBody;
 
-/// ACK message.
-#[repr(packed)]
+/// ACK message. An ACK for the REGISTER message may also carry the client
+/// capabilities accepted by the Arrow Service.
 pub struct AckMessage {
     pub err: u32,
+    pub capabilities: Option<u32>,
 }
 
 impl AckMessage {
     /// Create a new ACK message for a given error code.
     pub fn new(err: u32) -> Self {
-        Self { err }
+        Self {
+            err,
+            capabilities: None,
+        }
+    }
+
+    /// Add a given set of accepted client capabilities to the message.
+    #[cfg(test)]
+    pub fn with_capabilities(mut self, capabilities: u32) -> Self {
+        self.capabilities = Some(capabilities);
+        self
     }
 }
 
 impl Encode for AckMessage {
     fn encode(&self, buf: &mut BytesMut) {
-        let be_msg = Self {
-            err: self.err.to_be(),
-        };
+        buf.extend_from_slice(&self.err.to_be_bytes());
 
-        buf.extend_from_slice(utils::as_bytes(&be_msg))
+        if let Some(capabilities) = self.capabilities {
+            buf.extend_from_slice(&capabilities.to_be_bytes());
+        }
     }
 }
 
 impl MessageBody for AckMessage {
     fn len(&self) -> usize {
-        mem::size_of::<Self>()
+        if self.capabilities.is_some() {
+            8
+        } else {
+            4
+        }
     }
 }
 
@@ -56,17 +67,29 @@ impl ControlMessageBody for AckMessage {}
 
 impl FromBytes for AckMessage {
     fn from_bytes(bytes: &[u8]) -> Result<Option<Self>, DecodeError> {
-        if bytes.len() != mem::size_of::<Self>() {
+        if bytes.len() != 4 && bytes.len() != 8 {
             return Err(DecodeError::new(
                 "malformed Arrow Control Protocol ACK message",
             ));
         }
 
-        let ptr = bytes.as_ptr() as *const Self;
-        let msg = unsafe { &*ptr };
+        let mut err = [0u8; 4];
+
+        err.copy_from_slice(&bytes[..4]);
+
+        let capabilities = if bytes.len() == 8 {
+            let mut capabilities = [0u8; 4];
+
+            capabilities.copy_from_slice(&bytes[4..]);
+
+            Some(u32::from_be_bytes(capabilities))
+        } else {
+            None
+        };
 
         let res = Self {
-            err: u32::from_be(msg.err),
+            err: u32::from_be_bytes(err),
+            capabilities,
         };
 
         Ok(Some(res))
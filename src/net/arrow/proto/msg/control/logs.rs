@@ -0,0 +1,373 @@
+// Copyright 2020 Angelcam, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::mem;
+use std::str;
+
+use std::time::{Duration, UNIX_EPOCH};
+
+use bytes::BytesMut;
+
+use crate::utils;
+
+use crate::net::arrow::proto::codec::{Encode, FromBytes};
+use crate::net::arrow::proto::error::DecodeError;
+use crate::net::arrow::proto::msg::control::ControlMessageBody;
+use crate::net::arrow::proto::msg::MessageBody;
+use crate::utils::logger::ring::LogEntry;
+use crate::utils::logger::Severity;
+
+/// Flag indicating the last LOGS message of a response.
+pub const LOGS_FLAG_LAST: u8 = 0x01;
+
+/// Maximum size of encoded log entries in a single LOGS message. Larger
+/// responses are split into multiple messages.
+const MAX_LOGS_CHUNK_SIZE: usize = 16384;
+
+/// Get log severity from its code.
+pub fn severity_from_code(code: u8) -> Option<Severity> {
+    match code {
+        0 => Some(Severity::DEBUG),
+        1 => Some(Severity::INFO),
+        2 => Some(Severity::WARN),
+        3 => Some(Severity::ERROR),
+        _ => None,
+    }
+}
+
+/// GET_LOGS message.
+#[repr(packed)]
+pub struct GetLogsMessage {
+    /// Maximum number of log entries.
+    pub max_entries: u16,
+    /// Minimum severity of the log entries.
+    pub min_severity: u8,
+}
+
+impl GetLogsMessage {
+    /// Get minimum severity of the requested log entries. Unknown severity
+    /// codes are treated as ERROR.
+    pub fn severity(&self) -> Severity {
+        severity_from_code(self.min_severity).unwrap_or(Severity::ERROR)
+    }
+}
+
+impl Encode for GetLogsMessage {
+    fn encode(&self, buf: &mut BytesMut) {
+        let be_msg = Self {
+            max_entries: self.max_entries.to_be(),
+            min_severity: self.min_severity,
+        };
+
+        buf.extend_from_slice(utils::as_bytes(&be_msg))
+    }
+}
+
+impl MessageBody for GetLogsMessage {
+    fn len(&self) -> usize {
+        mem::size_of::<Self>()
+    }
+}
+
+impl ControlMessageBody for GetLogsMessage {}
+
+impl FromBytes for GetLogsMessage {
+    fn from_bytes(bytes: &[u8]) -> Result<Option<Self>, DecodeError> {
+        if bytes.len() != mem::size_of::<Self>() {
+            return Err(DecodeError::new(
+                "malformed Arrow Control Protocol GET_LOGS message",
+            ));
+        }
+
+        let ptr = bytes.as_ptr() as *const Self;
+        let msg = unsafe { &*ptr };
+
+        let res = Self {
+            max_entries: u16::from_be(msg.max_entries),
+            min_severity: msg.min_severity,
+        };
+
+        Ok(Some(res))
+    }
+}
+
+/// LOGS message header.
+#[repr(packed)]
+struct LogsMessageHeader {
+    request_id: u16,
+    flags: u8,
+    entries: u16,
+}
+
+impl<'a> From<&'a LogsMessage> for LogsMessageHeader {
+    fn from(message: &'a LogsMessage) -> Self {
+        Self {
+            request_id: message.request_id,
+            flags: message.flags,
+            entries: message.entries.len() as u16,
+        }
+    }
+}
+
+impl Encode for LogsMessageHeader {
+    fn encode(&self, buf: &mut BytesMut) {
+        let be_header = Self {
+            request_id: self.request_id.to_be(),
+            flags: self.flags,
+            entries: self.entries.to_be(),
+        };
+
+        buf.extend_from_slice(utils::as_bytes(&be_header))
+    }
+}
+
+impl FromBytes for LogsMessageHeader {
+    fn from_bytes(bytes: &[u8]) -> Result<Option<Self>, DecodeError> {
+        assert_eq!(bytes.len(), mem::size_of::<Self>());
+
+        let ptr = bytes.as_ptr() as *const Self;
+        let header = unsafe { &*ptr };
+
+        let header = Self {
+            request_id: u16::from_be(header.request_id),
+            flags: header.flags,
+            entries: u16::from_be(header.entries),
+        };
+
+        Ok(Some(header))
+    }
+}
+
+/// Log entry header.
+#[repr(packed)]
+struct EntryHeader {
+    timestamp: u64,
+    severity: u8,
+    line: u32,
+}
+
+impl<'a> From<&'a LogEntry> for EntryHeader {
+    fn from(entry: &'a LogEntry) -> Self {
+        let timestamp = entry
+            .timestamp()
+            .duration_since(UNIX_EPOCH)
+            .map(|t| t.as_millis() as u64)
+            .unwrap_or(0);
+
+        Self {
+            timestamp,
+            severity: entry.severity() as u8,
+            line: entry.line(),
+        }
+    }
+}
+
+impl Encode for EntryHeader {
+    fn encode(&self, buf: &mut BytesMut) {
+        let be_header = Self {
+            timestamp: self.timestamp.to_be(),
+            severity: self.severity,
+            line: self.line.to_be(),
+        };
+
+        buf.extend_from_slice(utils::as_bytes(&be_header))
+    }
+}
+
+impl FromBytes for EntryHeader {
+    fn from_bytes(bytes: &[u8]) -> Result<Option<Self>, DecodeError> {
+        assert_eq!(bytes.len(), mem::size_of::<Self>());
+
+        let ptr = bytes.as_ptr() as *const Self;
+        let header = unsafe { &*ptr };
+
+        let header = Self {
+            timestamp: u64::from_be(header.timestamp),
+            severity: header.severity,
+            line: u32::from_be(header.line),
+        };
+
+        Ok(Some(header))
+    }
+}
+
+/// Get encoded size of a given log entry.
+fn entry_len(entry: &LogEntry) -> usize {
+    mem::size_of::<EntryHeader>() + entry.file().len() + entry.message().len() + 2
+}
+
+/// Encode a given string as a null-terminated string (any null characters
+/// within the string are replaced).
+fn encode_string(s: &str, buf: &mut BytesMut) {
+    let start = buf.len();
+
+    buf.extend_from_slice(s.as_bytes());
+
+    for b in &mut buf[start..] {
+        if *b == 0 {
+            *b = b' ';
+        }
+    }
+
+    buf.extend_from_slice(&[0]);
+}
+
+/// Decode a null-terminated string and return it together with the number of
+/// bytes used.
+fn decode_string(bytes: &[u8]) -> Result<(&str, usize), DecodeError> {
+    let len = bytes
+        .iter()
+        .position(|&b| b == 0)
+        .ok_or_else(|| DecodeError::new("malformed log entry"))?;
+
+    let s = str::from_utf8(&bytes[..len]).map_err(|_| DecodeError::new("malformed log entry"))?;
+
+    Ok((s, len + 1))
+}
+
+/// Decode a single log entry and return it together with the number of bytes
+/// used.
+fn decode_entry(bytes: &[u8]) -> Result<(LogEntry, usize), DecodeError> {
+    let hsize = mem::size_of::<EntryHeader>();
+
+    if bytes.len() < hsize {
+        return Err(DecodeError::new("malformed log entry"));
+    }
+
+    let header =
+        EntryHeader::from_bytes(&bytes[..hsize])?.expect("unable to decode a log entry header");
+
+    let severity = severity_from_code(header.severity)
+        .ok_or_else(|| DecodeError::new("unknown log entry severity"))?;
+
+    let (file, flen) = decode_string(&bytes[hsize..])?;
+    let (message, mlen) = decode_string(&bytes[hsize + flen..])?;
+
+    let timestamp = UNIX_EPOCH + Duration::from_millis(header.timestamp);
+
+    let entry = LogEntry::new(timestamp, severity, file, header.line, message);
+
+    Ok((entry, hsize + flen + mlen))
+}
+
+/// LOGS message.
+pub struct LogsMessage {
+    /// ID of the corresponding GET_LOGS request.
+    pub request_id: u16,
+    /// LOGS message flags.
+    pub flags: u8,
+    /// Log entries.
+    pub entries: Vec<LogEntry>,
+}
+
+impl LogsMessage {
+    /// Split given log entries into a sequence of LOGS messages. The last
+    /// message of the sequence has the `LOGS_FLAG_LAST` flag set. There is
+    /// always at least one message.
+    pub fn split(request_id: u16, entries: Vec<LogEntry>) -> Vec<Self> {
+        let mut res = Vec::new();
+
+        let mut chunk = Vec::new();
+        let mut chunk_size = 0;
+
+        for entry in entries {
+            let len = entry_len(&entry);
+
+            if !chunk.is_empty() && (chunk_size + len) > MAX_LOGS_CHUNK_SIZE {
+                res.push(Self {
+                    request_id,
+                    flags: 0,
+                    entries: chunk,
+                });
+
+                chunk = Vec::new();
+                chunk_size = 0;
+            }
+
+            chunk.push(entry);
+            chunk_size += len;
+        }
+
+        res.push(Self {
+            request_id,
+            flags: LOGS_FLAG_LAST,
+            entries: chunk,
+        });
+
+        res
+    }
+}
+
+impl Encode for LogsMessage {
+    fn encode(&self, buf: &mut BytesMut) {
+        LogsMessageHeader::from(self).encode(buf);
+
+        for entry in &self.entries {
+            EntryHeader::from(entry).encode(buf);
+
+            encode_string(entry.file(), buf);
+            encode_string(entry.message(), buf);
+        }
+    }
+}
+
+impl MessageBody for LogsMessage {
+    fn len(&self) -> usize {
+        let entries = self.entries.iter().map(entry_len).sum::<usize>();
+
+        mem::size_of::<LogsMessageHeader>() + entries
+    }
+}
+
+impl ControlMessageBody for LogsMessage {}
+
+impl FromBytes for LogsMessage {
+    fn from_bytes(bytes: &[u8]) -> Result<Option<Self>, DecodeError> {
+        let hsize = mem::size_of::<LogsMessageHeader>();
+
+        if bytes.len() < hsize {
+            return Err(DecodeError::new(
+                "malformed Arrow Control Protocol LOGS message",
+            ));
+        }
+
+        let header = LogsMessageHeader::from_bytes(&bytes[..hsize])?
+            .expect("unable to decode a LOGS message header");
+
+        let mut entries = Vec::with_capacity(header.entries as usize);
+        let mut offset = hsize;
+
+        for _ in 0..header.entries {
+            let (entry, size) = decode_entry(&bytes[offset..])?;
+
+            entries.push(entry);
+
+            offset += size;
+        }
+
+        if offset != bytes.len() {
+            return Err(DecodeError::new(
+                "malformed Arrow Control Protocol LOGS message",
+            ));
+        }
+
+        let msg = Self {
+            request_id: header.request_id,
+            flags: header.flags,
+            entries,
+        };
+
+        Ok(Some(msg))
+    }
+}
@@ -14,12 +14,16 @@
 
 mod ack;
 mod hup;
+mod logs;
+mod probe;
 mod redirect;
 mod register;
 mod scan_report;
+mod service;
 mod status;
 mod svc_table;
 mod update;
+mod window;
 
 use std::mem;
 
@@ -33,34 +37,56 @@ use crate::utils;
 use crate::net::arrow::proto::codec::{Encode, FromBytes};
 use crate::net::arrow::proto::error::DecodeError;
 use crate::net::arrow::proto::msg::{ArrowMessageBody, MessageBody};
+use crate::net::arrow::proto::MAX_ARROW_PROTOCOL_VERSION;
 use crate::net::raw::ether::MacAddr;
+use crate::scanner::probe::ProbeResult;
 use crate::scanner::ScanResult;
 use crate::svc_table::ServiceTable;
+use crate::utils::logger::ring::LogEntry;
 use crate::utils::AsAny;
 
-use self::register::RegisterMessage;
 use self::scan_report::ScanReportMessage;
-use self::status::StatusMessage;
-use self::update::UpdateMessage;
 
 pub use self::ack::AckMessage;
 pub use self::hup::HupMessage;
+pub use self::logs::{GetLogsMessage, LogsMessage};
+pub use self::probe::ProbeResultMessage;
 pub use self::redirect::RedirectMessage;
+pub use self::register::RegisterMessage;
+pub use self::service::{ServiceIdMessage, ServiceMessage};
+pub use self::status::{ClientHealth, SessionCounters, StatusMessage};
 pub use self::svc_table::SimpleServiceTable;
+pub use self::update::UpdateMessage;
+pub use self::window::{WindowUpdateMessage, SESSION_WINDOW_SIZE};
 
 // status flags
 pub use self::status::STATUS_FLAG_SCAN;
 
+// client capabilities
+pub use self::register::{CAPABILITY_COMPRESSION, CAPABILITY_FLOW_CONTROL};
+
+/// Capabilities supported by this client.
+pub const CLIENT_CAPABILITIES: u32 = if cfg!(feature = "compression") {
+    CAPABILITY_FLOW_CONTROL | CAPABILITY_COMPRESSION
+} else {
+    CAPABILITY_FLOW_CONTROL
+};
+
+// LOGS message flags
+#[cfg(test)]
+pub use self::logs::LOGS_FLAG_LAST;
+
 // error codes
 pub const EC_NO_ERROR: u32 = 0x0000_0000;
 pub const EC_UNSUPPORTED_PROTOCOL_VERSION: u32 = 0x0000_0001;
 pub const EC_UNAUTHORIZED: u32 = 0x0000_0002;
 pub const EC_CONNECTION_ERROR: u32 = 0x0000_0003;
+pub const EC_UNSUPPORTED_METHOD: u32 = 0x0000_0004;
+pub const EC_TOO_MANY_SESSIONS: u32 = 0x0000_000a;
+pub const EC_NO_SUCH_SERVICE: u32 = 0x0000_000b;
+pub const EC_INVALID_SERVICE: u32 = 0x0000_000c;
 pub const EC_INTERNAL_SERVER_ERROR: u32 = 0xffff_ffff;
 
-// unused error codes
-//pub const EC_UNSUPPORTED_METHOD:           u32 = 0x0000_0004;
-
 // message type constants
 const CMSG_ACK: u16 = 0x0000;
 const CMSG_PING: u16 = 0x0001;
@@ -74,6 +100,16 @@ const CMSG_GET_STATUS: u16 = 0x0008;
 const CMSG_STATUS: u16 = 0x0009;
 const CMSG_GET_SCAN_REPORT: u16 = 0x000a;
 const CMSG_SCAN_REPORT: u16 = 0x000b;
+const CMSG_ADD_SERVICE: u16 = 0x000c;
+const CMSG_UPDATE_SERVICE: u16 = 0x000d;
+const CMSG_ENABLE_SERVICE: u16 = 0x000e;
+const CMSG_DISABLE_SERVICE: u16 = 0x000f;
+const CMSG_REMOVE_SERVICE: u16 = 0x0010;
+const CMSG_GET_LOGS: u16 = 0x0011;
+const CMSG_LOGS: u16 = 0x0012;
+const CMSG_PROBE_SERVICE: u16 = 0x0013;
+const CMSG_PROBE_RESULT: u16 = 0x0014;
+const CMSG_WINDOW_UPDATE: u16 = 0x0015;
 
 /// Arrow Control Protocol message types.
 #[allow(non_camel_case_types)]
@@ -92,6 +128,16 @@ pub enum ControlMessageType {
     UNKNOWN,
     GET_SCAN_REPORT,
     SCAN_REPORT,
+    ADD_SERVICE,
+    UPDATE_SERVICE,
+    ENABLE_SERVICE,
+    DISABLE_SERVICE,
+    REMOVE_SERVICE,
+    GET_LOGS,
+    LOGS,
+    PROBE_SERVICE,
+    PROBE_RESULT,
+    WINDOW_UPDATE,
 }
 
 impl ControlMessageType {
@@ -109,6 +155,16 @@ impl ControlMessageType {
             Self::STATUS => CMSG_STATUS,
             Self::GET_SCAN_REPORT => CMSG_GET_SCAN_REPORT,
             Self::SCAN_REPORT => CMSG_SCAN_REPORT,
+            Self::ADD_SERVICE => CMSG_ADD_SERVICE,
+            Self::UPDATE_SERVICE => CMSG_UPDATE_SERVICE,
+            Self::ENABLE_SERVICE => CMSG_ENABLE_SERVICE,
+            Self::DISABLE_SERVICE => CMSG_DISABLE_SERVICE,
+            Self::REMOVE_SERVICE => CMSG_REMOVE_SERVICE,
+            Self::GET_LOGS => CMSG_GET_LOGS,
+            Self::LOGS => CMSG_LOGS,
+            Self::PROBE_SERVICE => CMSG_PROBE_SERVICE,
+            Self::PROBE_RESULT => CMSG_PROBE_RESULT,
+            Self::WINDOW_UPDATE => CMSG_WINDOW_UPDATE,
             Self::UNKNOWN => panic!("UNKNOWN Control Protocol message type has no code"),
         }
     }
@@ -145,6 +201,16 @@ impl ControlMessageHeader {
             CMSG_STATUS => ControlMessageType::STATUS,
             CMSG_GET_SCAN_REPORT => ControlMessageType::GET_SCAN_REPORT,
             CMSG_SCAN_REPORT => ControlMessageType::SCAN_REPORT,
+            CMSG_ADD_SERVICE => ControlMessageType::ADD_SERVICE,
+            CMSG_UPDATE_SERVICE => ControlMessageType::UPDATE_SERVICE,
+            CMSG_ENABLE_SERVICE => ControlMessageType::ENABLE_SERVICE,
+            CMSG_DISABLE_SERVICE => ControlMessageType::DISABLE_SERVICE,
+            CMSG_REMOVE_SERVICE => ControlMessageType::REMOVE_SERVICE,
+            CMSG_GET_LOGS => ControlMessageType::GET_LOGS,
+            CMSG_LOGS => ControlMessageType::LOGS,
+            CMSG_PROBE_SERVICE => ControlMessageType::PROBE_SERVICE,
+            CMSG_PROBE_RESULT => ControlMessageType::PROBE_RESULT,
+            CMSG_WINDOW_UPDATE => ControlMessageType::WINDOW_UPDATE,
             _ => ControlMessageType::UNKNOWN,
         }
     }
@@ -218,15 +284,6 @@ impl ControlMessage {
         )
     }
 
-    /// Create a new STATUS Control Protocol message.
-    pub fn status(msg_id: u16, request_id: u16, status_flags: u32, active_sessions: u32) -> Self {
-        Self::new(
-            msg_id,
-            ControlMessageType::STATUS,
-            StatusMessage::new(request_id, status_flags, active_sessions),
-        )
-    }
-
     /// Create a new SCAN_REPORT Control Protocol message.
     pub fn scan_report<T>(
         msg_id: u16,
@@ -249,21 +306,6 @@ impl ControlMessage {
         Self::new(msg_id, ControlMessageType::PING, EmptyMessage)
     }
 
-    /// Create a new REGISTER Control Protocol message.
-    pub fn register(
-        msg_id: u16,
-        mac: MacAddr,
-        uuid: [u8; 16],
-        password: [u8; 16],
-        svc_table: SimpleServiceTable,
-    ) -> Self {
-        Self::new(
-            msg_id,
-            ControlMessageType::REGISTER,
-            RegisterMessage::new(mac, uuid, password, svc_table),
-        )
-    }
-
     /// Create a new UPDATE Control Protocol message.
     pub fn update(msg_id: u16, svc_table: SimpleServiceTable) -> Self {
         Self::new(
@@ -274,7 +316,7 @@ impl ControlMessage {
     }
 
     /// Create a new Control Protocol message.
-    fn new<B>(msg_id: u16, msg_type: ControlMessageType, body: B) -> Self
+    pub fn new<B>(msg_id: u16, msg_type: ControlMessageType, body: B) -> Self
     where
         B: ControlMessageBody + 'static,
     {
@@ -295,8 +337,30 @@ impl ControlMessage {
         self.body.as_ref().as_any().downcast_ref()
     }
 
-    /// Decode message body from given data according to a given message type.
+    /// Decode a Control Protocol message encoded using a given version of
+    /// the Arrow protocol.
+    pub fn decode(version: u8, bytes: &[u8]) -> Result<Option<Self>, DecodeError> {
+        let hsize = mem::size_of::<ControlMessageHeader>();
+
+        if bytes.len() < hsize {
+            return Err(DecodeError::new("malformed Arrow Control Protocol message"));
+        }
+
+        if let Some(header) = ControlMessageHeader::from_bytes(&bytes[..hsize])? {
+            let body = Self::decode_body(version, header.message_type(), &bytes[hsize..])?;
+
+            let msg = Self { header, body };
+
+            Ok(Some(msg))
+        } else {
+            panic!("unable to decode an Arrow Control Protocol message")
+        }
+    }
+
+    /// Decode message body from given data according to a given protocol
+    /// version and message type.
     fn decode_body(
+        version: u8,
         mtype: ControlMessageType,
         bytes: &[u8],
     ) -> Result<Box<dyn ControlMessageBody>, DecodeError> {
@@ -304,6 +368,19 @@ impl ControlMessage {
             ControlMessageType::ACK => Self::decode_ack_message(bytes),
             ControlMessageType::REDIRECT => Self::decode_redirect_message(bytes),
             ControlMessageType::HUP => Self::decode_hup_message(bytes),
+            ControlMessageType::REGISTER => Self::decode_register_message(version, bytes),
+            ControlMessageType::UPDATE => Self::decode_update_message(bytes),
+            ControlMessageType::STATUS => Self::decode_status_message(version, bytes),
+            ControlMessageType::ADD_SERVICE
+            | ControlMessageType::UPDATE_SERVICE
+            | ControlMessageType::PROBE_SERVICE => Self::decode_service_message(bytes),
+            ControlMessageType::ENABLE_SERVICE
+            | ControlMessageType::DISABLE_SERVICE
+            | ControlMessageType::REMOVE_SERVICE => Self::decode_service_id_message(bytes),
+            ControlMessageType::GET_LOGS => Self::decode_get_logs_message(bytes),
+            ControlMessageType::LOGS => Self::decode_logs_message(bytes),
+            ControlMessageType::PROBE_RESULT => Self::decode_probe_result_message(bytes),
+            ControlMessageType::WINDOW_UPDATE => Self::decode_window_update_message(bytes),
             ControlMessageType::PING
             | ControlMessageType::RESET_SVC_TABLE
             | ControlMessageType::SCAN_NETWORK
@@ -345,6 +422,99 @@ impl ControlMessage {
         }
     }
 
+    /// Decode a REGISTER message from given data.
+    fn decode_register_message(
+        version: u8,
+        bytes: &[u8],
+    ) -> Result<Box<dyn ControlMessageBody>, DecodeError> {
+        if let Some(msg) = RegisterMessage::decode(version, bytes)? {
+            Ok(Box::new(msg))
+        } else {
+            panic!("unable to decode an Arrow Control Protocol REGISTER message")
+        }
+    }
+
+    /// Decode an UPDATE message from given data.
+    fn decode_update_message(bytes: &[u8]) -> Result<Box<dyn ControlMessageBody>, DecodeError> {
+        if let Some(msg) = UpdateMessage::from_bytes(bytes)? {
+            Ok(Box::new(msg))
+        } else {
+            panic!("unable to decode an Arrow Control Protocol UPDATE message")
+        }
+    }
+
+    /// Decode a STATUS message from given data.
+    fn decode_status_message(
+        version: u8,
+        bytes: &[u8],
+    ) -> Result<Box<dyn ControlMessageBody>, DecodeError> {
+        if let Some(msg) = StatusMessage::decode(version, bytes)? {
+            Ok(Box::new(msg))
+        } else {
+            panic!("unable to decode an Arrow Control Protocol STATUS message")
+        }
+    }
+
+    /// Decode an ADD_SERVICE, UPDATE_SERVICE or PROBE_SERVICE message from
+    /// given data.
+    fn decode_service_message(bytes: &[u8]) -> Result<Box<dyn ControlMessageBody>, DecodeError> {
+        if let Some(msg) = ServiceMessage::from_bytes(bytes)? {
+            Ok(Box::new(msg))
+        } else {
+            panic!("unable to decode an Arrow Control Protocol service message")
+        }
+    }
+
+    /// Decode an ENABLE_SERVICE, DISABLE_SERVICE or REMOVE_SERVICE message
+    /// from given data.
+    fn decode_service_id_message(bytes: &[u8]) -> Result<Box<dyn ControlMessageBody>, DecodeError> {
+        if let Some(msg) = ServiceIdMessage::from_bytes(bytes)? {
+            Ok(Box::new(msg))
+        } else {
+            panic!("unable to decode an Arrow Control Protocol service message")
+        }
+    }
+
+    /// Decode a GET_LOGS message from given data.
+    fn decode_get_logs_message(bytes: &[u8]) -> Result<Box<dyn ControlMessageBody>, DecodeError> {
+        if let Some(msg) = GetLogsMessage::from_bytes(bytes)? {
+            Ok(Box::new(msg))
+        } else {
+            panic!("unable to decode an Arrow Control Protocol GET_LOGS message")
+        }
+    }
+
+    /// Decode a LOGS message from given data.
+    fn decode_logs_message(bytes: &[u8]) -> Result<Box<dyn ControlMessageBody>, DecodeError> {
+        if let Some(msg) = LogsMessage::from_bytes(bytes)? {
+            Ok(Box::new(msg))
+        } else {
+            panic!("unable to decode an Arrow Control Protocol LOGS message")
+        }
+    }
+
+    /// Decode a PROBE_RESULT message from given data.
+    fn decode_probe_result_message(
+        bytes: &[u8],
+    ) -> Result<Box<dyn ControlMessageBody>, DecodeError> {
+        if let Some(msg) = ProbeResultMessage::from_bytes(bytes)? {
+            Ok(Box::new(msg))
+        } else {
+            panic!("unable to decode an Arrow Control Protocol PROBE_RESULT message")
+        }
+    }
+
+    /// Decode a WINDOW_UPDATE message from given data.
+    fn decode_window_update_message(
+        bytes: &[u8],
+    ) -> Result<Box<dyn ControlMessageBody>, DecodeError> {
+        if let Some(msg) = WindowUpdateMessage::from_bytes(bytes)? {
+            Ok(Box::new(msg))
+        } else {
+            panic!("unable to decode an Arrow Control Protocol WINDOW_UPDATE message")
+        }
+    }
+
     /// Decode an empty message from given data (i.e. just check there is no data).
     fn decode_empty_message(bytes: &[u8]) -> Result<Box<dyn ControlMessageBody>, DecodeError> {
         if bytes.is_empty() {
@@ -371,39 +541,37 @@ impl MessageBody for ControlMessage {
 impl ArrowMessageBody for ControlMessage {}
 
 impl FromBytes for ControlMessage {
+    /// Decode a Control Protocol message encoded using the latest supported
+    /// version of the Arrow protocol.
     fn from_bytes(bytes: &[u8]) -> Result<Option<Self>, DecodeError> {
-        let hsize = mem::size_of::<ControlMessageHeader>();
-
-        if bytes.len() < hsize {
-            return Err(DecodeError::new("malformed Arrow Control Protocol message"));
-        }
-
-        if let Some(header) = ControlMessageHeader::from_bytes(&bytes[..hsize])? {
-            let body = Self::decode_body(header.message_type(), &bytes[hsize..])?;
-
-            let msg = Self { header, body };
-
-            Ok(Some(msg))
-        } else {
-            panic!("unable to decode an Arrow Control Protocol message")
-        }
+        Self::decode(MAX_ARROW_PROTOCOL_VERSION, bytes)
     }
 }
 
-/// Control Protocol message factory with shared message ID counter.
+/// Control Protocol message factory with shared message ID counter. The
+/// factory creates messages in the format given by a particular version of
+/// the Arrow protocol.
 #[derive(Clone)]
 pub struct ControlMessageFactory {
     counter: Arc<AtomicUsize>,
+    version: u8,
 }
 
 impl ControlMessageFactory {
-    /// Create a new Control Protocol message factory.
-    pub fn new() -> Self {
+    /// Create a new Control Protocol message factory for a given version of
+    /// the Arrow protocol.
+    pub fn new(version: u8) -> Self {
         Self {
             counter: Arc::new(AtomicUsize::new(0)),
+            version,
         }
     }
 
+    /// Get version of the Arrow protocol.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
     /// Get next message ID and increment the counter.
     fn next_id(&mut self) -> u16 {
         self.counter.fetch_add(1, Ordering::SeqCst) as u16
@@ -419,15 +587,36 @@ impl ControlMessageFactory {
         ControlMessage::hup(self.next_id(), session_id, error_code)
     }
 
-    /// Create a new STATUS message with a given request ID, flags and number
-    /// of active sessions.
+    /// Create a new WINDOW_UPDATE message extending the receive window of a
+    /// given session by a given number of bytes.
+    pub fn window_update(&mut self, session_id: u32, increment: u32) -> ControlMessage {
+        ControlMessage::new(
+            self.next_id(),
+            ControlMessageType::WINDOW_UPDATE,
+            WindowUpdateMessage::new(session_id, increment),
+        )
+    }
+
+    /// Create a new STATUS message with a given request ID, flags, number
+    /// of active sessions and client health details.
     pub fn status(
         &mut self,
         request_id: u16,
         status_flags: u32,
         active_sessions: u32,
+        health: ClientHealth,
     ) -> ControlMessage {
-        ControlMessage::status(self.next_id(), request_id, status_flags, active_sessions)
+        let msg = StatusMessage::new(request_id, status_flags, active_sessions);
+
+        // Arrow Protocol v2 extended the STATUS message with client health
+        // details
+        let msg = if self.version >= 2 {
+            msg.with_health(health)
+        } else {
+            msg
+        };
+
+        ControlMessage::new(self.next_id(), ControlMessageType::STATUS, msg)
     }
 
     /// Create a new SCAN_REPORT message for a given scan report.
@@ -443,6 +632,24 @@ impl ControlMessageFactory {
         ControlMessage::scan_report(self.next_id(), request_id, scan_result, svc_table)
     }
 
+    /// Create a sequence of LOGS messages containing given log entries.
+    pub fn logs(&mut self, request_id: u16, entries: Vec<LogEntry>) -> Vec<ControlMessage> {
+        LogsMessage::split(request_id, entries)
+            .into_iter()
+            .map(|msg| ControlMessage::new(self.next_id(), ControlMessageType::LOGS, msg))
+            .collect()
+    }
+
+    /// Create a new PROBE_RESULT message for a given request ID and probe
+    /// result.
+    pub fn probe_result(&mut self, request_id: u16, result: ProbeResult) -> ControlMessage {
+        ControlMessage::new(
+            self.next_id(),
+            ControlMessageType::PROBE_RESULT,
+            ProbeResultMessage::new(request_id, result),
+        )
+    }
+
     /// Create a new PING message.
     pub fn ping(&mut self) -> ControlMessage {
         ControlMessage::ping(self.next_id())
@@ -456,7 +663,16 @@ impl ControlMessageFactory {
         password: [u8; 16],
         svc_table: SimpleServiceTable,
     ) -> ControlMessage {
-        ControlMessage::register(self.next_id(), mac, uuid, password, svc_table)
+        let msg = RegisterMessage::new(mac, uuid, password, svc_table);
+
+        // Arrow Protocol v2 introduced client capabilities
+        let msg = if self.version >= 2 {
+            msg.with_capabilities(CLIENT_CAPABILITIES)
+        } else {
+            msg
+        };
+
+        ControlMessage::new(self.next_id(), ControlMessageType::REGISTER, msg)
     }
 
     /// Create a new UPDATE message.
@@ -464,3 +680,257 @@ impl ControlMessageFactory {
         ControlMessage::update(self.next_id(), svc_table)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::BTreeMap;
+    use std::net::{Ipv4Addr, SocketAddr};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use crate::net::arrow::proto::MIN_ARROW_PROTOCOL_VERSION;
+    use crate::scanner::probe::StreamType;
+    use crate::svc_table::{Service, SVC_TYPE_TCP};
+    use crate::utils::logger::Severity;
+
+    #[test]
+    fn test_register_versions() {
+        let mac = MacAddr::new(1, 2, 3, 4, 5, 6);
+        let addr = SocketAddr::from((Ipv4Addr::new(10, 0, 0, 1), 554));
+        let service = Service::rtsp(mac, addr, String::from("/stream"));
+
+        for version in MIN_ARROW_PROTOCOL_VERSION..=MAX_ARROW_PROTOCOL_VERSION {
+            let mut factory = ControlMessageFactory::new(version);
+
+            let svc_table = SimpleServiceTable::from(vec![(1, service.clone())]);
+
+            let msg = factory.register(mac, [1; 16], [2; 16], svc_table);
+
+            let mut buf = BytesMut::new();
+
+            msg.encode(&mut buf);
+
+            assert_eq!(buf.len(), msg.len());
+
+            let msg = ControlMessage::decode(version, &buf).unwrap().unwrap();
+
+            assert_eq!(msg.header().message_type(), ControlMessageType::REGISTER);
+
+            let register = msg.body::<RegisterMessage>().unwrap();
+
+            assert_eq!(register.capabilities().is_some(), version >= 2);
+            assert_eq!(register.svc_table.get(1), Some(service.clone()));
+
+            if let Some(capabilities) = register.capabilities() {
+                assert_eq!(capabilities, CLIENT_CAPABILITIES);
+            }
+        }
+    }
+
+    #[test]
+    fn test_ack_capabilities() {
+        let ack = AckMessage::new(EC_NO_ERROR);
+        let ack_with_capabilities = AckMessage::new(EC_NO_ERROR).with_capabilities(3);
+
+        for (ack, expected) in [(ack, None), (ack_with_capabilities, Some(3))] {
+            let msg = ControlMessage::new(1, ControlMessageType::ACK, ack);
+
+            let mut buf = BytesMut::new();
+
+            msg.encode(&mut buf);
+
+            assert_eq!(buf.len(), msg.len());
+
+            let msg = ControlMessage::from_bytes(&buf).unwrap().unwrap();
+
+            let ack = msg.body::<AckMessage>().unwrap();
+
+            assert_eq!(ack.err, EC_NO_ERROR);
+            assert_eq!(ack.capabilities, expected);
+        }
+
+        assert!(AckMessage::from_bytes(&[0; 6]).is_err());
+    }
+
+    #[test]
+    fn test_window_update() {
+        let mut factory = ControlMessageFactory::new(MAX_ARROW_PROTOCOL_VERSION);
+
+        let msg = factory.window_update(0x0100_0002, 4096);
+
+        let mut buf = BytesMut::new();
+
+        msg.encode(&mut buf);
+
+        assert_eq!(buf.len(), msg.len());
+
+        let msg = ControlMessage::from_bytes(&buf).unwrap().unwrap();
+
+        assert_eq!(
+            msg.header().message_type(),
+            ControlMessageType::WINDOW_UPDATE
+        );
+
+        let update = msg.body::<WindowUpdateMessage>().unwrap();
+
+        // the upper 8 bits of the session ID are reserved
+        let session_id = update.session_id;
+        let increment = update.increment;
+
+        assert_eq!(session_id, 2);
+        assert_eq!(increment, 4096);
+
+        assert!(WindowUpdateMessage::from_bytes(&[0; 6]).is_err());
+    }
+
+    #[test]
+    fn test_status_versions() {
+        let mut service_counts = BTreeMap::new();
+
+        service_counts.insert(SVC_TYPE_TCP, 2);
+
+        let health = ClientHealth {
+            version: String::from("1.2.3"),
+            uptime: Duration::from_secs(3600),
+            last_scan: Some((
+                UNIX_EPOCH + Duration::from_secs(1000),
+                Duration::from_secs(5),
+            )),
+            service_counts,
+            sessions: vec![SessionCounters {
+                session_id: 1,
+                service_id: 2,
+                bytes_sent: 100,
+                bytes_received: 200,
+            }],
+            memory_usage: Some(1 << 20),
+            log_level: Some(Severity::INFO),
+            log_entries: 10,
+            reconnects: 3,
+            backoff_failures: 2,
+            retry_delay: Some(Duration::from_secs(4)),
+        };
+
+        for version in MIN_ARROW_PROTOCOL_VERSION..=MAX_ARROW_PROTOCOL_VERSION {
+            let mut factory = ControlMessageFactory::new(version);
+
+            let msg = factory.status(1, STATUS_FLAG_SCAN, 1, health.clone());
+
+            let mut buf = BytesMut::new();
+
+            msg.encode(&mut buf);
+
+            assert_eq!(buf.len(), msg.len());
+
+            let msg = ControlMessage::decode(version, &buf).unwrap().unwrap();
+
+            let status = msg.body::<StatusMessage>().unwrap();
+
+            let request_id = status.request_id;
+            let status_flags = status.status_flags;
+
+            assert_eq!(request_id, 1);
+            assert_eq!(status_flags, STATUS_FLAG_SCAN);
+
+            if version >= 2 {
+                assert_eq!(status.health, Some(health.clone()));
+            } else {
+                assert!(status.health.is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn test_logs_chunks() {
+        let mut factory = ControlMessageFactory::new(MAX_ARROW_PROTOCOL_VERSION);
+
+        let messages = factory.logs(1, Vec::new());
+
+        assert_eq!(messages.len(), 1);
+
+        let logs = messages[0].body::<LogsMessage>().unwrap();
+
+        assert_eq!(logs.flags, LOGS_FLAG_LAST);
+        assert!(logs.entries.is_empty());
+
+        let entries = (0..100)
+            .map(|i| {
+                LogEntry::new(
+                    UNIX_EPOCH + Duration::from_millis(i),
+                    Severity::INFO,
+                    file!(),
+                    i as u32,
+                    "x".repeat(500),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let messages = factory.logs(2, entries.clone());
+
+        assert!(messages.len() > 1);
+
+        let mut received = Vec::new();
+
+        for (index, msg) in messages.iter().enumerate() {
+            let mut buf = BytesMut::new();
+
+            msg.encode(&mut buf);
+
+            assert_eq!(buf.len(), msg.len());
+
+            let msg = ControlMessage::from_bytes(&buf).unwrap().unwrap();
+
+            assert_eq!(msg.header().message_type(), ControlMessageType::LOGS);
+
+            let logs = msg.body::<LogsMessage>().unwrap();
+
+            let last = (index + 1) == messages.len();
+
+            assert_eq!(logs.request_id, 2);
+            assert_eq!((logs.flags & LOGS_FLAG_LAST) != 0, last);
+
+            received.extend(logs.entries.iter().cloned());
+        }
+
+        assert_eq!(received, entries);
+    }
+
+    #[test]
+    fn test_probe_result() {
+        let mut factory = ControlMessageFactory::new(MAX_ARROW_PROTOCOL_VERSION);
+
+        let results = vec![
+            ProbeResult::default(),
+            ProbeResult {
+                reachable: true,
+                stream_type: Some(StreamType::Supported),
+                status_code: Some(200),
+                codecs: vec![String::from("H264"), String::from("PCMU")],
+                latency: Some(Duration::from_millis(25)),
+            },
+        ];
+
+        for result in results {
+            let msg = factory.probe_result(3, result.clone());
+
+            let mut buf = BytesMut::new();
+
+            msg.encode(&mut buf);
+
+            assert_eq!(buf.len(), msg.len());
+
+            let msg = ControlMessage::from_bytes(&buf).unwrap().unwrap();
+
+            assert_eq!(
+                msg.header().message_type(),
+                ControlMessageType::PROBE_RESULT
+            );
+
+            let probe = msg.body::<ProbeResultMessage>().unwrap();
+
+            assert_eq!(probe.request_id, 3);
+            assert_eq!(probe.result, result);
+        }
+    }
+}
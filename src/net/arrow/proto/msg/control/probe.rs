@@ -0,0 +1,193 @@
+// Copyright 2020 Angelcam, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::mem;
+use std::str;
+
+use std::time::Duration;
+
+use bytes::BytesMut;
+
+use crate::utils;
+
+use crate::net::arrow::proto::codec::{Encode, FromBytes};
+use crate::net::arrow::proto::error::DecodeError;
+use crate::net::arrow::proto::msg::control::ControlMessageBody;
+use crate::net::arrow::proto::msg::MessageBody;
+use crate::scanner::probe::{ProbeResult, StreamType};
+
+/// Flag indicating that the probed service is reachable.
+const PROBE_FLAG_REACHABLE: u8 = 0x01;
+
+/// PROBE_RESULT message header.
+#[repr(packed)]
+struct ProbeResultMessageHeader {
+    request_id: u16,
+    flags: u8,
+    stream_type: u8,
+    status_code: u16,
+    latency: u32,
+}
+
+impl<'a> From<&'a ProbeResultMessage> for ProbeResultMessageHeader {
+    fn from(message: &'a ProbeResultMessage) -> Self {
+        let result = &message.result;
+
+        let flags = if result.reachable {
+            PROBE_FLAG_REACHABLE
+        } else {
+            0
+        };
+
+        let latency = result
+            .latency
+            .map(|latency| latency.as_millis() as u32)
+            .unwrap_or(0);
+
+        Self {
+            request_id: message.request_id,
+            flags,
+            stream_type: result.stream_type.map(StreamType::code).unwrap_or(0),
+            status_code: result.status_code.unwrap_or(0),
+            latency,
+        }
+    }
+}
+
+impl Encode for ProbeResultMessageHeader {
+    fn encode(&self, buf: &mut BytesMut) {
+        let be_header = Self {
+            request_id: self.request_id.to_be(),
+            flags: self.flags,
+            stream_type: self.stream_type,
+            status_code: self.status_code.to_be(),
+            latency: self.latency.to_be(),
+        };
+
+        buf.extend_from_slice(utils::as_bytes(&be_header))
+    }
+}
+
+impl FromBytes for ProbeResultMessageHeader {
+    fn from_bytes(bytes: &[u8]) -> Result<Option<Self>, DecodeError> {
+        assert_eq!(bytes.len(), mem::size_of::<Self>());
+
+        let ptr = bytes.as_ptr() as *const Self;
+        let header = unsafe { &*ptr };
+
+        let header = Self {
+            request_id: u16::from_be(header.request_id),
+            flags: header.flags,
+            stream_type: header.stream_type,
+            status_code: u16::from_be(header.status_code),
+            latency: u32::from_be(header.latency),
+        };
+
+        Ok(Some(header))
+    }
+}
+
+/// PROBE_RESULT message. The header is followed by a null-terminated,
+/// comma-separated list of codecs.
+pub struct ProbeResultMessage {
+    /// ID of the corresponding PROBE_SERVICE request.
+    pub request_id: u16,
+    /// Probe result.
+    pub result: ProbeResult,
+}
+
+impl ProbeResultMessage {
+    /// Create a new PROBE_RESULT message.
+    pub fn new(request_id: u16, result: ProbeResult) -> Self {
+        Self { request_id, result }
+    }
+
+    /// Get the encoded list of codecs.
+    fn codecs(&self) -> String {
+        self.result.codecs.join(",")
+    }
+}
+
+impl Encode for ProbeResultMessage {
+    fn encode(&self, buf: &mut BytesMut) {
+        ProbeResultMessageHeader::from(self).encode(buf);
+
+        buf.extend_from_slice(self.codecs().as_bytes());
+        buf.extend_from_slice(&[0]);
+    }
+}
+
+impl MessageBody for ProbeResultMessage {
+    fn len(&self) -> usize {
+        mem::size_of::<ProbeResultMessageHeader>() + self.codecs().len() + 1
+    }
+}
+
+impl ControlMessageBody for ProbeResultMessage {}
+
+impl FromBytes for ProbeResultMessage {
+    fn from_bytes(bytes: &[u8]) -> Result<Option<Self>, DecodeError> {
+        let hsize = mem::size_of::<ProbeResultMessageHeader>();
+
+        let length = bytes.len();
+
+        if length <= hsize || bytes[length - 1] != 0 {
+            return Err(DecodeError::new(
+                "malformed Arrow Control Protocol PROBE_RESULT message",
+            ));
+        }
+
+        let header = ProbeResultMessageHeader::from_bytes(&bytes[..hsize])?
+            .expect("unable to decode a PROBE_RESULT message header");
+
+        let codecs = str::from_utf8(&bytes[hsize..length - 1]).map_err(|_| {
+            DecodeError::new("malformed Arrow Control Protocol PROBE_RESULT message")
+        })?;
+
+        let codecs = codecs
+            .split(',')
+            .filter(|codec| !codec.is_empty())
+            .map(String::from)
+            .collect();
+
+        let reachable = (header.flags & PROBE_FLAG_REACHABLE) != 0;
+
+        let latency = if reachable {
+            Some(Duration::from_millis(header.latency as u64))
+        } else {
+            None
+        };
+
+        let status_code = if header.status_code == 0 {
+            None
+        } else {
+            Some(header.status_code)
+        };
+
+        let result = ProbeResult {
+            reachable,
+            stream_type: StreamType::from_code(header.stream_type),
+            status_code,
+            codecs,
+            latency,
+        };
+
+        let msg = Self {
+            request_id: header.request_id,
+            result,
+        };
+
+        Ok(Some(msg))
+    }
+}
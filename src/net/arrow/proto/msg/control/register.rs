@@ -18,14 +18,21 @@ use bytes::BytesMut;
 
 use crate::utils;
 
-use crate::net::arrow::proto::codec::Encode;
+use crate::net::arrow::proto::codec::{Encode, FromBytes};
+use crate::net::arrow::proto::error::DecodeError;
 use crate::net::arrow::proto::msg::control::{ControlMessageBody, SimpleServiceTable};
 use crate::net::arrow::proto::msg::MessageBody;
 use crate::net::raw::ether::MacAddr;
 
+/// Client capability indicating support for compressed session data.
+pub const CAPABILITY_COMPRESSION: u32 = 0x0000_0001;
+
+/// Client capability indicating support for session flow control (i.e.
+/// per-session receive windows extended using WINDOW_UPDATE messages).
+pub const CAPABILITY_FLOW_CONTROL: u32 = 0x0000_0002;
+
 /// REGISTER message header.
 #[repr(packed)]
-#[allow(dead_code)]
 struct RegisterMessageHeader {
     uuid: [u8; 16],
     mac: [u8; 6],
@@ -49,14 +56,33 @@ impl Encode for RegisterMessageHeader {
     }
 }
 
+impl FromBytes for RegisterMessageHeader {
+    fn from_bytes(bytes: &[u8]) -> Result<Option<Self>, DecodeError> {
+        assert_eq!(bytes.len(), mem::size_of::<Self>());
+
+        let ptr = bytes.as_ptr() as *const Self;
+        let header = unsafe { &*ptr };
+
+        let header = Self {
+            uuid: header.uuid,
+            mac: header.mac,
+            passwd: header.passwd,
+        };
+
+        Ok(Some(header))
+    }
+}
+
 /// REGISTER message.
 pub struct RegisterMessage {
     header: RegisterMessageHeader,
-    svc_table: SimpleServiceTable,
+    capabilities: Option<u32>,
+    /// Service table.
+    pub svc_table: SimpleServiceTable,
 }
 
 impl RegisterMessage {
-    /// Create a new REGISTER message.
+    /// Create a new REGISTER message (Arrow Protocol v1).
     pub fn new(
         mac: MacAddr,
         uuid: [u8; 16],
@@ -65,20 +91,86 @@ impl RegisterMessage {
     ) -> Self {
         let header = RegisterMessageHeader::new(mac, uuid, password);
 
-        Self { header, svc_table }
+        Self {
+            header,
+            capabilities: None,
+            svc_table,
+        }
+    }
+
+    /// Add a given set of client capabilities to the message (Arrow Protocol
+    /// v2 and later).
+    pub fn with_capabilities(mut self, capabilities: u32) -> Self {
+        self.capabilities = Some(capabilities);
+        self
+    }
+
+    /// Get client capabilities (Arrow Protocol v2 and later).
+    #[cfg(test)]
+    pub fn capabilities(&self) -> Option<u32> {
+        self.capabilities
+    }
+
+    /// Decode a REGISTER message encoded using a given version of the Arrow
+    /// protocol.
+    pub fn decode(version: u8, bytes: &[u8]) -> Result<Option<Self>, DecodeError> {
+        let mut hsize = mem::size_of::<RegisterMessageHeader>();
+
+        if version >= 2 {
+            hsize += mem::size_of::<u32>();
+        }
+
+        if bytes.len() < hsize {
+            return Err(DecodeError::new(
+                "malformed Arrow Control Protocol REGISTER message",
+            ));
+        }
+
+        let (header, rest) = bytes.split_at(mem::size_of::<RegisterMessageHeader>());
+
+        let header = RegisterMessageHeader::from_bytes(header)?
+            .expect("unable to decode an Arrow Control Protocol REGISTER message header");
+
+        let capabilities = if version >= 2 {
+            let mut capabilities = [0u8; 4];
+
+            capabilities.copy_from_slice(&rest[..4]);
+
+            Some(u32::from_be_bytes(capabilities))
+        } else {
+            None
+        };
+
+        let svc_table = SimpleServiceTable::from_bytes(&bytes[hsize..])?
+            .expect("unable to decode an Arrow Control Protocol REGISTER message service table");
+
+        let msg = Self {
+            header,
+            capabilities,
+            svc_table,
+        };
+
+        Ok(Some(msg))
     }
 }
 
 impl Encode for RegisterMessage {
     fn encode(&self, buf: &mut BytesMut) {
         self.header.encode(buf);
+
+        if let Some(capabilities) = self.capabilities {
+            buf.extend_from_slice(&capabilities.to_be_bytes());
+        }
+
         self.svc_table.encode(buf);
     }
 }
 
 impl MessageBody for RegisterMessage {
     fn len(&self) -> usize {
-        mem::size_of::<RegisterMessageHeader>() + self.svc_table.len()
+        let capabilities = self.capabilities.map(|_| mem::size_of::<u32>());
+
+        mem::size_of::<RegisterMessageHeader>() + capabilities.unwrap_or(0) + self.svc_table.len()
     }
 }
 
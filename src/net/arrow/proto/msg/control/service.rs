@@ -0,0 +1,127 @@
+// Copyright 2020 Angelcam, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::mem;
+
+use bytes::BytesMut;
+
+use crate::utils;
+
+use crate::net::arrow::proto::codec::{Encode, FromBytes};
+use crate::net::arrow::proto::error::DecodeError;
+use crate::net::arrow::proto::msg::control::svc_table::Element;
+use crate::net::arrow::proto::msg::control::ControlMessageBody;
+use crate::net::arrow::proto::msg::MessageBody;
+use crate::svc_table::Service;
+
+/// ADD_SERVICE, UPDATE_SERVICE and PROBE_SERVICE message. The service is
+/// encoded the same way as a service table element.
+pub struct ServiceMessage {
+    element: Element,
+}
+
+impl ServiceMessage {
+    /// Create a new service message for a given service ID and service.
+    #[cfg(test)]
+    pub fn new(svc_id: u16, service: Service) -> Self {
+        Self {
+            element: Element::new(svc_id, service),
+        }
+    }
+
+    /// Get the service ID (it has meaning only in the UPDATE_SERVICE
+    /// message).
+    pub fn svc_id(&self) -> u16 {
+        self.element.id
+    }
+
+    /// Get the service.
+    pub fn service(&self) -> &Service {
+        &self.element.service
+    }
+}
+
+impl Encode for ServiceMessage {
+    fn encode(&self, buf: &mut BytesMut) {
+        self.element.encode(buf)
+    }
+}
+
+impl MessageBody for ServiceMessage {
+    fn len(&self) -> usize {
+        self.element.len()
+    }
+}
+
+impl ControlMessageBody for ServiceMessage {}
+
+impl FromBytes for ServiceMessage {
+    fn from_bytes(bytes: &[u8]) -> Result<Option<Self>, DecodeError> {
+        let (element, size) = Element::decode(bytes)?;
+
+        let element = element.ok_or_else(|| DecodeError::new("invalid service type"))?;
+
+        if size != bytes.len() {
+            return Err(DecodeError::new(
+                "malformed Arrow Control Protocol service message",
+            ));
+        }
+
+        Ok(Some(Self { element }))
+    }
+}
+
+/// ENABLE_SERVICE, DISABLE_SERVICE and REMOVE_SERVICE message.
+#[repr(packed)]
+pub struct ServiceIdMessage {
+    /// Service ID.
+    pub svc_id: u16,
+}
+
+impl Encode for ServiceIdMessage {
+    fn encode(&self, buf: &mut BytesMut) {
+        let be_msg = Self {
+            svc_id: self.svc_id.to_be(),
+        };
+
+        buf.extend_from_slice(utils::as_bytes(&be_msg))
+    }
+}
+
+impl MessageBody for ServiceIdMessage {
+    fn len(&self) -> usize {
+        mem::size_of::<Self>()
+    }
+}
+
+impl ControlMessageBody for ServiceIdMessage {}
+
+impl FromBytes for ServiceIdMessage {
+    fn from_bytes(bytes: &[u8]) -> Result<Option<Self>, DecodeError> {
+        if bytes.len() != mem::size_of::<Self>() {
+            return Err(DecodeError::new(
+                "malformed Arrow Control Protocol service message",
+            ));
+        }
+
+        let ptr = bytes.as_ptr() as *const Self;
+        let msg = unsafe { &*ptr };
+
+        let res = Self {
+            svc_id: u16::from_be(msg.svc_id),
+        };
+
+        Ok(Some(res))
+    }
+}
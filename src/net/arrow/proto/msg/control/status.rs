@@ -13,26 +13,378 @@
 // limitations under the License.
 
 use std::mem;
+use std::str;
+
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use bytes::BytesMut;
 
 use crate::utils;
 
-use crate::net::arrow::proto::codec::Encode;
+use crate::net::arrow::proto::codec::{Encode, FromBytes};
+use crate::net::arrow::proto::error::DecodeError;
+use crate::net::arrow::proto::msg::control::logs::severity_from_code;
 use crate::net::arrow::proto::msg::control::ControlMessageBody;
 use crate::net::arrow::proto::msg::MessageBody;
+use crate::utils::logger::Severity;
 
 /// Status flag indicating that there is a network scan currently in progress.
 pub const STATUS_FLAG_SCAN: u32 = 0x0000_0001;
 
-/// STATUS message.
+// types of the client health records
+const HEALTH_RECORD_CLIENT_VERSION: u16 = 0x0001;
+const HEALTH_RECORD_UPTIME: u16 = 0x0002;
+const HEALTH_RECORD_LAST_SCAN: u16 = 0x0003;
+const HEALTH_RECORD_SERVICE_COUNT: u16 = 0x0004;
+const HEALTH_RECORD_SESSION: u16 = 0x0005;
+const HEALTH_RECORD_MEMORY_USAGE: u16 = 0x0006;
+const HEALTH_RECORD_LOGGER: u16 = 0x0007;
+const HEALTH_RECORD_BACKOFF: u16 = 0x0008;
+
+/// Byte counters of a single service session.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct SessionCounters {
+    /// Session ID.
+    pub session_id: u32,
+    /// Service ID.
+    pub service_id: u16,
+    /// Number of bytes sent to the Arrow Service.
+    pub bytes_sent: u64,
+    /// Number of bytes received from the Arrow Service.
+    pub bytes_received: u64,
+}
+
+/// Client health details. Arrow Protocol v2 appends them to the STATUS
+/// message as a sequence of TLV records (record type: u16, value length:
+/// u16, value). Unknown records are skipped by the decoder.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ClientHealth {
+    /// Client version.
+    pub version: String,
+    /// Time since the client was started.
+    pub uptime: Duration,
+    /// Time when the last network scan finished and its duration.
+    pub last_scan: Option<(SystemTime, Duration)>,
+    /// Number of visible services for each service type code.
+    pub service_counts: BTreeMap<u16, u16>,
+    /// Byte counters of active sessions.
+    pub sessions: Vec<SessionCounters>,
+    /// Resident memory size in bytes (if known).
+    pub memory_usage: Option<u64>,
+    /// Current log level.
+    pub log_level: Option<Severity>,
+    /// Number of log entries kept in memory.
+    pub log_entries: u32,
+    /// Number of reconnects since the client was started.
+    pub reconnects: u32,
+    /// Number of consecutive connection failures counted by the reconnect
+    /// backoff.
+    pub backoff_failures: u32,
+    /// The last delay before a reconnect.
+    pub retry_delay: Option<Duration>,
+}
+
+impl ClientHealth {
+    /// Get all health records.
+    fn records(&self) -> Vec<(u16, Vec<u8>)> {
+        let mut res = Vec::new();
+
+        res.push((
+            HEALTH_RECORD_CLIENT_VERSION,
+            self.version.as_bytes().to_vec(),
+        ));
+
+        res.push((
+            HEALTH_RECORD_UPTIME,
+            (self.uptime.as_millis() as u64).to_be_bytes().to_vec(),
+        ));
+
+        if let Some((finished, duration)) = self.last_scan {
+            let finished = finished
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64;
+
+            let mut value = Vec::new();
+
+            value.extend_from_slice(&finished.to_be_bytes());
+            value.extend_from_slice(&(duration.as_millis() as u32).to_be_bytes());
+
+            res.push((HEALTH_RECORD_LAST_SCAN, value));
+        }
+
+        for (svc_type, count) in &self.service_counts {
+            let mut value = Vec::new();
+
+            value.extend_from_slice(&svc_type.to_be_bytes());
+            value.extend_from_slice(&count.to_be_bytes());
+
+            res.push((HEALTH_RECORD_SERVICE_COUNT, value));
+        }
+
+        for session in &self.sessions {
+            let mut value = Vec::new();
+
+            value.extend_from_slice(&session.session_id.to_be_bytes());
+            value.extend_from_slice(&session.service_id.to_be_bytes());
+            value.extend_from_slice(&session.bytes_sent.to_be_bytes());
+            value.extend_from_slice(&session.bytes_received.to_be_bytes());
+
+            res.push((HEALTH_RECORD_SESSION, value));
+        }
+
+        if let Some(memory_usage) = self.memory_usage {
+            res.push((
+                HEALTH_RECORD_MEMORY_USAGE,
+                memory_usage.to_be_bytes().to_vec(),
+            ));
+        }
+
+        if let Some(level) = self.log_level {
+            let mut value = vec![level as u8];
+
+            value.extend_from_slice(&self.log_entries.to_be_bytes());
+
+            res.push((HEALTH_RECORD_LOGGER, value));
+        }
+
+        let retry_delay = self
+            .retry_delay
+            .map(|delay| delay.as_millis() as u32)
+            .unwrap_or(0);
+
+        let mut value = Vec::new();
+
+        value.extend_from_slice(&self.reconnects.to_be_bytes());
+        value.extend_from_slice(&self.backoff_failures.to_be_bytes());
+        value.extend_from_slice(&retry_delay.to_be_bytes());
+
+        res.push((HEALTH_RECORD_BACKOFF, value));
+
+        res
+    }
+
+    /// Process a given health record.
+    fn process_record(&mut self, rtype: u16, value: &[u8]) -> Result<(), DecodeError> {
+        let malformed = || DecodeError::new("malformed Arrow Control Protocol STATUS message");
+
+        match rtype {
+            HEALTH_RECORD_CLIENT_VERSION => {
+                self.version = str::from_utf8(value).map_err(|_| malformed())?.to_string();
+            }
+            HEALTH_RECORD_UPTIME => {
+                let uptime = read_u64(value).ok_or_else(malformed)?;
+
+                self.uptime = Duration::from_millis(uptime);
+            }
+            HEALTH_RECORD_LAST_SCAN => {
+                if value.len() != 12 {
+                    return Err(malformed());
+                }
+
+                let finished = read_u64(&value[..8]).ok_or_else(malformed)?;
+                let duration = read_u32(&value[8..]).ok_or_else(malformed)?;
+
+                self.last_scan = Some((
+                    UNIX_EPOCH + Duration::from_millis(finished),
+                    Duration::from_millis(duration as u64),
+                ));
+            }
+            HEALTH_RECORD_SERVICE_COUNT => {
+                if value.len() != 4 {
+                    return Err(malformed());
+                }
+
+                let svc_type = read_u16(&value[..2]).ok_or_else(malformed)?;
+                let count = read_u16(&value[2..]).ok_or_else(malformed)?;
+
+                self.service_counts.insert(svc_type, count);
+            }
+            HEALTH_RECORD_SESSION => {
+                if value.len() != 22 {
+                    return Err(malformed());
+                }
+
+                let session = SessionCounters {
+                    session_id: read_u32(&value[..4]).ok_or_else(malformed)?,
+                    service_id: read_u16(&value[4..6]).ok_or_else(malformed)?,
+                    bytes_sent: read_u64(&value[6..14]).ok_or_else(malformed)?,
+                    bytes_received: read_u64(&value[14..]).ok_or_else(malformed)?,
+                };
+
+                self.sessions.push(session);
+            }
+            HEALTH_RECORD_MEMORY_USAGE => {
+                self.memory_usage = Some(read_u64(value).ok_or_else(malformed)?);
+            }
+            HEALTH_RECORD_LOGGER => {
+                if value.len() != 5 {
+                    return Err(malformed());
+                }
+
+                self.log_level = severity_from_code(value[0]);
+                self.log_entries = read_u32(&value[1..]).ok_or_else(malformed)?;
+            }
+            HEALTH_RECORD_BACKOFF => {
+                if value.len() != 12 {
+                    return Err(malformed());
+                }
+
+                let retry_delay = read_u32(&value[8..]).ok_or_else(malformed)?;
+
+                self.reconnects = read_u32(&value[..4]).ok_or_else(malformed)?;
+                self.backoff_failures = read_u32(&value[4..8]).ok_or_else(malformed)?;
+
+                self.retry_delay = if retry_delay > 0 {
+                    Some(Duration::from_millis(retry_delay as u64))
+                } else {
+                    None
+                };
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+}
+
+impl Encode for ClientHealth {
+    fn encode(&self, buf: &mut BytesMut) {
+        for (rtype, value) in self.records() {
+            buf.extend_from_slice(&rtype.to_be_bytes());
+            buf.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            buf.extend_from_slice(&value);
+        }
+    }
+}
+
+impl MessageBody for ClientHealth {
+    fn len(&self) -> usize {
+        self.records()
+            .iter()
+            .map(|(_, value)| 4 + value.len())
+            .sum()
+    }
+}
+
+impl FromBytes for ClientHealth {
+    fn from_bytes(mut bytes: &[u8]) -> Result<Option<Self>, DecodeError> {
+        let mut res = Self::default();
+
+        while !bytes.is_empty() {
+            if bytes.len() < 4 {
+                return Err(DecodeError::new(
+                    "malformed Arrow Control Protocol STATUS message",
+                ));
+            }
+
+            let rtype = u16::from_be_bytes([bytes[0], bytes[1]]);
+            let length = u16::from_be_bytes([bytes[2], bytes[3]]) as usize;
+
+            if bytes.len() < (4 + length) {
+                return Err(DecodeError::new(
+                    "malformed Arrow Control Protocol STATUS message",
+                ));
+            }
+
+            res.process_record(rtype, &bytes[4..4 + length])?;
+
+            bytes = &bytes[4 + length..];
+        }
+
+        Ok(Some(res))
+    }
+}
+
+/// Read a big-endian u16 from a given slice of exactly two bytes.
+fn read_u16(bytes: &[u8]) -> Option<u16> {
+    let mut buf = [0u8; 2];
+
+    if bytes.len() != buf.len() {
+        return None;
+    }
+
+    buf.copy_from_slice(bytes);
+
+    Some(u16::from_be_bytes(buf))
+}
+
+/// Read a big-endian u32 from a given slice of exactly four bytes.
+fn read_u32(bytes: &[u8]) -> Option<u32> {
+    let mut buf = [0u8; 4];
+
+    if bytes.len() != buf.len() {
+        return None;
+    }
+
+    buf.copy_from_slice(bytes);
+
+    Some(u32::from_be_bytes(buf))
+}
+
+/// Read a big-endian u64 from a given slice of exactly eight bytes.
+fn read_u64(bytes: &[u8]) -> Option<u64> {
+    let mut buf = [0u8; 8];
+
+    if bytes.len() != buf.len() {
+        return None;
+    }
+
+    buf.copy_from_slice(bytes);
+
+    Some(u64::from_be_bytes(buf))
+}
+
+/// STATUS message header.
 #[repr(packed)]
-pub struct StatusMessage {
+struct StatusMessageHeader {
     request_id: u16,
     status_flags: u32,
     active_sessions: u32,
 }
 
+impl Encode for StatusMessageHeader {
+    fn encode(&self, buf: &mut BytesMut) {
+        let be_header = Self {
+            request_id: self.request_id.to_be(),
+            status_flags: self.status_flags.to_be(),
+            active_sessions: self.active_sessions.to_be(),
+        };
+
+        buf.extend_from_slice(utils::as_bytes(&be_header))
+    }
+}
+
+impl FromBytes for StatusMessageHeader {
+    fn from_bytes(bytes: &[u8]) -> Result<Option<Self>, DecodeError> {
+        assert_eq!(bytes.len(), mem::size_of::<Self>());
+
+        let ptr = bytes.as_ptr() as *const Self;
+        let header = unsafe { &*ptr };
+
+        let header = Self {
+            request_id: u16::from_be(header.request_id),
+            status_flags: u32::from_be(header.status_flags),
+            active_sessions: u32::from_be(header.active_sessions),
+        };
+
+        Ok(Some(header))
+    }
+}
+
+/// STATUS message.
+pub struct StatusMessage {
+    /// ID of the corresponding GET_STATUS request.
+    pub request_id: u16,
+    /// Status flags.
+    pub status_flags: u32,
+    /// Number of active sessions.
+    pub active_sessions: u32,
+    /// Client health details (Arrow Protocol v2 only).
+    pub health: Option<ClientHealth>,
+}
+
 impl StatusMessage {
     /// Create a new STATUS message for a given request ID, status flags and
     /// number of active sessions.
@@ -41,25 +393,68 @@ impl StatusMessage {
             request_id,
             status_flags,
             active_sessions,
+            health: None,
+        }
+    }
+
+    /// Attach given client health details.
+    pub fn with_health(mut self, health: ClientHealth) -> Self {
+        self.health = Some(health);
+        self
+    }
+
+    /// Decode STATUS message encoded using a given version of the Arrow
+    /// protocol.
+    pub fn decode(version: u8, bytes: &[u8]) -> Result<Option<Self>, DecodeError> {
+        let hsize = mem::size_of::<StatusMessageHeader>();
+
+        if bytes.len() < hsize || (version < 2 && bytes.len() != hsize) {
+            return Err(DecodeError::new(
+                "malformed Arrow Control Protocol STATUS message",
+            ));
         }
+
+        let header = StatusMessageHeader::from_bytes(&bytes[..hsize])?
+            .expect("unable to decode an Arrow Control Protocol STATUS message header");
+
+        let health = if version >= 2 {
+            ClientHealth::from_bytes(&bytes[hsize..])?
+        } else {
+            None
+        };
+
+        let msg = Self {
+            request_id: header.request_id,
+            status_flags: header.status_flags,
+            active_sessions: header.active_sessions,
+            health,
+        };
+
+        Ok(Some(msg))
     }
 }
 
 impl Encode for StatusMessage {
     fn encode(&self, buf: &mut BytesMut) {
-        let be_msg = Self {
-            request_id: self.request_id.to_be(),
-            status_flags: self.status_flags.to_be(),
-            active_sessions: self.active_sessions.to_be(),
+        let header = StatusMessageHeader {
+            request_id: self.request_id,
+            status_flags: self.status_flags,
+            active_sessions: self.active_sessions,
         };
 
-        buf.extend_from_slice(utils::as_bytes(&be_msg))
+        header.encode(buf);
+
+        if let Some(health) = self.health.as_ref() {
+            health.encode(buf);
+        }
     }
 }
 
 impl MessageBody for StatusMessage {
     fn len(&self) -> usize {
-        mem::size_of::<Self>()
+        let health = self.health.as_ref().map(|health| health.len()).unwrap_or(0);
+
+        mem::size_of::<StatusMessageHeader>() + health
     }
 }
 
@@ -15,17 +15,18 @@
 use std::mem;
 
 use std::collections::HashMap;
-use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4};
 
 use bytes::BytesMut;
 
 use crate::utils;
 
-use crate::net::arrow::proto::codec::Encode;
+use crate::net::arrow::proto::codec::{Encode, FromBytes};
+use crate::net::arrow::proto::error::DecodeError;
 use crate::net::arrow::proto::msg::MessageBody;
 use crate::net::raw::ether::MacAddr;
 use crate::net::utils::IpAddrEx;
-use crate::svc_table::{BoxServiceTable, Service, ServiceIdentifier, ServiceTable};
+use crate::svc_table::{BoxServiceTable, Service, ServiceIdentifier, ServiceTable, ServiceType};
 
 /// Service Table element header.
 #[repr(packed)]
@@ -75,16 +76,55 @@ impl Encode for ElementHeader {
     }
 }
 
+impl ElementHeader {
+    /// Get socket address of the service.
+    fn socket_address(&self) -> Result<SocketAddr, DecodeError> {
+        let ip_addr = match self.ip_version {
+            4 => {
+                let mut octets = [0u8; 4];
+
+                octets.copy_from_slice(&self.ip_addr[..4]);
+
+                IpAddr::V4(Ipv4Addr::from(octets))
+            }
+            6 => IpAddr::V6(Ipv6Addr::from(self.ip_addr)),
+            _ => return Err(DecodeError::new("invalid IP address version")),
+        };
+
+        Ok(SocketAddr::new(ip_addr, self.port))
+    }
+}
+
+impl FromBytes for ElementHeader {
+    fn from_bytes(bytes: &[u8]) -> Result<Option<Self>, DecodeError> {
+        assert_eq!(bytes.len(), mem::size_of::<Self>());
+
+        let ptr = bytes.as_ptr() as *const Self;
+        let header = unsafe { &*ptr };
+
+        let header = Self {
+            svc_id: u16::from_be(header.svc_id),
+            svc_type: u16::from_be(header.svc_type),
+            mac_addr: header.mac_addr,
+            ip_version: header.ip_version,
+            ip_addr: header.ip_addr,
+            port: u16::from_be(header.port),
+        };
+
+        Ok(Some(header))
+    }
+}
+
 /// Simple service table element.
 #[derive(Clone)]
-struct Element {
-    id: u16,
-    service: Service,
+pub(super) struct Element {
+    pub(super) id: u16,
+    pub(super) service: Service,
 }
 
 impl Element {
     /// Create a new element for the simple service table.
-    fn new(id: u16, service: Service) -> Self {
+    pub(super) fn new(id: u16, service: Service) -> Self {
         Self { id, service }
     }
 }
@@ -100,6 +140,63 @@ impl Encode for Element {
     }
 }
 
+impl Element {
+    /// Decode a single element from a given buffer. The method returns the
+    /// element (or None if this is the terminating element) and the number
+    /// of bytes used.
+    pub(super) fn decode(bytes: &[u8]) -> Result<(Option<Self>, usize), DecodeError> {
+        let hsize = mem::size_of::<ElementHeader>();
+
+        if bytes.len() < hsize {
+            return Err(DecodeError::new("malformed service table element"));
+        }
+
+        let header = ElementHeader::from_bytes(&bytes[..hsize])?
+            .expect("unable to decode a service table element header");
+
+        let plen = bytes[hsize..]
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or_else(|| DecodeError::new("malformed service table element"))?;
+
+        let path = std::str::from_utf8(&bytes[hsize..hsize + plen])
+            .map_err(|_| DecodeError::new("invalid service path"))?
+            .to_string();
+
+        let size = hsize + plen + 1;
+
+        let svc_type = ServiceType::from_code(header.svc_type)
+            .ok_or_else(|| DecodeError::new("unknown service type"))?;
+
+        if svc_type == ServiceType::ControlProtocol {
+            return Ok((None, size));
+        }
+
+        let mac = MacAddr::from_slice(&header.mac_addr);
+        let addr = header.socket_address()?;
+
+        let opt_path = if path.is_empty() {
+            None
+        } else {
+            Some(path.clone())
+        };
+
+        let service = match svc_type {
+            ServiceType::RTSP => Service::rtsp(mac, addr, path),
+            ServiceType::LockedRTSP => Service::locked_rtsp(mac, addr, opt_path),
+            ServiceType::UnknownRTSP => Service::unknown_rtsp(mac, addr),
+            ServiceType::UnsupportedRTSP => Service::unsupported_rtsp(mac, addr, path),
+            ServiceType::HTTP => Service::http(mac, addr),
+            ServiceType::MJPEG => Service::mjpeg(mac, addr, path),
+            ServiceType::LockedMJPEG => Service::locked_mjpeg(mac, addr, opt_path),
+            ServiceType::TCP => Service::tcp(mac, addr),
+            ServiceType::ControlProtocol => unreachable!(),
+        };
+
+        Ok((Some(Self::new(header.svc_id, service)), size))
+    }
+}
+
 impl MessageBody for Element {
     fn len(&self) -> usize {
         let plen = self.service.path().unwrap_or("").as_bytes().len() + 1;
@@ -184,3 +281,82 @@ impl MessageBody for SimpleServiceTable {
         len + control.len()
     }
 }
+
+impl FromBytes for SimpleServiceTable {
+    fn from_bytes(bytes: &[u8]) -> Result<Option<Self>, DecodeError> {
+        let mut map = HashMap::new();
+        let mut offset = 0;
+
+        loop {
+            let (elem, size) = Element::decode(&bytes[offset..])?;
+
+            offset += size;
+
+            if let Some(elem) = elem {
+                map.insert(elem.id, elem);
+            } else if offset == bytes.len() {
+                return Ok(Some(Self { map }));
+            } else {
+                return Err(DecodeError::new("malformed service table"));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::net::SocketAddrV6;
+
+    #[test]
+    fn test_encode_decode() {
+        let mac = MacAddr::new(1, 2, 3, 4, 5, 6);
+
+        let addr_v4 = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 1), 554));
+        let addr_v6 = SocketAddr::V6(SocketAddrV6::new(
+            Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1),
+            80,
+            0,
+            0,
+        ));
+
+        let services = vec![
+            (1, Service::rtsp(mac, addr_v4, String::from("/stream"))),
+            (2, Service::locked_rtsp(mac, addr_v4, None)),
+            (3, Service::http(mac, addr_v6)),
+            (4, Service::tcp(mac, addr_v4)),
+        ];
+
+        let table = SimpleServiceTable::from(services.clone());
+
+        let mut buf = BytesMut::new();
+
+        table.encode(&mut buf);
+
+        assert_eq!(buf.len(), table.len());
+
+        let decoded = SimpleServiceTable::from_bytes(&buf).unwrap().unwrap();
+
+        for (id, service) in services {
+            assert_eq!(decoded.get(id), Some(service));
+        }
+
+        assert_eq!(decoded.get(5), None);
+    }
+
+    #[test]
+    fn test_decode_malformed() {
+        let table = SimpleServiceTable::from(Vec::new());
+
+        let mut buf = BytesMut::new();
+
+        table.encode(&mut buf);
+
+        assert!(SimpleServiceTable::from_bytes(&buf[..buf.len() - 1]).is_err());
+
+        buf.extend_from_slice(&[0]);
+
+        assert!(SimpleServiceTable::from_bytes(&buf).is_err());
+    }
+}
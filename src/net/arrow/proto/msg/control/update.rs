@@ -14,13 +14,15 @@
 
 use bytes::BytesMut;
 
-use crate::net::arrow::proto::codec::Encode;
+use crate::net::arrow::proto::codec::{Encode, FromBytes};
+use crate::net::arrow::proto::error::DecodeError;
 use crate::net::arrow::proto::msg::control::{ControlMessageBody, SimpleServiceTable};
 use crate::net::arrow::proto::msg::MessageBody;
 
 /// UPDATE message.
 pub struct UpdateMessage {
-    svc_table: SimpleServiceTable,
+    /// Service table.
+    pub svc_table: SimpleServiceTable,
 }
 
 impl UpdateMessage {
@@ -43,3 +45,12 @@ impl MessageBody for UpdateMessage {
 }
 
 impl ControlMessageBody for UpdateMessage {}
+
+impl FromBytes for UpdateMessage {
+    fn from_bytes(bytes: &[u8]) -> Result<Option<Self>, DecodeError> {
+        let svc_table = SimpleServiceTable::from_bytes(bytes)?
+            .expect("unable to decode an Arrow Control Protocol UPDATE message service table");
+
+        Ok(Some(Self { svc_table }))
+    }
+}
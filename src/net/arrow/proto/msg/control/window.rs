@@ -0,0 +1,90 @@
+// Copyright 2017 click2stream, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::mem;
+
+use bytes::BytesMut;
+
+use crate::utils;
+
+use crate::net::arrow::proto::codec::{Encode, FromBytes};
+use crate::net::arrow::proto::error::DecodeError;
+use crate::net::arrow::proto::msg::control::ControlMessageBody;
+use crate::net::arrow::proto::msg::MessageBody;
+
+/// Initial size of the receive window of every session (in bytes of session
+/// data payload). If the session flow control has been negotiated, the Arrow
+/// Service must not send more session data than the window allows. The
+/// window is extended using WINDOW_UPDATE messages as the service consumes
+/// the data.
+pub const SESSION_WINDOW_SIZE: usize = 1024 * 1024;
+
+/// WINDOW_UPDATE message.
+#[repr(packed)]
+pub struct WindowUpdateMessage {
+    /// Session ID (note: the upper 8 bits are reserved).
+    pub session_id: u32,
+    /// Number of bytes the receive window is extended by.
+    pub increment: u32,
+}
+
+impl WindowUpdateMessage {
+    /// Create a new WINDOW_UPDATE message for a given session ID and window
+    /// increment.
+    pub fn new(session_id: u32, increment: u32) -> Self {
+        Self {
+            session_id: session_id & ((1 << 24) - 1),
+            increment,
+        }
+    }
+}
+
+impl Encode for WindowUpdateMessage {
+    fn encode(&self, buf: &mut BytesMut) {
+        let be_msg = Self {
+            session_id: self.session_id.to_be(),
+            increment: self.increment.to_be(),
+        };
+
+        buf.extend_from_slice(utils::as_bytes(&be_msg))
+    }
+}
+
+impl MessageBody for WindowUpdateMessage {
+    fn len(&self) -> usize {
+        mem::size_of::<Self>()
+    }
+}
+
+impl ControlMessageBody for WindowUpdateMessage {}
+
+impl FromBytes for WindowUpdateMessage {
+    fn from_bytes(bytes: &[u8]) -> Result<Option<Self>, DecodeError> {
+        if bytes.len() != mem::size_of::<Self>() {
+            return Err(DecodeError::new(
+                "malformed Arrow Control Protocol WINDOW_UPDATE message",
+            ));
+        }
+
+        let ptr = bytes.as_ptr() as *const Self;
+        let msg = unsafe { &*ptr };
+
+        let res = Self {
+            session_id: u32::from_be(msg.session_id),
+            increment: u32::from_be(msg.increment),
+        };
+
+        Ok(Some(res))
+    }
+}
@@ -22,11 +22,15 @@ use crate::utils;
 
 use crate::net::arrow::proto::codec::{Decode, Encode, FromBytes};
 use crate::net::arrow::proto::error::DecodeError;
-use crate::net::arrow::proto::ARROW_PROTOCOL_VERSION;
+use crate::net::arrow::proto::{self, MAX_ARROW_PROTOCOL_VERSION};
 use crate::utils::AsAny;
 
 pub use crate::net::arrow::proto::msg::control::ControlMessage;
 
+/// Arrow Message flag indicating that the payload is compressed (the flags
+/// are stored in the upper 8 bits of the session ID).
+pub const ARROW_MESSAGE_FLAG_COMPRESSED: u8 = 0x01;
+
 /// Common trait for message body types.
 pub trait MessageBody: Encode {
     /// Get size of the body in bytes.
@@ -47,18 +51,20 @@ pub struct ArrowMessageHeader {
     pub version: u8,
     /// Service ID.
     pub service: u16,
-    /// Session ID (note: the upper 8 bits are reserved).
+    /// Session ID (note: the upper 8 bits, originally reserved, carry message
+    /// flags; they are not part of the session ID and they are stripped when
+    /// a header is decoded).
     pub session: u32,
     /// Payload size.
     size: u32,
 }
 
 impl ArrowMessageHeader {
-    /// Create a new Arrow Message header with a given service ID, session ID
-    /// and payload size.
-    fn new(service: u16, session: u32, size: u32) -> Self {
+    /// Create a new Arrow Message header with a given protocol version,
+    /// service ID, session ID and payload size.
+    fn new(version: u8, service: u16, session: u32, size: u32) -> Self {
         Self {
-            version: ARROW_PROTOCOL_VERSION,
+            version,
             service,
             session: session & ((1 << 24) - 1),
             size,
@@ -93,7 +99,7 @@ impl FromBytes for ArrowMessageHeader {
             size: u32::from_be(header.size),
         };
 
-        if res.version == ARROW_PROTOCOL_VERSION {
+        if proto::is_supported_version(res.version) {
             Ok(Some(res))
         } else {
             Err(DecodeError::new("unsupported Arrow Protocol version"))
@@ -110,12 +116,16 @@ impl ArrowMessageBody for Bytes {}
 pub struct ArrowMessage {
     /// Message header.
     header: ArrowMessageHeader,
+    /// Message flags.
+    flags: u8,
     /// Encoded message body.
     payload: Bytes,
 }
 
 impl ArrowMessage {
-    /// Create a new Arrow Message with a given service ID, session ID and payload.
+    /// Create a new Arrow Message with a given service ID, session ID and
+    /// payload. The message will use the latest supported version of the
+    /// Arrow protocol.
     pub fn new<B>(service: u16, session: u32, body: B) -> Self
     where
         B: ArrowMessageBody + 'static,
@@ -125,20 +135,42 @@ impl ArrowMessage {
         body.encode(&mut payload);
 
         Self {
-            header: ArrowMessageHeader::new(service, session, 0),
+            header: ArrowMessageHeader::new(MAX_ARROW_PROTOCOL_VERSION, service, session, 0),
+            flags: 0,
             payload: payload.freeze(),
         }
     }
 
+    /// Set given message flags.
+    pub fn with_flags(mut self, flags: u8) -> Self {
+        self.flags = flags;
+        self
+    }
+
     /// Get reference to the message header.
     pub fn header(&self) -> ArrowMessageHeader {
         self.header
     }
 
+    /// Set version of the Arrow protocol used for encoding this message.
+    pub fn set_version(&mut self, version: u8) {
+        self.header.version = version;
+    }
+
+    /// Check if the message payload is compressed.
+    pub fn is_compressed(&self) -> bool {
+        (self.flags & ARROW_MESSAGE_FLAG_COMPRESSED) != 0
+    }
+
     /// Get encoded message body.
     pub fn payload(&self) -> &[u8] {
         self.payload.as_ref()
     }
+
+    /// Get size of the encoded message (including the header) in bytes.
+    pub fn size(&self) -> usize {
+        mem::size_of::<ArrowMessageHeader>() + self.payload.len()
+    }
 }
 
 impl From<ControlMessage> for ArrowMessage {
@@ -149,12 +181,15 @@ impl From<ControlMessage> for ArrowMessage {
 
 impl Encode for ArrowMessage {
     fn encode(&self, buf: &mut BytesMut) {
-        let header = ArrowMessageHeader::new(
+        let mut header = ArrowMessageHeader::new(
+            self.header.version,
             self.header.service,
             self.header.session,
             self.payload.len() as u32,
         );
 
+        header.session |= u32::from(self.flags) << 24;
+
         header.encode(buf);
 
         buf.extend_from_slice(self.payload.as_ref())
@@ -176,10 +211,17 @@ impl Decode for ArrowMessage {
                 return Ok(None);
             }
 
+            // the flags are stored in the upper 8 bits of the session ID
+            let flags = buf[3];
+
             let message = buf.split_to(msize);
             let payload = message.freeze().split_off(hsize);
 
-            let msg = Self { header, payload };
+            let msg = Self {
+                header,
+                flags,
+                payload,
+            };
 
             Ok(Some(msg))
         } else {
@@ -187,3 +229,36 @@ impl Decode for ArrowMessage {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_flags() {
+        let msg = ArrowMessage::new(1, 0x00ab_cdef, Bytes::from("hello"))
+            .with_flags(ARROW_MESSAGE_FLAG_COMPRESSED);
+
+        let mut buf = BytesMut::new();
+
+        msg.encode(&mut buf);
+
+        assert_eq!(buf.len(), msg.size());
+
+        let msg = ArrowMessage::decode(&mut buf).unwrap().unwrap();
+
+        let session = msg.header().session;
+
+        assert_eq!(session, 0x00ab_cdef);
+        assert!(msg.is_compressed());
+        assert_eq!(msg.payload(), b"hello");
+
+        let msg = ArrowMessage::new(1, 0x00ab_cdef, Bytes::from("hello"));
+
+        msg.encode(&mut buf);
+
+        let msg = ArrowMessage::decode(&mut buf).unwrap().unwrap();
+
+        assert!(!msg.is_compressed());
+    }
+}
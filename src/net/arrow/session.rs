@@ -12,11 +12,15 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::io;
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::{Display, Formatter};
 use std::net::SocketAddr;
 use std::pin::Pin;
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime};
 
 use bytes::BytesMut;
 
@@ -25,51 +29,289 @@ use futures::{Future, Stream};
 
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::TcpStream;
+use tokio::time::Delay;
 
 use crate::context::ApplicationContext;
+use crate::net::arrow::compression::{self, PayloadCompressor, PayloadDecompressor};
 use crate::net::arrow::error::{ArrowError, ConnectionError};
 use crate::net::arrow::proto::msg::control::{
-    ControlMessageFactory, EC_CONNECTION_ERROR, EC_NO_ERROR,
+    ControlMessageFactory, EC_CONNECTION_ERROR, EC_NO_ERROR, EC_TOO_MANY_SESSIONS,
+    SESSION_WINDOW_SIZE,
 };
-use crate::net::arrow::proto::msg::ArrowMessage;
-use crate::svc_table::{BoxServiceTable, ServiceTable};
+use crate::net::arrow::proto::msg::{ArrowMessage, ARROW_MESSAGE_FLAG_COMPRESSED};
+use crate::svc_table::{BoxServiceTable, ServiceTable, ServiceType};
 use crate::utils::logger::{BoxLogger, Logger};
 
-const OUTPUT_BUFFER_LIMIT: usize = 4 * 1024 * 1024;
+/// Amount of buffered output data that marks a session as congested.
+const OUTPUT_BUFFER_HIGH_WATERMARK: usize = 1024 * 1024;
+
+/// Amount of buffered output data that marks a congested session as
+/// recovered. Consumed data are also confirmed to the Arrow Service only if
+/// there is no more buffered output data than this.
+const OUTPUT_BUFFER_LOW_WATERMARK: usize = 256 * 1024;
+
+/// Minimum amount of consumed session data confirmed to the Arrow Service
+/// by a single WINDOW_UPDATE message.
+const WINDOW_UPDATE_THRESHOLD: usize = SESSION_WINDOW_SIZE / 4;
 
 const CONNECTION_TIMEOUT: Duration = Duration::from_secs(20);
 
+/// Maximum size of a single Arrow Message carrying session data. Larger
+/// input buffers are split into several messages so that control messages
+/// (e.g. ACKs and PINGs) queued in the meantime do not have to wait for a
+/// single large payload.
+pub const MAX_CHUNK_SIZE: usize = 4096;
+
+/// Amount of time worth of data that can be sent at once by a rate limited
+/// session.
+const RATE_LIMIT_BURST: Duration = Duration::from_millis(100);
+
+/// Minimum size of a burst allowed by a rate limiter.
+const RATE_LIMIT_MIN_BURST: u64 = 1500;
+
+/// Period for re-checking throttled sessions.
+const THROTTLE_PERIOD: Duration = Duration::from_millis(10);
+
+/// Reason why a service session was closed.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SessionCloseReason {
+    /// The service closed the connection.
+    Closed,
+    /// The connection was reset by the service.
+    Reset,
+    /// The service refused the connection.
+    Refused,
+    /// Timeout while connecting to the service.
+    Timeout,
+    /// The Arrow Service sent more data than the session window allows.
+    BufferOverflow,
+    /// There was no activity in the session for too long.
+    IdleTimeout,
+    /// The session was closed by the Arrow Service using a given HUP error
+    /// code.
+    Hangup(u32),
+    /// The connection to the Arrow Service was lost.
+    Disconnected,
+    /// Unspecified connection error.
+    Error,
+    /// The client is shutting down.
+    Shutdown,
+}
+
+impl SessionCloseReason {
+    /// Get error code to be sent to the Arrow Service in the corresponding
+    /// HUP message. The Arrow protocol does not distinguish between
+    /// particular connection errors, so all of them are reported as
+    /// `EC_CONNECTION_ERROR`. The detailed reason is available only locally.
+    pub fn error_code(self) -> u32 {
+        match self {
+            Self::Closed | Self::Shutdown | Self::IdleTimeout => EC_NO_ERROR,
+            Self::Hangup(code) => code,
+            Self::Reset
+            | Self::Refused
+            | Self::Timeout
+            | Self::BufferOverflow
+            | Self::Disconnected
+            | Self::Error => EC_CONNECTION_ERROR,
+        }
+    }
+}
+
+impl Display for SessionCloseReason {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        match self {
+            Self::Closed => f.write_str("closed by the service"),
+            Self::Reset => f.write_str("connection reset"),
+            Self::Refused => f.write_str("connection refused"),
+            Self::Timeout => f.write_str("connection timeout"),
+            Self::BufferOverflow => f.write_str("buffer overflow"),
+            Self::IdleTimeout => f.write_str("idle timeout"),
+            Self::Hangup(code) => write!(f, "closed by the Arrow Service ({:08x})", code),
+            Self::Disconnected => f.write_str("Arrow Service connection lost"),
+            Self::Error => f.write_str("connection error"),
+            Self::Shutdown => f.write_str("client shutdown"),
+        }
+    }
+}
+
+impl<'a> From<&'a io::Error> for SessionCloseReason {
+    fn from(err: &'a io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::BrokenPipe => Self::Reset,
+            io::ErrorKind::ConnectionRefused => Self::Refused,
+            io::ErrorKind::TimedOut => Self::Timeout,
+            _ => Self::Error,
+        }
+    }
+}
+
+/// Information about a service session.
+#[derive(Debug, Clone)]
+pub struct SessionInfo {
+    service_id: u16,
+    session_id: u32,
+    address: SocketAddr,
+    start_time: SystemTime,
+    bytes_sent: u64,
+    bytes_received: u64,
+    duration: Duration,
+}
+
+impl SessionInfo {
+    /// Get service ID.
+    pub fn service_id(&self) -> u16 {
+        self.service_id
+    }
+
+    /// Get session ID.
+    pub fn session_id(&self) -> u32 {
+        self.session_id
+    }
+
+    /// Get address of the service.
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Get time when the session was opened.
+    pub fn start_time(&self) -> SystemTime {
+        self.start_time
+    }
+
+    /// Get number of bytes sent to the service.
+    pub fn bytes_sent(&self) -> u64 {
+        self.bytes_sent
+    }
+
+    /// Get number of bytes received from the service.
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received
+    }
+
+    /// Get session duration.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+}
+
+/// Token bucket rate limiter.
+struct TokenBucket {
+    rate: u32,
+    capacity: u64,
+    tokens: u64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// Create a new token bucket for a given rate (in bytes per second).
+    fn new(rate: u32) -> Self {
+        let burst = u64::from(rate) * RATE_LIMIT_BURST.as_millis() as u64 / 1000;
+        let capacity = burst.max(RATE_LIMIT_MIN_BURST);
+
+        Self {
+            rate,
+            capacity,
+            tokens: capacity,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Get the rate.
+    fn rate(&self) -> u32 {
+        self.rate
+    }
+
+    /// Get the number of bytes that can be sent right now.
+    fn available(&mut self) -> usize {
+        let now = Instant::now();
+
+        let elapsed = now.duration_since(self.last_refill);
+
+        let new_tokens = u128::from(self.rate) * elapsed.as_micros() / 1_000_000;
+
+        // do not move the refill time if there were no whole tokens added,
+        // we'd lose the fractions otherwise
+        if new_tokens > 0 {
+            self.tokens = (self.tokens + new_tokens as u64).min(self.capacity);
+            self.last_refill = now;
+        }
+
+        self.tokens as usize
+    }
+
+    /// Consume a given number of bytes.
+    fn consume(&mut self, n: usize) {
+        self.tokens = self.tokens.saturating_sub(n as u64);
+    }
+}
+
 /// Session context.
 struct SessionContext {
     service_id: u16,
     session_id: u32,
+    address: SocketAddr,
+    start_time: SystemTime,
+    started: Instant,
+    bytes_sent: u64,
+    bytes_received: u64,
     input: BytesMut,
     output: BytesMut,
     buffer_capacity: usize,
     session_manager_task: Option<Waker>,
     session_transport_task: Option<Waker>,
+    flow_control_task: Option<Waker>,
+    congested: bool,
+    unconfirmed: usize,
+    last_activity: Instant,
     closed: bool,
+    close_reason: Option<SessionCloseReason>,
     error: Option<ConnectionError>,
 }
 
 impl SessionContext {
-    /// Create a new session context for a given service ID and session ID.
-    fn new(service_id: u16, session_id: u32) -> Self {
+    /// Create a new session context for a given service ID, session ID and
+    /// service address.
+    fn new(service_id: u16, session_id: u32, address: SocketAddr) -> Self {
         let buffer_capacity = 8192;
 
         Self {
             service_id,
             session_id,
+            address,
+            start_time: SystemTime::now(),
+            started: Instant::now(),
+            bytes_sent: 0,
+            bytes_received: 0,
             input: BytesMut::with_capacity(buffer_capacity),
             output: BytesMut::with_capacity(buffer_capacity),
             buffer_capacity,
             session_manager_task: None,
             session_transport_task: None,
+            flow_control_task: None,
+            congested: false,
+            unconfirmed: 0,
+            last_activity: Instant::now(),
             closed: false,
+            close_reason: None,
             error: None,
         }
     }
 
+    /// Get information about the session.
+    fn info(&self) -> SessionInfo {
+        SessionInfo {
+            service_id: self.service_id,
+            session_id: self.session_id,
+            address: self.address,
+            start_time: self.start_time,
+            bytes_sent: self.bytes_sent,
+            bytes_received: self.bytes_received,
+            duration: self.started.elapsed(),
+        }
+    }
+
     /// Extend the output buffer with data from a given Arrow Message.
     fn push_output_message(&mut self, msg: ArrowMessage) {
         // ignore all incoming messages after the connection gets closed
@@ -77,38 +319,97 @@ impl SessionContext {
             return;
         }
 
-        let data = msg.payload();
+        self.output.extend_from_slice(msg.payload());
 
-        if (self.output.len() + data.len()) > OUTPUT_BUFFER_LIMIT {
-            // we cannot backpressure here, so we'll set an error state
-            self.set_error(ConnectionError::new("output buffer limit exceeded"));
-        } else {
-            self.output.extend_from_slice(data);
+        self.last_activity = Instant::now();
 
-            // we MUST notify the transport task that there is some data
-            // available in the output buffer again
-            if !self.output.is_empty() {
-                if let Some(task) = self.session_transport_task.take() {
-                    task.wake();
-                }
+        // the amount of buffered data is limited either by the session window
+        // or by pausing the Arrow Service connection (see
+        // `SessionManager::poll_congested()`)
+        if self.output.len() >= OUTPUT_BUFFER_HIGH_WATERMARK {
+            self.congested = true;
+        }
+
+        // we MUST notify the transport task that there is some data
+        // available in the output buffer again
+        if !self.output.is_empty() {
+            if let Some(task) = self.session_transport_task.take() {
+                task.wake();
             }
         }
     }
 
-    /// Take all the data from the input buffer and return them as an Arrow
-    /// Message. The method returns:
-    /// * `Poll::Ready(Some(Ok(_)))` if there was some data available
+    /// Account a given amount of session data received within the session
+    /// window. The session is closed if the Arrow Service does not respect
+    /// the window.
+    fn consume_window(&mut self, len: usize) {
+        self.unconfirmed += len;
+
+        if self.unconfirmed > SESSION_WINDOW_SIZE {
+            self.set_error(
+                ConnectionError::new("session window exceeded"),
+                SessionCloseReason::BufferOverflow,
+            );
+        }
+    }
+
+    /// Take the amount of session data that can be confirmed to the Arrow
+    /// Service (i.e. the session window increment). Received data are
+    /// confirmed in batches once the service consumes most of the buffered
+    /// data. The current task will be notified when the output buffer gets
+    /// drained if there are some data waiting for the confirmation.
+    fn poll_window_update(&mut self, cx: &mut Context) -> Option<usize> {
+        if self.closed || self.unconfirmed < WINDOW_UPDATE_THRESHOLD {
+            None
+        } else if self.output.len() > OUTPUT_BUFFER_LOW_WATERMARK {
+            self.flow_control_task = Some(cx.waker().clone());
+
+            None
+        } else {
+            Some(std::mem::take(&mut self.unconfirmed))
+        }
+    }
+
+    /// Check if the session is congested (i.e. the service is not able to
+    /// consume the data as fast as they arrive). The current task will be
+    /// notified once the congestion is over.
+    fn poll_congested(&mut self, cx: &mut Context) -> bool {
+        if self.congested && !self.closed {
+            self.flow_control_task = Some(cx.waker().clone());
+
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Close the session if there has been no traffic in either direction
+    /// for a given amount of time.
+    fn check_idle(&mut self, timeout: Duration) {
+        if self.last_activity.elapsed() >= timeout {
+            self.close(SessionCloseReason::IdleTimeout);
+        }
+    }
+
+    /// Take at most `max_len` bytes from the input buffer and return them as
+    /// an Arrow Message. The method returns:
+    /// * `Poll::Ready(Some(_))` if there was some data available
     /// * `Poll::Ready(None)` if there was no data available and the context
     ///   has been closed
-    /// * `Poll::Pending` if there was no data available
+    /// * `Poll::Pending` if there was no data available or if the data could
+    ///   not be taken because of the limit (the current task won't be
+    ///   notified in such case)
     fn take_input_message(
         &mut self,
         cx: &mut Context,
-    ) -> Poll<Option<Result<ArrowMessage, ConnectionError>>> {
-        let data = self.input.split().freeze();
+        max_len: usize,
+    ) -> Poll<Option<ArrowMessage>> {
+        let len = self.input.len().min(max_len);
+
+        let data = self.input.split_to(len).freeze();
 
-        // we MUST notify the transport task that the input buffer is empty
-        // again
+        // we MUST notify the transport task that there is some space in the
+        // input buffer again
         if let Some(task) = self.session_transport_task.take() {
             task.wake();
         }
@@ -116,12 +417,13 @@ impl SessionContext {
         if !data.is_empty() {
             let message = ArrowMessage::new(self.service_id, self.session_id, data);
 
-            Poll::Ready(Some(Ok(message)))
+            Poll::Ready(Some(message))
+        } else if !self.input.is_empty() {
+            // the session is throttled, it's up to the caller to poll us
+            // again later
+            Poll::Pending
         } else if self.closed {
-            match self.error.take() {
-                Some(err) => Poll::Ready(Some(Err(err))),
-                None => Poll::Ready(None),
-            }
+            Poll::Ready(None)
         } else {
             // save the current task and wait until there is some data
             // available in the input buffer again
@@ -150,9 +452,16 @@ impl SessionContext {
             Poll::Pending
         } else if let Poll::Ready(res) = stream.poll_read_buf(cx, &mut self.input) {
             match res {
-                Ok(len) if len == 0 => self.close(),
-                Err(err) => self.set_error(ConnectionError::from(err)),
-                _ => (),
+                Ok(len) if len == 0 => self.close(SessionCloseReason::Closed),
+                Ok(len) => {
+                    self.bytes_received += len as u64;
+                    self.last_activity = Instant::now();
+                }
+                Err(err) => {
+                    let reason = SessionCloseReason::from(&err);
+
+                    self.set_error(ConnectionError::from(err), reason);
+                }
             }
 
             // we MUST notify the session manager task that there is more data
@@ -191,8 +500,27 @@ impl SessionContext {
                 Poll::Pending
             }
         } else if let Poll::Ready(res) = stream.poll_write_buf(cx, &mut self.output) {
-            if let Err(err) = res {
-                self.set_error(ConnectionError::from(err));
+            match res {
+                Ok(len) if len > 0 => {
+                    self.bytes_sent += len as u64;
+                    self.last_activity = Instant::now();
+                }
+                Err(err) => {
+                    let reason = SessionCloseReason::from(&err);
+
+                    self.set_error(ConnectionError::from(err), reason);
+                }
+                _ => (),
+            }
+
+            // we MUST notify the flow control task that the congestion is
+            // over and that the consumed data can be confirmed
+            if self.output.len() <= OUTPUT_BUFFER_LOW_WATERMARK {
+                self.congested = false;
+
+                if let Some(task) = self.flow_control_task.take() {
+                    task.wake();
+                }
             }
 
             Poll::Ready(())
@@ -204,10 +532,16 @@ impl SessionContext {
         }
     }
 
-    /// Mark the context as closed. Note that this method does not flush any
-    /// buffer.
-    fn close(&mut self) {
+    /// Mark the context as closed for a given reason. Note that this method
+    /// does not flush any buffer.
+    fn close(&mut self, reason: SessionCloseReason) {
+        // keep the original reason if the context has been already closed
+        if self.closed {
+            return;
+        }
+
         self.closed = true;
+        self.close_reason = Some(reason);
 
         // we MUST notify the session transport task that the session has been
         // closed
@@ -220,11 +554,17 @@ impl SessionContext {
         if let Some(task) = self.session_manager_task.take() {
             task.wake();
         }
+
+        // we MUST notify the flow control task as well because the session
+        // is not congested anymore
+        if let Some(task) = self.flow_control_task.take() {
+            task.wake();
+        }
     }
 
     /// Mark the context as closed and set a given error. Note that this
     /// method does not flush any buffer.
-    fn set_error(&mut self, err: ConnectionError) {
+    fn set_error(&mut self, err: ConnectionError, reason: SessionCloseReason) {
         // ignore all errors after the connection gets closed
         if self.closed {
             return;
@@ -232,22 +572,57 @@ impl SessionContext {
 
         self.error = Some(err);
 
-        self.close();
+        self.close(reason);
+    }
+}
+
+/// Shared handle to an active session. It can be used for getting live
+/// session statistics.
+#[doc(hidden)]
+#[derive(Clone)]
+pub struct SessionHandle {
+    context: Arc<Mutex<SessionContext>>,
+}
+
+impl SessionHandle {
+    /// Get information about the session.
+    pub fn info(&self) -> SessionInfo {
+        self.context.lock().unwrap().info()
     }
 }
 
 /// Arrow session (i.e. connection to an external service).
 struct Session {
+    service_id: u16,
+    session_id: u32,
     context: Arc<Mutex<SessionContext>>,
+    compressor: Option<PayloadCompressor>,
+    decompressor: Option<PayloadDecompressor>,
+    flow_control: bool,
 }
 
 impl Session {
-    /// Create a new session for a given service ID and session ID.
-    fn new(service_id: u16, session_id: u32, addr: SocketAddr) -> Self {
-        let context = Arc::new(Mutex::new(SessionContext::new(service_id, session_id)));
+    /// Create a new session for a given service ID and session ID. Data sent
+    /// to the Arrow Service will be compressed using a given compressor (if
+    /// any). Data received from the Arrow Service are limited by the session
+    /// window if the flow control is enabled.
+    fn new(
+        service_id: u16,
+        session_id: u32,
+        addr: SocketAddr,
+        compressor: Option<PayloadCompressor>,
+        flow_control: bool,
+    ) -> Self {
+        let context = SessionContext::new(service_id, session_id, addr);
+        let context = Arc::new(Mutex::new(context));
 
         let session = Session {
+            service_id,
+            session_id,
             context: context.clone(),
+            compressor,
+            decompressor: None,
+            flow_control,
         };
 
         tokio::spawn(async move {
@@ -255,32 +630,138 @@ impl Session {
 
             match transport.await {
                 Ok(transport) => transport.await,
-                Err(err) => context.lock().unwrap().set_error(err),
+                Err((err, reason)) => context.lock().unwrap().set_error(err, reason),
             }
         });
 
         session
     }
 
-    /// Push a given Arrow Message into the output buffer.
+    /// Push a given Arrow Message into the output buffer. Compressed
+    /// messages are decompressed first. Compressed messages are accepted
+    /// only if the compression has been negotiated for the session.
     fn push(&mut self, msg: ArrowMessage) {
-        self.context.lock().unwrap().push_output_message(msg)
+        let mut context = self.context.lock().unwrap();
+
+        // the session window applies to the data as they were sent
+        if self.flow_control {
+            context.consume_window(msg.payload().len());
+        }
+
+        if !msg.is_compressed() {
+            return context.push_output_message(msg);
+        } else if self.compressor.is_none() {
+            let err = ConnectionError::new("unexpected compressed session data");
+
+            return context.set_error(err, SessionCloseReason::Error);
+        }
+
+        if self.decompressor.is_none() {
+            self.decompressor = PayloadDecompressor::new();
+        }
+
+        let data = self
+            .decompressor
+            .as_mut()
+            .ok_or_else(|| ConnectionError::new("compressed session data are not supported"))
+            .and_then(|decompressor| decompressor.decompress(msg.payload()));
+
+        match data {
+            Ok(data) => {
+                let msg = ArrowMessage::new(self.service_id, self.session_id, data);
+
+                context.push_output_message(msg)
+            }
+            Err(err) => context.set_error(err, SessionCloseReason::Error),
+        }
     }
 
-    /// Take an Arrow Message from the input buffer. The method returns:
-    /// * `Poll::Ready(Some(Ok(_)))` if there was some data available
+    /// Take an Arrow Message with payload of at most `max_len` bytes
+    /// (after compression) from the input buffer. The method returns:
+    /// * `Poll::Ready(Some(_))` if there was some data available
     /// * `Poll::Ready(None)` if there was no data available and the context
     ///   has been closed
-    /// * `Poll::Pending` if there was no data available
-    fn take(&mut self, cx: &mut Context) -> Poll<Option<Result<ArrowMessage, ConnectionError>>> {
-        self.context.lock().unwrap().take_input_message(cx)
+    /// * `Poll::Pending` if there was no data available or if the data could
+    ///   not be taken because of the limit
+    fn take(&mut self, cx: &mut Context, max_len: usize) -> Poll<Option<ArrowMessage>> {
+        let max_len = self.input_limit(max_len);
+
+        let res = self.context.lock().unwrap().take_input_message(cx, max_len);
+
+        if let Some(compressor) = self.compressor.as_mut() {
+            if let Poll::Ready(Some(msg)) = res {
+                let data = compressor.compress(msg.payload());
+
+                let msg = ArrowMessage::new(self.service_id, self.session_id, data)
+                    .with_flags(ARROW_MESSAGE_FLAG_COMPRESSED);
+
+                return Poll::Ready(Some(msg));
+            }
+        }
+
+        res
+    }
+
+    /// Get the maximum number of input bytes that can be sent in a single
+    /// Arrow Message with payload of at most `max_len` bytes.
+    fn input_limit(&self, max_len: usize) -> usize {
+        if self.compressor.is_some() {
+            compression::max_compressible_size(max_len)
+        } else {
+            max_len
+        }
+    }
+
+    /// Get ID of the service.
+    fn service_id(&self) -> u16 {
+        self.service_id
+    }
+
+    /// Get information about the session.
+    fn info(&self) -> SessionInfo {
+        self.context.lock().unwrap().info()
+    }
+
+    /// Get a shared handle to the session.
+    fn handle(&self) -> SessionHandle {
+        SessionHandle {
+            context: self.context.clone(),
+        }
     }
 
-    /// Mark the session as closed. The session context won't accept any new
-    /// data, however the buffered data can be still processed. It's up to
-    /// the corresponding tasks to consume all remaining data.
-    fn close(&mut self) {
-        self.context.lock().unwrap().close()
+    /// Get the reason why the session was closed and the corresponding error
+    /// (if any).
+    fn close_reason(&self) -> (SessionCloseReason, Option<ConnectionError>) {
+        let context = self.context.lock().unwrap();
+
+        let reason = context.close_reason.unwrap_or(SessionCloseReason::Closed);
+
+        (reason, context.error.clone())
+    }
+
+    /// Close the session if there has been no traffic in either direction
+    /// for a given amount of time.
+    fn check_idle(&mut self, timeout: Duration) {
+        self.context.lock().unwrap().check_idle(timeout)
+    }
+
+    /// Take the amount of session data that can be confirmed to the Arrow
+    /// Service (if any).
+    fn poll_window_update(&mut self, cx: &mut Context) -> Option<usize> {
+        self.context.lock().unwrap().poll_window_update(cx)
+    }
+
+    /// Check if the session is congested.
+    fn poll_congested(&mut self, cx: &mut Context) -> bool {
+        self.context.lock().unwrap().poll_congested(cx)
+    }
+
+    /// Mark the session as closed for a given reason. The session context
+    /// won't accept any new data, however the buffered data can be still
+    /// processed. It's up to the corresponding tasks to consume all remaining
+    /// data.
+    fn close(&mut self, reason: SessionCloseReason) {
+        self.context.lock().unwrap().close(reason)
     }
 }
 
@@ -296,11 +777,20 @@ impl SessionTransport {
     async fn connect(
         context: Arc<Mutex<SessionContext>>,
         addr: SocketAddr,
-    ) -> Result<Self, ConnectionError> {
+    ) -> Result<Self, (ConnectionError, SessionCloseReason)> {
         let stream = tokio::time::timeout(CONNECTION_TIMEOUT, TcpStream::connect(addr))
             .await
-            .map_err(|_| ConnectionError::new("connection timeout"))?
-            .map_err(ConnectionError::from)?;
+            .map_err(|_| {
+                (
+                    ConnectionError::new("connection timeout"),
+                    SessionCloseReason::Timeout,
+                )
+            })?
+            .map_err(|err| {
+                let reason = SessionCloseReason::from(&err);
+
+                (ConnectionError::from(err), reason)
+            })?;
 
         let transport = Self { context, stream };
 
@@ -333,8 +823,19 @@ impl Future for SessionTransport {
     }
 }
 
+/// Error returned when a session cannot be created.
+enum SessionError {
+    /// The maximum number of concurrent sessions has been reached.
+    Limit,
+    /// The client is shutting down.
+    ShuttingDown,
+    /// Any other error.
+    Other(ArrowError),
+}
+
 /// Arrow session manager.
 pub struct SessionManager {
+    app_context: ApplicationContext,
     logger: BoxLogger,
     svc_table: BoxServiceTable,
     cmsg_factory: ControlMessageFactory,
@@ -342,6 +843,15 @@ pub struct SessionManager {
     sessions: HashMap<u32, Session>,
     poll_order: VecDeque<u32>,
     new_session: Option<Waker>,
+    max_sessions: Option<usize>,
+    idle_timeout: Option<Duration>,
+    rate_limiter: Option<TokenBucket>,
+    service_rate_limiters: HashMap<u16, TokenBucket>,
+    throttle: Option<Delay>,
+    accepting: bool,
+    compression: bool,
+    flow_control: bool,
+    compressed_svc_types: Arc<HashSet<ServiceType>>,
 }
 
 impl SessionManager {
@@ -351,15 +861,57 @@ impl SessionManager {
 
         Self {
             logger: app_context.get_logger(),
+            max_sessions: app_context.get_max_sessions(),
+            idle_timeout: app_context.get_session_idle_timeout(),
+            rate_limiter: app_context.get_rate_limit().map(TokenBucket::new),
+            compressed_svc_types: app_context.get_compressed_service_types(),
+            app_context,
             svc_table: svc_table.boxed(),
             cmsg_factory,
             cmsg_queue: VecDeque::new(),
             sessions: HashMap::new(),
             poll_order: VecDeque::new(),
             new_session: None,
+            service_rate_limiters: HashMap::new(),
+            throttle: None,
+            accepting: true,
+            compression: false,
+            flow_control: false,
         }
     }
 
+    /// Enable or disable compression of data sent by new sessions (the
+    /// compression must be supported by the Arrow Service). Only sessions of
+    /// the configured service types will be compressed.
+    pub fn set_compression(&mut self, enabled: bool) {
+        self.compression = enabled;
+    }
+
+    /// Enable or disable the session flow control for new sessions (the flow
+    /// control must be supported by the Arrow Service).
+    pub fn set_flow_control(&mut self, enabled: bool) {
+        self.flow_control = enabled;
+    }
+
+    /// Check if reading of session data from the Arrow Service should be
+    /// paused because a service is not able to consume its data. This is
+    /// needed only if the Arrow Service does not support the session flow
+    /// control, session windows are used otherwise. The current task will
+    /// be notified once all sessions are ready to accept more data.
+    pub fn poll_congested(&mut self, cx: &mut Context) -> bool {
+        if self.flow_control {
+            return false;
+        }
+
+        let mut congested = false;
+
+        for session in self.sessions.values_mut() {
+            congested |= session.poll_congested(cx);
+        }
+
+        congested
+    }
+
     /// Get number of active sessions.
     pub fn len(&self) -> usize {
         self.sessions.len()
@@ -370,44 +922,129 @@ impl SessionManager {
     pub fn send(&mut self, msg: ArrowMessage) {
         let header = msg.header();
 
-        let session = self.take_session(header.service, header.session);
+        let session_id = header.session;
 
-        if let Ok(mut session) = session {
-            session.push(msg);
+        match self.take_session(header.service, session_id) {
+            Ok(mut session) => {
+                session.push(msg);
 
-            self.sessions.insert(header.session, session);
-        } else if let Err(err) = session {
-            log_warn!(
-                self.logger,
-                "unable to connect to a remote service: {}",
-                err
-            );
+                self.sessions.insert(session_id, session);
+            }
+            Err(SessionError::Limit) => {
+                log_warn!(
+                    self.logger,
+                    "unable to open a new session, the maximum number of concurrent sessions has been reached; session ID: {:08x}",
+                    session_id
+                );
 
-            let msg = self.create_hup_message(header.session, EC_CONNECTION_ERROR);
+                let msg = self.create_hup_message(session_id, EC_TOO_MANY_SESSIONS);
 
-            self.cmsg_queue.push_back(msg);
+                self.cmsg_queue.push_back(msg);
+            }
+            Err(SessionError::ShuttingDown) => {
+                log_debug!(
+                    self.logger,
+                    "refusing a new session, the client is shutting down; session ID: {:08x}",
+                    session_id
+                );
+
+                let msg = self.create_hup_message(session_id, EC_CONNECTION_ERROR);
+
+                self.cmsg_queue.push_back(msg);
+            }
+            Err(SessionError::Other(err)) => {
+                log_warn!(
+                    self.logger,
+                    "unable to connect to a remote service: {}",
+                    err
+                );
+
+                let msg = self.create_hup_message(session_id, EC_CONNECTION_ERROR);
+
+                self.cmsg_queue.push_back(msg);
+            }
         }
     }
 
-    /// Close a given session.
-    pub fn close(&mut self, session_id: u32, _: u32) {
+    /// Close all sessions that have been idle for too long.
+    pub fn check_timeouts(&mut self) {
+        if let Some(timeout) = self.idle_timeout {
+            for session in self.sessions.values_mut() {
+                session.check_idle(timeout);
+            }
+        }
+    }
+
+    /// Close a given session as requested by the Arrow Service.
+    pub fn close(&mut self, session_id: u32, error_code: u32) {
         if let Some(mut session) = self.sessions.remove(&session_id) {
-            log_info!(
-                self.logger,
-                "closing service connection; session ID: {:08x}",
-                session_id
-            );
+            let reason = SessionCloseReason::Hangup(error_code);
 
-            session.close();
+            session.close(reason);
+
+            self.session_closed(&session, reason);
         }
     }
 
+    /// Close all sessions and refuse all new ones. A HUP message will be sent
+    /// to the Arrow Service for every closed session.
+    pub fn shutdown(&mut self) {
+        self.accepting = false;
+
+        let sessions = std::mem::take(&mut self.sessions);
+
+        self.poll_order.clear();
+
+        for (session_id, mut session) in sessions {
+            let reason = SessionCloseReason::Shutdown;
+
+            session.close(reason);
+
+            self.session_closed(&session, reason);
+
+            let msg = self.create_hup_message(session_id, reason.error_code());
+
+            self.cmsg_queue.push_back(msg);
+        }
+    }
+
+    /// Log a given closed session and notify the event listeners.
+    fn session_closed(&mut self, session: &Session, reason: SessionCloseReason) {
+        let info = session.info();
+
+        log_info!(
+            self.logger,
+            "service connection closed; session ID: {:08x}, reason: {}, sent: {} bytes, received: {} bytes, duration: {}.{:03} s",
+            info.session_id(),
+            reason,
+            info.bytes_sent(),
+            info.bytes_received(),
+            info.duration().as_secs(),
+            info.duration().subsec_millis()
+        );
+
+        self.app_context.session_closed(&info, reason);
+    }
+
     /// Take a given session object.
-    fn take_session(&mut self, service_id: u16, session_id: u32) -> Result<Session, ArrowError> {
+    fn take_session(&mut self, service_id: u16, session_id: u32) -> Result<Session, SessionError> {
         let session = if let Some(session) = self.sessions.remove(&session_id) {
             session
         } else {
-            let session = self.connect(service_id, session_id)?;
+            if !self.accepting {
+                return Err(SessionError::ShuttingDown);
+            }
+
+            if let Some(max_sessions) = self.max_sessions {
+                if self.sessions.len() >= max_sessions {
+                    return Err(SessionError::Limit);
+                }
+            }
+
+            let session = self
+                .connect(service_id, session_id)
+                .map_err(SessionError::Other)?;
+            self.app_context.session_opened(session.handle());
             self.poll_order.push_back(session_id);
             // notify the message consuming task
             if let Some(task) = self.new_session.take() {
@@ -433,6 +1070,28 @@ impl SessionManager {
             ))
         })?;
 
+        // sessions of the same service share a single rate limiter
+        if let Some(rate) = svc.rate_limit() {
+            let current = self
+                .service_rate_limiters
+                .get(&service_id)
+                .map(|limiter| limiter.rate());
+
+            if current != Some(rate) {
+                self.service_rate_limiters
+                    .insert(service_id, TokenBucket::new(rate));
+            }
+        } else {
+            self.service_rate_limiters.remove(&service_id);
+        }
+
+        let compressor =
+            if self.compression && self.compressed_svc_types.contains(&svc.service_type()) {
+                PayloadCompressor::new()
+            } else {
+                None
+            };
+
         log_info!(
             self.logger,
             "connecting to remote service: {}, service ID: {:04x}, session ID: {:08x}",
@@ -441,7 +1100,66 @@ impl SessionManager {
             session_id
         );
 
-        Ok(Session::new(service_id, session_id, addr))
+        if compressor.is_some() {
+            log_debug!(
+                self.logger,
+                "session data will be compressed; session ID: {:08x}",
+                session_id
+            );
+        }
+
+        Ok(Session::new(
+            service_id,
+            session_id,
+            addr,
+            compressor,
+            self.flow_control,
+        ))
+    }
+
+    /// Get the number of bytes that can be sent right now by a session of a
+    /// given service.
+    fn quota(&mut self, service_id: u16) -> usize {
+        let mut res = MAX_CHUNK_SIZE;
+
+        if let Some(limiter) = self.rate_limiter.as_mut() {
+            res = res.min(limiter.available());
+        }
+
+        if let Some(limiter) = self.service_rate_limiters.get_mut(&service_id) {
+            res = res.min(limiter.available());
+        }
+
+        res
+    }
+
+    /// Consume a given number of bytes from the quota of a given service.
+    fn consume_quota(&mut self, service_id: u16, n: usize) {
+        if let Some(limiter) = self.rate_limiter.as_mut() {
+            limiter.consume(n);
+        }
+
+        if let Some(limiter) = self.service_rate_limiters.get_mut(&service_id) {
+            limiter.consume(n);
+        }
+    }
+
+    /// Create WINDOW_UPDATE messages for all sessions that have consumed
+    /// enough data.
+    fn poll_window_updates(&mut self, cx: &mut Context) {
+        let mut updates = Vec::new();
+
+        for (session_id, session) in self.sessions.iter_mut() {
+            if let Some(increment) = session.poll_window_update(cx) {
+                updates.push((*session_id, increment as u32));
+            }
+        }
+
+        for (session_id, increment) in updates {
+            let msg = self.cmsg_factory.window_update(session_id, increment);
+
+            self.cmsg_queue.push_back(ArrowMessage::from(msg));
+        }
     }
 
     /// Create HUP message for a given session.
@@ -459,14 +1177,14 @@ impl SessionManager {
 
 impl Drop for SessionManager {
     fn drop(&mut self) {
-        for (session_id, mut session) in self.sessions.drain() {
-            log_info!(
-                self.logger,
-                "closing service connection; session ID: {:08x}",
-                session_id
-            );
+        let sessions = std::mem::take(&mut self.sessions);
 
-            session.close();
+        for (_, mut session) in sessions {
+            let reason = SessionCloseReason::Disconnected;
+
+            session.close(reason);
+
+            self.session_closed(&session, reason);
         }
     }
 }
@@ -475,46 +1193,62 @@ impl Stream for SessionManager {
     type Item = Result<ArrowMessage, ArrowError>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        if self.flow_control {
+            self.poll_window_updates(cx);
+        }
+
+        // control messages are never throttled
         if let Some(msg) = self.cmsg_queue.pop_front() {
             return Poll::Ready(Some(Ok(msg)));
         }
 
+        if let Some(throttle) = self.throttle.as_mut() {
+            if Pin::new(throttle).poll(cx).is_ready() {
+                self.throttle = None;
+            }
+        }
+
+        let mut throttled = false;
+
         let mut count = self.poll_order.len();
 
         while count > 0 {
             if let Some(session_id) = self.poll_order.pop_front() {
                 if let Some(mut session) = self.sessions.remove(&session_id) {
-                    match session.take(cx) {
+                    let service_id = session.service_id();
+
+                    let quota = self.quota(service_id);
+
+                    match session.take(cx, quota) {
                         Poll::Pending => {
+                            throttled |= session.input_limit(quota) == 0;
+
                             self.sessions.insert(session_id, session);
                             self.poll_order.push_back(session_id);
                         }
                         Poll::Ready(None) => {
-                            log_info!(
-                                self.logger,
-                                "service connection closed; session ID: {:08x}",
-                                session_id
-                            );
+                            let (reason, err) = session.close_reason();
 
-                            let msg = self.create_hup_message(session_id, EC_NO_ERROR);
+                            if let Some(err) = err {
+                                log_warn!(
+                                    self.logger,
+                                    "service connection error; session ID: {:08x}: {}",
+                                    session_id,
+                                    err
+                                );
+                            }
 
-                            return Poll::Ready(Some(Ok(msg)));
-                        }
-                        Poll::Ready(Some(Ok(msg))) => {
-                            self.sessions.insert(session_id, session);
-                            self.poll_order.push_back(session_id);
+                            self.session_closed(&session, reason);
+
+                            let msg = self.create_hup_message(session_id, reason.error_code());
 
                             return Poll::Ready(Some(Ok(msg)));
                         }
-                        Poll::Ready(Some(Err(err))) => {
-                            log_warn!(
-                                self.logger,
-                                "service connection error; session ID: {:08x}: {}",
-                                session_id,
-                                err
-                            );
+                        Poll::Ready(Some(msg)) => {
+                            self.consume_quota(service_id, msg.payload().len());
 
-                            let msg = self.create_hup_message(session_id, EC_CONNECTION_ERROR);
+                            self.sessions.insert(session_id, session);
+                            self.poll_order.push_back(session_id);
 
                             return Poll::Ready(Some(Ok(msg)));
                         }
@@ -525,6 +1259,18 @@ impl Stream for SessionManager {
             count -= 1;
         }
 
+        // throttled sessions need to be re-checked once there are some
+        // tokens available again
+        if throttled && self.throttle.is_none() {
+            let mut throttle = tokio::time::delay_for(THROTTLE_PERIOD);
+
+            if Pin::new(&mut throttle).poll(cx).is_pending() {
+                self.throttle = Some(throttle);
+            } else {
+                cx.waker().wake_by_ref();
+            }
+        }
+
         // the session manager needs to be re-polled in case there is a new
         // session
         self.new_session = Some(cx.waker().clone());
@@ -532,3 +1278,232 @@ impl Stream for SessionManager {
         Poll::Pending
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use bytes::Bytes;
+
+    use futures::task::noop_waker;
+
+    /// Create a new session context.
+    fn create_context() -> SessionContext {
+        SessionContext::new(1, 1, SocketAddr::from(([127, 0, 0, 1], 80)))
+    }
+
+    /// Create a new session data message with a given size.
+    fn create_message(size: usize) -> ArrowMessage {
+        ArrowMessage::new(1, 1, Bytes::from(vec![0u8; size]))
+    }
+
+    #[test]
+    fn test_flow_control() {
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        let mut context = create_context();
+
+        context.push_output_message(create_message(OUTPUT_BUFFER_HIGH_WATERMARK - 1));
+
+        assert!(!context.poll_congested(&mut cx));
+
+        context.push_output_message(create_message(OUTPUT_BUFFER_HIGH_WATERMARK));
+
+        // the session must not be closed even though there is more data
+        // than the high watermark
+        assert!(context.poll_congested(&mut cx));
+        assert!(context.flow_control_task.is_some());
+        assert!(!context.closed);
+
+        let mut stream = tokio::io::sink();
+
+        while context.poll_write_output(&mut cx, &mut stream).is_ready() {
+            if context.output.is_empty() {
+                break;
+            }
+        }
+
+        assert!(context.output.is_empty());
+        assert!(context.flow_control_task.is_none());
+        assert!(!context.poll_congested(&mut cx));
+    }
+
+    #[test]
+    fn test_session_window() {
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        let mut context = create_context();
+
+        let half = SESSION_WINDOW_SIZE / 2;
+
+        context.consume_window(half);
+        context.push_output_message(create_message(half));
+
+        // nothing can be confirmed while the data are still buffered
+        assert_eq!(context.poll_window_update(&mut cx), None);
+
+        context.consume_window(half);
+        context.push_output_message(create_message(half));
+
+        assert_eq!(context.poll_window_update(&mut cx), None);
+        assert!(!context.closed);
+
+        let mut stream = tokio::io::sink();
+
+        while context.poll_write_output(&mut cx, &mut stream).is_ready() {
+            if context.output.is_empty() {
+                break;
+            }
+        }
+
+        // the whole window can be confirmed once the data are consumed
+        assert_eq!(
+            context.poll_window_update(&mut cx),
+            Some(SESSION_WINDOW_SIZE)
+        );
+        assert_eq!(context.poll_window_update(&mut cx), None);
+
+        // small amounts of data are not confirmed immediately
+        context.consume_window(WINDOW_UPDATE_THRESHOLD - 1);
+
+        assert_eq!(context.poll_window_update(&mut cx), None);
+
+        // the Arrow Service must not exceed the window
+        context.consume_window(SESSION_WINDOW_SIZE - WINDOW_UPDATE_THRESHOLD + 1);
+
+        assert!(!context.closed);
+
+        context.consume_window(1);
+
+        assert!(context.closed);
+        assert!(context.error.is_some());
+
+        assert_eq!(
+            context.close_reason,
+            Some(SessionCloseReason::BufferOverflow)
+        );
+    }
+
+    #[test]
+    fn test_congested_session() {
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        let mut context = create_context();
+
+        // the session is never closed because of the amount of buffered data
+        for _ in 0..16 {
+            context.push_output_message(create_message(OUTPUT_BUFFER_HIGH_WATERMARK));
+        }
+
+        context.check_idle(Duration::from_secs(60));
+
+        assert!(!context.closed);
+        assert!(context.poll_congested(&mut cx));
+
+        // closed sessions do not hold the Arrow Service connection
+        context.close(SessionCloseReason::Hangup(EC_NO_ERROR));
+
+        assert!(!context.poll_congested(&mut cx));
+    }
+
+    #[test]
+    fn test_idle_timeout() {
+        let timeout = Duration::from_secs(60);
+
+        let mut context = create_context();
+
+        context.check_idle(timeout);
+
+        assert!(!context.closed);
+
+        context.push_output_message(create_message(1));
+        context.last_activity -= timeout;
+
+        context.check_idle(timeout);
+
+        assert!(context.closed);
+        assert_eq!(context.close_reason, Some(SessionCloseReason::IdleTimeout));
+    }
+
+    #[test]
+    fn test_token_bucket() {
+        let mut bucket = TokenBucket::new(100_000);
+
+        assert_eq!(bucket.available(), 10_000);
+
+        bucket.consume(10_000);
+
+        assert_eq!(bucket.available(), 0);
+
+        bucket.last_refill -= Duration::from_millis(50);
+
+        assert_eq!(bucket.available(), 5_000);
+
+        // the bucket must not overflow
+        bucket.last_refill -= Duration::from_secs(10);
+
+        assert_eq!(bucket.available(), 10_000);
+
+        // there is a minimum burst size
+        let mut bucket = TokenBucket::new(100);
+
+        assert_eq!(bucket.available(), RATE_LIMIT_MIN_BURST as usize);
+    }
+
+    #[test]
+    fn test_limited_input() {
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        let mut context = create_context();
+
+        context.input.extend_from_slice(b"hello");
+
+        // throttled session
+        assert!(context.take_input_message(&mut cx, 0).is_pending());
+
+        match context.take_input_message(&mut cx, 2) {
+            Poll::Ready(Some(msg)) => assert_eq!(msg.payload(), b"he"),
+            _ => panic!("message expected"),
+        }
+
+        context.close(SessionCloseReason::Closed);
+
+        // the remaining data must be taken before the end of stream
+        match context.take_input_message(&mut cx, usize::MAX) {
+            Poll::Ready(Some(msg)) => assert_eq!(msg.payload(), b"llo"),
+            _ => panic!("message expected"),
+        }
+
+        assert!(matches!(
+            context.take_input_message(&mut cx, usize::MAX),
+            Poll::Ready(None)
+        ));
+    }
+
+    #[test]
+    fn test_close_reason() {
+        let mut context = create_context();
+
+        context.close(SessionCloseReason::Hangup(3));
+
+        // the first reason must be kept
+        context.set_error(ConnectionError::new("error"), SessionCloseReason::Reset);
+
+        assert_eq!(context.close_reason, Some(SessionCloseReason::Hangup(3)));
+        assert!(context.error.is_none());
+
+        let err = io::Error::from(io::ErrorKind::ConnectionRefused);
+
+        assert_eq!(SessionCloseReason::from(&err), SessionCloseReason::Refused);
+        assert_eq!(
+            SessionCloseReason::Refused.error_code(),
+            EC_CONNECTION_ERROR
+        );
+        assert_eq!(SessionCloseReason::IdleTimeout.error_code(), EC_NO_ERROR);
+        assert_eq!(SessionCloseReason::Hangup(3).error_code(), 3);
+    }
+}
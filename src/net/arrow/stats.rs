@@ -0,0 +1,186 @@
+// Copyright 2020 Angelcam, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::{Duration, Instant};
+
+/// Arrow Service connection statistics.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionStats {
+    rtt: Option<Duration>,
+    smoothed_rtt: Option<Duration>,
+    rtt_jitter: Option<Duration>,
+    connection_attempts: u64,
+    connected_since: Option<Instant>,
+    bytes_sent: u64,
+    bytes_received: u64,
+    backoff_failures: u32,
+    retry_delay: Option<Duration>,
+}
+
+impl ConnectionStats {
+    /// Create new empty connection statistics.
+    #[doc(hidden)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the last measured round-trip time (i.e. time between sending a
+    /// PING message and receiving the corresponding ACK).
+    pub fn rtt(&self) -> Option<Duration> {
+        self.rtt
+    }
+
+    /// Get smoothed round-trip time.
+    pub fn smoothed_rtt(&self) -> Option<Duration> {
+        self.smoothed_rtt
+    }
+
+    /// Get round-trip time variation.
+    pub fn rtt_jitter(&self) -> Option<Duration> {
+        self.rtt_jitter
+    }
+
+    /// Get number of reconnects (i.e. all connection attempts except the
+    /// first one).
+    pub fn reconnects(&self) -> u64 {
+        self.connection_attempts.saturating_sub(1)
+    }
+
+    /// Get uptime of the current Arrow Service connection (`None` means that
+    /// the client is not connected).
+    pub fn uptime(&self) -> Option<Duration> {
+        self.connected_since.map(|t| t.elapsed())
+    }
+
+    /// Get total number of bytes sent to the Arrow Service.
+    pub fn bytes_sent(&self) -> u64 {
+        self.bytes_sent
+    }
+
+    /// Get total number of bytes received from the Arrow Service.
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received
+    }
+
+    /// Get number of consecutive connection failures counted by the
+    /// reconnect backoff.
+    pub fn backoff_failures(&self) -> u32 {
+        self.backoff_failures
+    }
+
+    /// Get the last delay before a reconnect (`None` means that the client
+    /// reconnected immediately or it has not been disconnected yet).
+    pub fn retry_delay(&self) -> Option<Duration> {
+        self.retry_delay
+    }
+
+    /// Add a given round-trip time sample. The smoothed RTT and the RTT
+    /// variation are calculated the same way as in RFC 6298.
+    #[doc(hidden)]
+    pub fn add_rtt_sample(&mut self, rtt: Duration) {
+        if let (Some(srtt), Some(rttvar)) = (self.smoothed_rtt, self.rtt_jitter) {
+            let diff = srtt.max(rtt) - srtt.min(rtt);
+
+            self.rtt_jitter = Some(rttvar * 3 / 4 + diff / 4);
+            self.smoothed_rtt = Some(srtt * 7 / 8 + rtt / 8);
+        } else {
+            self.rtt_jitter = Some(rtt / 2);
+            self.smoothed_rtt = Some(rtt);
+        }
+
+        self.rtt = Some(rtt);
+    }
+
+    /// Report a new connection attempt.
+    #[doc(hidden)]
+    pub fn connection_attempt(&mut self) {
+        self.connection_attempts += 1;
+    }
+
+    /// Report that the connection has been established or lost.
+    #[doc(hidden)]
+    pub fn set_connected(&mut self, connected: bool) {
+        if !connected {
+            self.connected_since = None;
+        } else if self.connected_since.is_none() {
+            self.connected_since = Some(Instant::now());
+        }
+    }
+
+    /// Update the reconnect backoff state.
+    #[doc(hidden)]
+    pub fn set_backoff(&mut self, failures: u32, retry_delay: Option<Duration>) {
+        self.backoff_failures = failures;
+        self.retry_delay = retry_delay;
+    }
+
+    /// Add given numbers of sent and received bytes.
+    #[doc(hidden)]
+    pub fn add_transferred_bytes(&mut self, sent: u64, received: u64) {
+        self.bytes_sent += sent;
+        self.bytes_received += received;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rtt() {
+        let mut stats = ConnectionStats::new();
+
+        assert!(stats.rtt().is_none());
+
+        stats.add_rtt_sample(Duration::from_millis(100));
+
+        assert_eq!(stats.smoothed_rtt(), Some(Duration::from_millis(100)));
+        assert_eq!(stats.rtt_jitter(), Some(Duration::from_millis(50)));
+
+        stats.add_rtt_sample(Duration::from_millis(180));
+
+        assert_eq!(stats.rtt(), Some(Duration::from_millis(180)));
+        assert_eq!(stats.smoothed_rtt(), Some(Duration::from_millis(110)));
+        assert_eq!(
+            stats.rtt_jitter(),
+            Some(Duration::from_millis(57) + Duration::from_micros(500))
+        );
+    }
+
+    #[test]
+    fn test_reconnects() {
+        let mut stats = ConnectionStats::new();
+
+        assert_eq!(stats.reconnects(), 0);
+
+        stats.connection_attempt();
+
+        assert_eq!(stats.reconnects(), 0);
+
+        stats.connection_attempt();
+        stats.connection_attempt();
+
+        assert_eq!(stats.reconnects(), 2);
+
+        assert!(stats.uptime().is_none());
+
+        stats.set_connected(true);
+
+        assert!(stats.uptime().is_some());
+
+        stats.set_connected(false);
+
+        assert!(stats.uptime().is_none());
+    }
+}
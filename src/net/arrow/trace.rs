@@ -0,0 +1,437 @@
+// Copyright 2020 Angelcam, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+use std::io;
+use std::mem;
+
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use json::JsonValue;
+
+use crate::net::arrow::proto::codec::FromBytes;
+use crate::net::arrow::proto::msg::control::{ControlMessageHeader, ControlMessageType};
+use crate::net::arrow::proto::msg::ArrowMessage;
+use crate::utils::json::{FromJson, ParseError, ToJson};
+use crate::utils::rotating_file::RotatingFile;
+
+/// Direction of a traced message.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum TraceDirection {
+    /// The message was received from the Arrow Service.
+    Received,
+    /// The message was sent to the Arrow Service.
+    Sent,
+}
+
+impl TraceDirection {
+    /// Get string representation of the direction.
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Received => "in",
+            Self::Sent => "out",
+        }
+    }
+
+    /// Get the direction from its string representation.
+    fn from_name(s: &str) -> Option<Self> {
+        match s {
+            "in" => Some(Self::Received),
+            "out" => Some(Self::Sent),
+            _ => None,
+        }
+    }
+}
+
+/// Single record of a message trace.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TraceRecord {
+    /// Time when the message was received or sent.
+    pub timestamp: SystemTime,
+    /// Message direction.
+    pub direction: TraceDirection,
+    /// Service ID.
+    pub service_id: u16,
+    /// Session ID.
+    pub session_id: u32,
+    /// Payload length.
+    pub length: usize,
+    /// Control message ID (Control Protocol messages only).
+    pub msg_id: Option<u16>,
+    /// Control message type (Control Protocol messages only).
+    pub msg_type: Option<String>,
+    /// Message payload (if recorded). Payloads of REGISTER messages and
+    /// session data are never recorded because they may contain
+    /// credentials.
+    pub payload: Option<Vec<u8>>,
+}
+
+impl TraceRecord {
+    /// Create a new trace record for a given message.
+    fn new(direction: TraceDirection, msg: &ArrowMessage, payload: bool) -> Self {
+        let header = msg.header();
+        let data = msg.payload();
+
+        let hsize = mem::size_of::<ControlMessageHeader>();
+
+        let cmsg_header = if header.service == 0 && data.len() >= hsize {
+            ControlMessageHeader::from_bytes(&data[..hsize])
+                .ok()
+                .and_then(|header| header)
+        } else {
+            None
+        };
+
+        // REGISTER messages contain the client credentials and session data
+        // may contain credentials for the services
+        let sensitive = match cmsg_header {
+            Some(header) => header.message_type() == ControlMessageType::REGISTER,
+            None => header.service != 0,
+        };
+
+        let payload = if payload && !sensitive {
+            Some(data.to_vec())
+        } else {
+            None
+        };
+
+        Self {
+            timestamp: SystemTime::now(),
+            direction,
+            service_id: header.service,
+            session_id: header.session,
+            length: data.len(),
+            msg_id: cmsg_header.map(|header| header.msg_id),
+            msg_type: cmsg_header.map(|header| format!("{:?}", header.message_type())),
+            payload,
+        }
+    }
+}
+
+impl ToJson for TraceRecord {
+    fn to_json(&self) -> JsonValue {
+        let timestamp = self
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+
+        let mut res = object! {
+            "timestamp" => timestamp,
+            "direction" => self.direction.as_str(),
+            "service_id" => self.service_id,
+            "session_id" => self.session_id,
+            "length" => self.length
+        };
+
+        if let Some(msg_id) = self.msg_id {
+            res["msg_id"] = msg_id.into();
+        }
+
+        if let Some(msg_type) = self.msg_type.as_ref() {
+            res["msg_type"] = msg_type.as_str().into();
+        }
+
+        if let Some(payload) = self.payload.as_ref() {
+            res["payload"] = hex_encode(payload).into();
+        }
+
+        res
+    }
+}
+
+impl FromJson for TraceRecord {
+    fn from_json(value: JsonValue) -> Result<Self, ParseError> {
+        let record;
+
+        if let JsonValue::Object(r) = value {
+            record = r;
+        } else {
+            return Err(ParseError::new("JSON object expected"));
+        }
+
+        let timestamp = record
+            .get("timestamp")
+            .and_then(|v| v.as_u64())
+            .ok_or_else(|| ParseError::new("missing field \"timestamp\""))?;
+        let direction = record
+            .get("direction")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ParseError::new("missing field \"direction\""))?;
+        let service_id = record
+            .get("service_id")
+            .and_then(|v| v.as_u16())
+            .ok_or_else(|| ParseError::new("missing field \"service_id\""))?;
+        let session_id = record
+            .get("session_id")
+            .and_then(|v| v.as_u32())
+            .ok_or_else(|| ParseError::new("missing field \"session_id\""))?;
+        let length = record
+            .get("length")
+            .and_then(|v| v.as_usize())
+            .ok_or_else(|| ParseError::new("missing field \"length\""))?;
+
+        let direction = TraceDirection::from_name(direction)
+            .ok_or_else(|| ParseError::new("unknown message direction"))?;
+
+        let msg_id = record.get("msg_id").and_then(|v| v.as_u16());
+        let msg_type = record
+            .get("msg_type")
+            .and_then(|v| v.as_str())
+            .map(String::from);
+
+        let payload = record
+            .get("payload")
+            .and_then(|v| v.as_str())
+            .map(hex_decode)
+            .transpose()?;
+
+        let res = Self {
+            timestamp: UNIX_EPOCH + Duration::from_millis(timestamp),
+            direction,
+            service_id,
+            session_id,
+            length,
+            msg_id,
+            msg_type,
+            payload,
+        };
+
+        Ok(res)
+    }
+}
+
+impl Display for TraceRecord {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        let since_epoch = self
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+
+        let tm = time::at(time::Timespec::new(since_epoch.as_secs() as i64, 0));
+
+        let direction = match self.direction {
+            TraceDirection::Received => "<-",
+            TraceDirection::Sent => "->",
+        };
+
+        write!(
+            f,
+            "{}.{:03} {} service {:04x} session {:06x} length {}",
+            time::strftime("%F %T", &tm).unwrap(),
+            since_epoch.subsec_millis(),
+            direction,
+            self.service_id,
+            self.session_id,
+            self.length
+        )?;
+
+        if let Some(msg_type) = self.msg_type.as_ref() {
+            write!(f, " {}", msg_type)?;
+        }
+
+        if let Some(msg_id) = self.msg_id {
+            write!(f, " (msg_id {})", msg_id)?;
+        }
+
+        if let Some(payload) = self.payload.as_ref() {
+            for (i, chunk) in payload.chunks(16).enumerate() {
+                let hex = chunk
+                    .iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+
+                let ascii = chunk
+                    .iter()
+                    .map(|&b| {
+                        if b.is_ascii_graphic() || b == b' ' {
+                            b as char
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect::<String>();
+
+                write!(f, "\n    {:06x}  {:<47}  |{}|", i * 16, hex, ascii)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Encode given data as a hex string.
+fn hex_encode(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Decode a given hex string.
+fn hex_decode(s: &str) -> Result<Vec<u8>, ParseError> {
+    if (s.len() & 1) != 0 || !s.is_ascii() {
+        return Err(ParseError::new("invalid hex string"));
+    }
+
+    (0..s.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&s[i..i + 2], 16).map_err(|_| ParseError::new("invalid hex string"))
+        })
+        .collect()
+}
+
+/// Arrow message tracer. It records all messages exchanged with the Arrow
+/// Service into a given file (one JSON object per line). The file is rotated
+/// the same way as the file logger does it. The records are buffered, use
+/// the `flush` method to make sure they are written into the file.
+#[derive(Clone)]
+pub struct MessageTracer {
+    file: Arc<Mutex<RotatingFile>>,
+    payload: bool,
+}
+
+impl MessageTracer {
+    /// Create a new message tracer writing into a given file with a given
+    /// size limit and a given number of backup files (rotations). Message
+    /// payloads (except for REGISTER messages and session data) will be
+    /// recorded only if the `payload` flag is set.
+    pub fn new<P>(path: P, limit: usize, rotations: usize, payload: bool) -> io::Result<Self>
+    where
+        PathBuf: From<P>,
+    {
+        let file = RotatingFile::open(path, limit, rotations)?;
+
+        let res = Self {
+            file: Arc::new(Mutex::new(file)),
+            payload,
+        };
+
+        Ok(res)
+    }
+
+    /// Record a given message.
+    pub fn trace(&self, direction: TraceDirection, msg: &ArrowMessage) -> io::Result<()> {
+        let record = TraceRecord::new(direction, msg, self.payload);
+
+        let mut line = record.to_json().dump();
+
+        line.push('\n');
+
+        self.file.lock().unwrap().write(line.as_bytes())
+    }
+
+    /// Flush all buffered records into the trace file.
+    pub fn flush(&self) -> io::Result<()> {
+        self.file.lock().unwrap().flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    use crate::net::arrow::proto::msg::control::{
+        ControlMessage, ControlMessageFactory, SimpleServiceTable,
+    };
+    use crate::net::arrow::proto::MIN_ARROW_PROTOCOL_VERSION;
+    use crate::net::raw::ether::MacAddr;
+    use crate::svc_table::Service;
+
+    #[test]
+    fn test_trace_record() {
+        let msg = ArrowMessage::from(ControlMessage::ping(3));
+
+        let record = TraceRecord::new(TraceDirection::Sent, &msg, true);
+
+        assert_eq!(record.msg_id, Some(3));
+        assert_eq!(record.msg_type.as_deref(), Some("PING"));
+        assert_eq!(record.payload.as_deref(), Some(msg.payload()));
+
+        let mut expected = record.clone();
+
+        // the timestamp is stored with millisecond precision
+        let timestamp = record.timestamp.duration_since(UNIX_EPOCH).unwrap();
+
+        expected.timestamp = UNIX_EPOCH + Duration::from_millis(timestamp.as_millis() as u64);
+
+        let decoded = TraceRecord::from_json(record.to_json()).unwrap();
+
+        assert_eq!(decoded, expected);
+    }
+
+    #[test]
+    fn test_sensitive_payload() {
+        let register = ControlMessageFactory::new(MIN_ARROW_PROTOCOL_VERSION).register(
+            MacAddr::new(0, 1, 2, 3, 4, 5),
+            [1; 16],
+            [2; 16],
+            SimpleServiceTable::from(Vec::<(u16, Service)>::new()),
+        );
+
+        let msg = ArrowMessage::from(register);
+
+        let record = TraceRecord::new(TraceDirection::Sent, &msg, true);
+
+        assert_eq!(record.msg_type.as_deref(), Some("REGISTER"));
+        assert_eq!(record.length, msg.payload().len());
+        assert!(record.payload.is_none());
+
+        let msg = ArrowMessage::new(1, 2, bytes::Bytes::from_static(b"hello"));
+
+        let record = TraceRecord::new(TraceDirection::Sent, &msg, true);
+
+        assert_eq!(record.length, 5);
+        assert!(record.payload.is_none());
+    }
+
+    #[test]
+    fn test_message_tracer() {
+        let dir = env::temp_dir().join(format!("arrow-trace-test-{}", process::id()));
+
+        fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("trace");
+
+        let tracer = MessageTracer::new(path.clone(), 1 << 20, 0, false).unwrap();
+
+        let msg = ArrowMessage::new(1, 2, bytes::Bytes::from_static(b"hello"));
+
+        tracer.trace(TraceDirection::Received, &msg).unwrap();
+        tracer.flush().unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+
+        fs::remove_dir_all(&dir).ok();
+
+        let lines = content.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 1);
+
+        let record = TraceRecord::from_json(json::parse(lines[0]).unwrap()).unwrap();
+
+        assert_eq!(record.direction, TraceDirection::Received);
+        assert_eq!(record.service_id, 1);
+        assert_eq!(record.session_id, 2);
+        assert_eq!(record.length, 5);
+        assert!(record.msg_type.is_none());
+        assert!(record.payload.is_none());
+    }
+}
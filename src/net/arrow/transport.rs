@@ -0,0 +1,149 @@
+// Copyright 2020 Angelcam, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Transports for the Arrow Protocol.
+
+use std::io;
+
+use std::pin::Pin;
+use std::str::FromStr;
+
+use futures::future::{Future, FutureExt};
+
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+
+use crate::context::ApplicationContext;
+use crate::net::tls::TlsError;
+use crate::net::url::Url;
+use crate::net::ws::WebSocketStream;
+
+/// Default port of the WebSocket transport.
+const DEFAULT_WSS_PORT: u16 = 443;
+
+/// Common trait for duplex streams that can carry the Arrow Protocol.
+pub trait ArrowStream: AsyncRead + AsyncWrite + Send + Unpin {}
+
+impl<T> ArrowStream for T where T: AsyncRead + AsyncWrite + Send + Unpin {}
+
+/// Type alias.
+pub type ArrowTransportFuture =
+    Pin<Box<dyn Future<Output = Result<Box<dyn ArrowStream>, io::Error>> + Send>>;
+
+/// Common trait for Arrow Protocol transports. A transport is responsible for
+/// opening a duplex stream to a given Arrow Service address. The stream is
+/// expected to be secured (if needed) and ready to carry Arrow Messages.
+pub trait ArrowTransport: Send + Sync {
+    /// Open a new stream to a given Arrow Service address.
+    fn connect(&self, addr: &str) -> ArrowTransportFuture;
+}
+
+/// Default Arrow Protocol transport (i.e. TLS over TCP). The transport
+/// respects the configured proxy and it uses the WebSocket transport for
+/// addresses in the "wss://host[:port][/path]" format. All other addresses
+/// are expected to be in the "host:port" format.
+pub struct DefaultTransport {
+    app_context: ApplicationContext,
+}
+
+impl DefaultTransport {
+    /// Create a new default transport.
+    pub fn new(app_context: ApplicationContext) -> Self {
+        Self { app_context }
+    }
+}
+
+impl ArrowTransport for DefaultTransport {
+    fn connect(&self, addr: &str) -> ArrowTransportFuture {
+        let app_context = self.app_context.clone();
+        let addr = addr.to_string();
+
+        let future = async move {
+            let tls_connector = app_context.get_tls_connector().map_err(|err| {
+                io::Error::new(
+                    io::ErrorKind::Other,
+                    format!("unable to get TLS context: {}", err),
+                )
+            })?;
+
+            let ws_url = if addr.starts_with("wss://") {
+                let url = Url::from_str(&addr).map_err(|_| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("invalid Arrow Service address: {}", addr),
+                    )
+                })?;
+
+                Some(url)
+            } else {
+                None
+            };
+
+            let socket_addr = if let Some(url) = ws_url.as_ref() {
+                format!("{}:{}", url.host(), url.port().unwrap_or(DEFAULT_WSS_PORT))
+            } else {
+                addr.clone()
+            };
+
+            // WebSocket servers use regular certificates, so we need to
+            // verify the hostname
+            let hostname = ws_url.as_ref().map(|url| url.host());
+
+            let stream = if let Some(proxy) = app_context.get_proxy(&socket_addr) {
+                let stream = proxy.connect(&socket_addr).await.map_err(TlsError::new);
+
+                match (stream, hostname) {
+                    (Ok(stream), Some(hostname)) => {
+                        tls_connector
+                            .connect_stream_verified(hostname, stream)
+                            .await
+                    }
+                    (Ok(stream), None) => tls_connector.connect_stream(stream).await,
+                    (Err(err), _) => Err(err),
+                }
+            } else if let Some(hostname) = hostname {
+                match TcpStream::connect(socket_addr.as_str()).await {
+                    Ok(stream) => {
+                        tls_connector
+                            .connect_stream_verified(hostname, stream)
+                            .await
+                    }
+                    Err(err) => Err(TlsError::from(err)),
+                }
+            } else {
+                tls_connector.connect(socket_addr.as_str()).await
+            };
+
+            let stream = stream.map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+
+            if let Some(url) = ws_url.as_ref() {
+                // the Host header must contain the port if it's not the default one
+                let host = match url.port() {
+                    Some(port) => format!("{}:{}", url.host(), port),
+                    None => url.host().to_string(),
+                };
+
+                let stream = WebSocketStream::connect(stream, &host, url.path())
+                    .await
+                    .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+
+                Ok(Box::new(stream) as Box<dyn ArrowStream>)
+            } else {
+                Ok(Box::new(stream) as Box<dyn ArrowStream>)
+            }
+        };
+
+        future.boxed()
+    }
+}
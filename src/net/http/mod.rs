@@ -143,6 +143,7 @@ pub struct Request {
     host: String,
     port: u16,
     inner: GenericRequestBuilder,
+    method: Method,
     timeout: Option<Duration>,
     max_line_length: usize,
     max_header_lines: usize,
@@ -177,6 +178,7 @@ impl Request {
             host: host.to_string(),
             port,
             inner,
+            method,
             timeout: Some(Duration::from_secs(20)),
             max_line_length: 4096,
             max_header_lines: 1024,
@@ -191,6 +193,11 @@ impl Request {
         Self::new(Method::GET, url, true)
     }
 
+    /// Create a new HEAD request.
+    pub fn head(url: &str) -> Result<Self, Error> {
+        Self::new(Method::HEAD, url, true)
+    }
+
     /// Set protocol version.
     pub fn set_version(mut self, version: &str) -> Self {
         self.inner = self.inner.set_version(version);
@@ -230,7 +237,8 @@ impl Request {
             self.max_line_length,
             self.max_header_lines,
             self.ignore_response_body,
-        );
+        )
+        .head_request(self.method == Method::HEAD);
 
         let stream = TcpStream::connect((self.host.as_ref(), self.port)).await?;
 
@@ -322,10 +330,11 @@ impl Response {
 /// HTTP client codec.
 pub struct ClientCodec {
     hdecoder: GenericResponseHeaderDecoder,
-    bdecoder: Option<Box<dyn MessageBodyDecoder>>,
+    bdecoder: Option<Box<dyn MessageBodyDecoder + Send>>,
     header: Option<GenericResponseHeader>,
     max_line_length: usize,
     ignore_response_body: bool,
+    head_request: bool,
 }
 
 impl ClientCodec {
@@ -343,8 +352,16 @@ impl ClientCodec {
             header: None,
             max_line_length,
             ignore_response_body,
+            head_request: false,
         }
     }
+
+    /// Expect responses to HEAD requests (i.e. responses without message
+    /// body).
+    pub fn head_request(mut self, head_request: bool) -> Self {
+        self.head_request = head_request;
+        self
+    }
 }
 
 impl Decoder for ClientCodec {
@@ -356,8 +373,8 @@ impl Decoder for ClientCodec {
             if let Some(header) = self.hdecoder.decode(data)? {
                 let status_code = header.status_code();
 
-                let bdecoder: Box<dyn MessageBodyDecoder> = if (status_code >= 100
-                    && status_code < 200)
+                let bdecoder: Box<dyn MessageBodyDecoder + Send> = if self.head_request
+                    || (status_code >= 100 && status_code < 200)
                     || status_code == 204
                     || status_code == 304
                 {
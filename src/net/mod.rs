@@ -19,7 +19,9 @@ pub mod rtsp;
 pub mod http;
 
 pub mod arrow;
+pub mod proxy;
 pub mod raw;
 pub mod tls;
 pub mod url;
 pub mod utils;
+pub mod ws;
@@ -310,26 +310,68 @@ pub struct TlsConnector {
 }
 
 impl TlsConnector {
-    /// Take a given asynchronous stream and perform a TLS handshake.
+    /// Connect to a given address and perform a TLS handshake.
     pub async fn connect<T>(&self, addr: T) -> Result<TlsStream<TcpStream>, TlsError>
     where
         T: ToSocketAddrs,
     {
         let stream = TcpStream::connect(addr);
         let stream = stream.await?;
+
+        self.connect_stream(stream).await
+    }
+
+    /// Take a given asynchronous stream and perform a TLS handshake.
+    pub async fn connect_stream<S>(&self, stream: S) -> Result<TlsStream<S>, TlsError>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        self.handshake(stream, None).await
+    }
+
+    /// Take a given asynchronous stream and perform a TLS handshake. The
+    /// server certificate must be valid for a given hostname. The hostname
+    /// is also sent in the SNI extension.
+    pub async fn connect_stream_verified<S>(
+        &self,
+        hostname: &str,
+        stream: S,
+    ) -> Result<TlsStream<S>, TlsError>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        self.handshake(stream, Some(hostname)).await
+    }
+
+    /// Perform a TLS handshake (optionally verifying a given hostname).
+    async fn handshake<S>(
+        &self,
+        stream: S,
+        hostname: Option<&str>,
+    ) -> Result<TlsStream<S>, TlsError>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
         let stream = InnerSslStream::new(stream);
 
         let configuration = self.inner.configure()?;
 
+        // IPv6 addresses may be enclosed in square brackets
+        let hostname = hostname.map(|h| h.trim_start_matches('[').trim_end_matches(']'));
+
         let handshake = futures::future::lazy(move |cx| {
             let _drop_context = set_async_context(cx);
 
-            // NOTE: We do not need to validate the server name because we use only one root
-            // certificate. It's a self signed certificate issued directly by Angelcam and used
-            // only for Arrow.
-            configuration
-                .verify_hostname(false)
-                .connect("hostname", stream)
+            if let Some(hostname) = hostname {
+                configuration.connect(hostname, stream)
+            } else {
+                // NOTE: We do not need to validate the server name because we use only one root
+                // certificate. It's a self signed certificate issued directly by Angelcam and
+                // used only for Arrow.
+                configuration
+                    .verify_hostname(false)
+                    .connect("hostname", stream)
+            }
         });
 
         let handshake = handshake.await;
@@ -0,0 +1,546 @@
+// Copyright 2020 Angelcam, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Minimal WebSocket client transport (RFC 6455). The transport carries an
+//! arbitrary byte stream inside binary WebSocket messages.
+
+use std::fmt;
+use std::io;
+use std::str;
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::pin::Pin;
+
+use bytes::{Buf, BufMut, BytesMut};
+
+use futures::ready;
+use futures::task::{Context, Poll};
+
+use openssl::base64;
+use openssl::sha;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use uuid::Uuid;
+
+/// GUID used for computing the Sec-WebSocket-Accept header.
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Maximum size of the HTTP response header.
+const MAX_RESPONSE_HEADER_SIZE: usize = 8192;
+
+/// Maximum size of a single incoming frame payload.
+const MAX_FRAME_PAYLOAD: u64 = 1 << 24;
+
+/// Do not accept any more data for writing if the output buffer is longer
+/// than this.
+const OUTPUT_BUFFER_LIMIT: usize = 65536;
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xa;
+
+/// WebSocket error.
+#[derive(Debug, Clone)]
+pub struct WebSocketError {
+    msg: String,
+}
+
+impl WebSocketError {
+    /// Create a new error with a given message.
+    pub fn new<T>(msg: T) -> Self
+    where
+        T: ToString,
+    {
+        Self {
+            msg: msg.to_string(),
+        }
+    }
+}
+
+impl Error for WebSocketError {}
+
+impl Display for WebSocketError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        f.write_str(&self.msg)
+    }
+}
+
+impl From<io::Error> for WebSocketError {
+    fn from(err: io::Error) -> Self {
+        Self::new(err)
+    }
+}
+
+/// Decoded frame header.
+struct FrameHeader {
+    opcode: u8,
+    mask: Option<[u8; 4]>,
+    header_len: usize,
+    payload_len: usize,
+}
+
+impl FrameHeader {
+    /// Try to decode a frame header from a given buffer. The method returns
+    /// `None` if there is not enough data in the buffer.
+    fn decode(data: &[u8]) -> io::Result<Option<Self>> {
+        if data.len() < 2 {
+            return Ok(None);
+        }
+
+        let opcode = data[0] & 0x0f;
+        let masked = (data[1] & 0x80) != 0;
+
+        let (payload_len, mut header_len) = match data[1] & 0x7f {
+            126 if data.len() < 4 => return Ok(None),
+            126 => (u64::from(u16::from_be_bytes([data[2], data[3]])), 4),
+            127 if data.len() < 10 => return Ok(None),
+            127 => {
+                let mut len = [0u8; 8];
+
+                len.copy_from_slice(&data[2..10]);
+
+                (u64::from_be_bytes(len), 10)
+            }
+            len => (u64::from(len), 2),
+        };
+
+        if payload_len > MAX_FRAME_PAYLOAD {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "WebSocket frame too large",
+            ));
+        }
+
+        let mask = if masked {
+            if data.len() < (header_len + 4) {
+                return Ok(None);
+            }
+
+            let mut mask = [0u8; 4];
+
+            mask.copy_from_slice(&data[header_len..header_len + 4]);
+
+            header_len += 4;
+
+            Some(mask)
+        } else {
+            None
+        };
+
+        let res = Self {
+            opcode,
+            mask,
+            header_len,
+            payload_len: payload_len as usize,
+        };
+
+        Ok(Some(res))
+    }
+}
+
+/// Encode a single masked (client-to-server) frame with a given opcode and
+/// payload.
+fn encode_frame(buffer: &mut BytesMut, opcode: u8, payload: &[u8]) {
+    let len = payload.len();
+
+    buffer.reserve(len + 14);
+
+    buffer.put_u8(0x80 | opcode);
+
+    if len < 126 {
+        buffer.put_u8(0x80 | len as u8);
+    } else if len <= 0xffff {
+        buffer.put_u8(0x80 | 126);
+        buffer.put_u16(len as u16);
+    } else {
+        buffer.put_u8(0x80 | 127);
+        buffer.put_u64(len as u64);
+    }
+
+    let mask = random_mask();
+
+    buffer.put_slice(&mask);
+
+    for (i, b) in payload.iter().enumerate() {
+        buffer.put_u8(b ^ mask[i & 3]);
+    }
+}
+
+/// Generate a random masking key.
+fn random_mask() -> [u8; 4] {
+    let uuid = Uuid::new_v4();
+    let bytes = uuid.as_bytes();
+
+    [bytes[0], bytes[1], bytes[2], bytes[3]]
+}
+
+/// Compute expected value of the Sec-WebSocket-Accept header for a given key.
+fn accept_key(key: &str) -> String {
+    let mut hasher = sha::Sha1::new();
+
+    hasher.update(key.as_bytes());
+    hasher.update(WEBSOCKET_GUID.as_bytes());
+
+    base64::encode_block(&hasher.finish())
+}
+
+/// Client side of a WebSocket connection. Everything written into the stream
+/// is sent as binary messages and payload of all incoming binary messages can
+/// be read from the stream.
+pub struct WebSocketStream<S> {
+    inner: S,
+    input: BytesMut,
+    payload: BytesMut,
+    output: BytesMut,
+    closed: bool,
+    close_sent: bool,
+}
+
+impl<S> WebSocketStream<S> {
+    /// Create a new WebSocket stream from a given stream. No handshake is
+    /// performed.
+    fn new(stream: S) -> Self {
+        Self {
+            inner: stream,
+            input: BytesMut::new(),
+            payload: BytesMut::new(),
+            output: BytesMut::new(),
+            closed: false,
+            close_sent: false,
+        }
+    }
+
+    /// Process all complete frames in the input buffer.
+    fn process_input(&mut self) -> io::Result<()> {
+        while let Some(header) = FrameHeader::decode(&self.input)? {
+            let frame_len = header.header_len + header.payload_len;
+
+            if self.input.len() < frame_len {
+                return Ok(());
+            }
+
+            let mut frame = self.input.split_to(frame_len);
+
+            frame.advance(header.header_len);
+
+            if let Some(mask) = header.mask {
+                for (i, b) in frame.iter_mut().enumerate() {
+                    *b ^= mask[i & 3];
+                }
+            }
+
+            match header.opcode {
+                OPCODE_BINARY | OPCODE_CONTINUATION => self.payload.extend_from_slice(&frame),
+                OPCODE_PING => encode_frame(&mut self.output, OPCODE_PONG, &frame),
+                OPCODE_PONG => (),
+                OPCODE_CLOSE => {
+                    if !self.close_sent {
+                        encode_frame(&mut self.output, OPCODE_CLOSE, &frame);
+
+                        self.close_sent = true;
+                    }
+
+                    self.closed = true;
+                }
+                OPCODE_TEXT => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "unexpected WebSocket text message",
+                    ))
+                }
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "unknown WebSocket frame type",
+                    ))
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<S> WebSocketStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    /// Perform the client handshake over a given stream. The host and path
+    /// are used in the HTTP upgrade request.
+    pub async fn connect(mut stream: S, host: &str, path: &str) -> Result<Self, WebSocketError> {
+        let key = base64::encode_block(Uuid::new_v4().as_bytes());
+
+        let request = format!(
+            "GET {} HTTP/1.1\r\n\
+             Host: {}\r\n\
+             Upgrade: websocket\r\n\
+             Connection: Upgrade\r\n\
+             Sec-WebSocket-Key: {}\r\n\
+             Sec-WebSocket-Version: 13\r\n\
+             \r\n",
+            path, host, key
+        );
+
+        stream.write_all(request.as_bytes()).await?;
+        stream.flush().await?;
+
+        // NOTE: we need to read the response byte by byte in order not to
+        // consume any data following the response header
+        let mut header = Vec::new();
+
+        while !header.ends_with(b"\r\n\r\n") {
+            if header.len() >= MAX_RESPONSE_HEADER_SIZE {
+                return Err(WebSocketError::new("WebSocket response header too long"));
+            }
+
+            header.push(stream.read_u8().await?);
+        }
+
+        let header = str::from_utf8(&header)
+            .map_err(|_| WebSocketError::new("invalid WebSocket handshake response"))?;
+
+        let mut lines = header.split("\r\n");
+
+        let status_line = lines.next().unwrap_or("");
+
+        let mut parts = status_line.splitn(3, ' ');
+
+        let protocol = parts.next().unwrap_or("");
+        let status_code = parts.next().unwrap_or("");
+
+        if !protocol.starts_with("HTTP/") {
+            return Err(WebSocketError::new("invalid WebSocket handshake response"));
+        } else if status_code != "101" {
+            return Err(WebSocketError::new(format!(
+                "WebSocket upgrade rejected (status code: {})",
+                status_code
+            )));
+        }
+
+        let expected_accept = accept_key(&key);
+
+        let accepted = lines
+            .filter_map(|line| {
+                let mut parts = line.splitn(2, ':');
+
+                let name = parts.next()?.trim();
+                let value = parts.next()?.trim();
+
+                Some((name, value))
+            })
+            .any(|(name, value)| {
+                name.eq_ignore_ascii_case("sec-websocket-accept") && value == expected_accept
+            });
+
+        if !accepted {
+            return Err(WebSocketError::new(
+                "invalid WebSocket handshake response (Sec-WebSocket-Accept mismatch)",
+            ));
+        }
+
+        Ok(Self::new(stream))
+    }
+
+    /// Write as much data from the output buffer as possible.
+    fn poll_write_output(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+        while !self.output.is_empty() {
+            let inner = Pin::new(&mut self.inner);
+
+            let len = ready!(inner.poll_write(cx, &self.output))?;
+
+            if len == 0 {
+                return Poll::Ready(Err(io::Error::from(io::ErrorKind::WriteZero)));
+            }
+
+            self.output.advance(len);
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S> AsyncRead for WebSocketStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+
+        loop {
+            if !this.payload.is_empty() {
+                let len = buf.len().min(this.payload.len());
+
+                buf[..len].copy_from_slice(&this.payload[..len]);
+
+                this.payload.advance(len);
+
+                return Poll::Ready(Ok(len));
+            } else if this.closed {
+                // try to send the close frame reply, errors are not important
+                // at this point
+                let _ = this.poll_write_output(cx);
+
+                return Poll::Ready(Ok(0));
+            }
+
+            let mut chunk = [0u8; 8192];
+
+            let inner = Pin::new(&mut this.inner);
+
+            let len = ready!(inner.poll_read(cx, &mut chunk))?;
+
+            if len == 0 {
+                return Poll::Ready(Ok(0));
+            }
+
+            this.input.extend_from_slice(&chunk[..len]);
+
+            this.process_input()?;
+
+            // send replies to control frames (if any)
+            if let Poll::Ready(Err(err)) = this.poll_write_output(cx) {
+                return Poll::Ready(Err(err));
+            }
+        }
+    }
+}
+
+impl<S> AsyncWrite for WebSocketStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+
+        if this.output.len() > OUTPUT_BUFFER_LIMIT {
+            ready!(this.poll_write_output(cx))?;
+        }
+
+        encode_frame(&mut this.output, OPCODE_BINARY, buf);
+
+        // the data is buffered now, write errors will be reported later
+        if let Poll::Ready(Err(err)) = this.poll_write_output(cx) {
+            return Poll::Ready(Err(err));
+        }
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+
+        ready!(this.poll_write_output(cx))?;
+
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+
+        if !this.close_sent {
+            encode_frame(&mut this.output, OPCODE_CLOSE, &[]);
+
+            this.close_sent = true;
+        }
+
+        ready!(this.poll_write_output(cx))?;
+
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accept_key() {
+        // example from RFC 6455
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[test]
+    fn test_frame_encoding() {
+        let mut buffer = BytesMut::new();
+
+        let payload = vec![0xaa; 300];
+
+        encode_frame(&mut buffer, OPCODE_BINARY, &payload);
+
+        let header = FrameHeader::decode(&buffer).unwrap().unwrap();
+
+        assert_eq!(header.opcode, OPCODE_BINARY);
+        assert_eq!(header.header_len, 8);
+        assert_eq!(header.payload_len, 300);
+
+        let mask = header.mask.unwrap();
+
+        let decoded = buffer[header.header_len..]
+            .iter()
+            .enumerate()
+            .map(|(i, b)| b ^ mask[i & 3])
+            .collect::<Vec<_>>();
+
+        assert_eq!(decoded, payload);
+
+        // incomplete header
+        assert!(FrameHeader::decode(&buffer[..3]).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_frame_processing() {
+        let mut stream = WebSocketStream::new(());
+
+        // unmasked binary frame split into two fragments, a ping and a
+        // partial binary frame
+        stream.input.extend_from_slice(&[0x02, 0x03, 1, 2, 3]);
+        stream.input.extend_from_slice(&[0x80, 0x02, 4, 5]);
+        stream.input.extend_from_slice(&[0x89, 0x01, 9]);
+        stream.input.extend_from_slice(&[0x82, 0x05, 6]);
+
+        stream.process_input().unwrap();
+
+        assert_eq!(&stream.payload[..], &[1, 2, 3, 4, 5]);
+        assert_eq!(&stream.input[..], &[0x82, 0x05, 6]);
+
+        // pong reply
+        assert_eq!(stream.output[0], 0x80 | OPCODE_PONG);
+        assert_eq!(stream.output[1], 0x81);
+
+        // close frame
+        stream.input.clear();
+        stream.input.extend_from_slice(&[0x88, 0x00]);
+
+        stream.process_input().unwrap();
+
+        assert!(stream.closed);
+        assert!(stream.close_sent);
+    }
+}
@@ -23,10 +23,12 @@ use std::error::Error;
 use std::fmt::{Display, Formatter};
 use std::net::{IpAddr, SocketAddr, SocketAddrV4, SocketAddrV6};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use futures::{Future, FutureExt};
 
+use tokio::net::TcpStream;
+
 use crate::net::raw::pcap;
 
 use crate::net::http::Request as HttpRequest;
@@ -39,6 +41,7 @@ use crate::net::raw::tcp::scanner::{PortCollection, TcpPortScanner};
 use crate::net::rtsp::sdp::{FromAttribute, MediaType, RTPMap, SessionDescription};
 use crate::net::rtsp::Request as RtspRequest;
 use crate::net::rtsp::Response as RtspResponse;
+use crate::scanner::probe::{ProbeResult, StreamType};
 use crate::scanner::result::{ScanResult, HR_FLAG_ARP, HR_FLAG_ICMP};
 use crate::svc_table::{Service, ServiceType};
 use crate::utils::logger::{BoxLogger, Logger};
@@ -159,6 +162,98 @@ pub fn scan_network(
     Ok(report)
 }
 
+/// Probe a given service using the same checks as the network scan (i.e.
+/// without scanning the whole network).
+pub async fn probe_service(logger: BoxLogger, service: Service) -> Result<ProbeResult> {
+    let context = Context::new(
+        logger,
+        Arc::new(HashSet::new()),
+        Arc::new(Vec::new()),
+        Arc::new(Vec::new()),
+    )?;
+
+    probe(context, service).await
+}
+
+/// Probe a given service.
+async fn probe(context: Context, service: Service) -> Result<ProbeResult> {
+    let addr = service
+        .address()
+        .ok_or_else(|| DiscoveryError::new("the service has no address"))?;
+
+    let path = service.path().unwrap_or("/").to_string();
+
+    let mut res = ProbeResult::default();
+
+    let start = Instant::now();
+
+    match service.service_type() {
+        ServiceType::RTSP
+        | ServiceType::LockedRTSP
+        | ServiceType::UnknownRTSP
+        | ServiceType::UnsupportedRTSP => {
+            if !is_rtsp_service(context.clone(), addr).await {
+                return Ok(res);
+            }
+
+            res.reachable = true;
+            res.latency = Some(start.elapsed());
+
+            let stream_type = match describe_rtsp_stream(context, addr, &path).await {
+                Some(response) => {
+                    res.status_code = Some(response.status_code());
+
+                    if response.status_code() == 200 {
+                        res.codecs = get_sdp_codecs(response.body());
+                    }
+
+                    get_rtsp_response_stream_type(response, &path)
+                }
+                None => StreamType::Error,
+            };
+
+            res.stream_type = Some(stream_type);
+        }
+        ServiceType::MJPEG | ServiceType::LockedMJPEG => {
+            if !is_http_service(context.clone(), addr).await {
+                return Ok(res);
+            }
+
+            res.reachable = true;
+            res.latency = Some(start.elapsed());
+
+            let stream_type = match get_http_response(context, addr, &path).await {
+                Ok(response) => {
+                    res.status_code = Some(response.status_code());
+
+                    StreamType::from(response)
+                }
+                Err(_) => StreamType::Error,
+            };
+
+            res.stream_type = Some(stream_type);
+        }
+        ServiceType::HTTP => {
+            if let Ok(response) = get_http_head_response(context, addr, &path).await {
+                res.reachable = true;
+                res.latency = Some(start.elapsed());
+                res.status_code = Some(response.status_code());
+            }
+        }
+        ServiceType::TCP => {
+            let timeout = context.get_request_timeout();
+
+            if let Ok(Ok(_)) = tokio::time::timeout(timeout, TcpStream::connect(addr)).await {
+                res.reachable = true;
+                res.latency = Some(start.elapsed());
+            }
+        }
+        ServiceType::ControlProtocol => (),
+    }
+
+    Ok(res)
+}
+
 /// Internal data for the network scanner context.
 struct ContextData {
     logger: BoxLogger,
@@ -406,16 +501,6 @@ where
     Ok(res)
 }
 
-/// Stream type.
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
-enum StreamType {
-    Supported,
-    Locked,
-    Unsupported,
-    NotFound,
-    Error,
-}
-
 impl From<RtspResponse> for StreamType {
     fn from(response: RtspResponse) -> Self {
         let status_code = response.status_code();
@@ -485,6 +570,27 @@ fn is_supported_rtsp_service(sdp: &[u8]) -> bool {
     }
 }
 
+/// Get a list of all distinct codecs from a given session description.
+fn get_sdp_codecs(sdp: &[u8]) -> Vec<String> {
+    let mut res = Vec::new();
+
+    if let Ok(sdp) = SessionDescription::parse(sdp) {
+        for md in sdp.media_descriptions {
+            for attr in md.attributes {
+                if let Ok(rtpmap) = RTPMap::from_attr(&attr) {
+                    let codec = rtpmap.encoding.to_uppercase();
+
+                    if !res.contains(&codec) {
+                        res.push(codec);
+                    }
+                }
+            }
+        }
+    }
+
+    res
+}
+
 /// Check if a given HTTP response can be interpreted as an MJPEG stream.
 fn is_supported_mjpeg_service(response: &HttpResponse) -> bool {
     let ctype = response
@@ -528,6 +634,20 @@ async fn get_http_response(context: Context, addr: SocketAddr, path: &str) -> Re
         .map_err(|err| DiscoveryError::new(format!("HTTP client error: {}", err)))
 }
 
+/// Get response to a HEAD request for a given path from a given HTTP server.
+async fn get_http_head_response(
+    context: Context,
+    addr: SocketAddr,
+    path: &str,
+) -> Result<HttpResponse> {
+    HttpRequest::head(&format!("http://{}{}", addr, path))
+        .map_err(|err| DiscoveryError::new(format!("HTTP client error: {}", err)))?
+        .set_request_timeout(Some(context.get_request_timeout()))
+        .send()
+        .await
+        .map_err(|err| DiscoveryError::new(format!("HTTP client error: {}", err)))
+}
+
 /// Find all RTSP services.
 async fn find_rtsp_services<I>(context: Context, open_ports: I) -> Vec<(MacAddr, SocketAddr)>
 where
@@ -697,27 +817,35 @@ async fn get_rtsp_stream(
 
 /// Get stream type for a given RTSP service and path.
 async fn get_rtsp_stream_type(context: Context, addr: SocketAddr, path: &str) -> StreamType {
-    let path = path.to_string();
-
-    let request = RtspRequest::describe(&format!("rtsp://{}{}", addr, path));
+    describe_rtsp_stream(context, addr, path)
+        .await
+        .map(|response| get_rtsp_response_stream_type(response, path))
+        .unwrap_or(StreamType::Error)
+}
 
-    if request.is_err() {
-        return StreamType::Error;
-    }
+/// Send an RTSP DESCRIBE request for a given path to a given RTSP service and
+/// return the response (or None in case of an error).
+async fn describe_rtsp_stream(
+    context: Context,
+    addr: SocketAddr,
+    path: &str,
+) -> Option<RtspResponse> {
+    let request = RtspRequest::describe(&format!("rtsp://{}{}", addr, path)).ok()?;
 
     request
-        .unwrap()
         .set_request_timeout(Some(context.get_request_timeout()))
         .send()
         .await
-        .map(|response| {
-            if is_hipcam_rtsp_response(&response) && path != "/11" && path != "/12" {
-                StreamType::NotFound
-            } else {
-                StreamType::from(response)
-            }
-        })
-        .unwrap_or(StreamType::Error)
+        .ok()
+}
+
+/// Get stream type from a given RTSP DESCRIBE response for a given path.
+fn get_rtsp_response_stream_type(response: RtspResponse, path: &str) -> StreamType {
+    if is_hipcam_rtsp_response(&response) && path != "/11" && path != "/12" {
+        StreamType::NotFound
+    } else {
+        StreamType::from(response)
+    }
 }
 
 /// Check if a given RTSP response is from a buggy Hi(I)pcam RTSP server.
@@ -819,3 +947,20 @@ fn test_service_filtering() {
     assert_eq!(services.len(), 1);
     assert_eq!(services[0].1.port(), 554);
 }
+
+#[cfg(test)]
+#[test]
+fn test_sdp_codecs() {
+    let sdp = b"v=0\r\n\
+        o=- 0 0 IN IP4 127.0.0.1\r\n\
+        s=-\r\n\
+        t=0 0\r\n\
+        m=video 0 RTP/AVP 96\r\n\
+        a=rtpmap:96 H264/90000\r\n\
+        m=audio 0 RTP/AVP 0 8\r\n\
+        a=rtpmap:0 PCMU/8000\r\n\
+        a=rtpmap:8 pcma/8000\r\n";
+
+    assert_eq!(get_sdp_codecs(sdp), vec!["H264", "PCMU", "PCMA"]);
+    assert!(get_sdp_codecs(b"garbage").is_empty());
+}
@@ -15,6 +15,7 @@
 #[cfg(feature = "discovery")]
 pub mod discovery;
 
+pub mod probe;
 pub mod result;
 
 pub use self::result::{HostRecord, ScanResult, HR_FLAG_ARP, HR_FLAG_ICMP};
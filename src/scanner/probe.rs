@@ -0,0 +1,104 @@
+// Copyright 2020 Angelcam, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Probing of individual services.
+
+use std::time::Duration;
+
+#[cfg(feature = "discovery")]
+use crate::scanner::discovery;
+
+#[cfg(feature = "discovery")]
+use crate::utils::logger::Logger;
+
+use crate::svc_table::Service;
+use crate::utils::logger::BoxLogger;
+
+/// Stream type detected for a given service and path.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum StreamType {
+    Supported,
+    Locked,
+    Unsupported,
+    NotFound,
+    Error,
+}
+
+impl StreamType {
+    /// Get stream type code.
+    pub fn code(self) -> u8 {
+        match self {
+            Self::Supported => 1,
+            Self::Locked => 2,
+            Self::Unsupported => 3,
+            Self::NotFound => 4,
+            Self::Error => 5,
+        }
+    }
+
+    /// Get stream type for a given code.
+    pub fn from_code(code: u8) -> Option<Self> {
+        let res = match code {
+            1 => Self::Supported,
+            2 => Self::Locked,
+            3 => Self::Unsupported,
+            4 => Self::NotFound,
+            5 => Self::Error,
+            _ => return None,
+        };
+
+        Some(res)
+    }
+}
+
+/// Service probe result.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ProbeResult {
+    /// The service is reachable.
+    pub reachable: bool,
+    /// Detected stream type (RTSP and MJPEG services only).
+    pub stream_type: Option<StreamType>,
+    /// Status code of the last RTSP/HTTP response.
+    pub status_code: Option<u16>,
+    /// Codecs listed in the session description (RTSP services only).
+    pub codecs: Vec<String>,
+    /// Time it took to connect to the service (reachable services only).
+    pub latency: Option<Duration>,
+}
+
+/// Probe a given service using the same checks as the network discovery.
+/// `None` is returned if service probing is not supported (i.e. the client
+/// has been built without the discovery feature).
+#[cfg(feature = "discovery")]
+pub async fn probe_service(logger: BoxLogger, service: Service) -> Option<ProbeResult> {
+    let mut logger = logger;
+
+    let res = discovery::probe_service(logger.clone(), service)
+        .await
+        .unwrap_or_else(|err| {
+            log_warn!(logger, "service probe error: {}", err);
+
+            ProbeResult::default()
+        });
+
+    Some(res)
+}
+
+/// Probe a given service using the same checks as the network discovery.
+/// `None` is returned if service probing is not supported (i.e. the client
+/// has been built without the discovery feature).
+#[cfg(not(feature = "discovery"))]
+pub async fn probe_service(_: BoxLogger, _: Service) -> Option<ProbeResult> {
+    None
+}
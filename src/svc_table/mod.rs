@@ -35,6 +35,36 @@ pub use self::service::{
 
 const ACTIVE_THRESHOLD: i64 = 1200;
 
+/// Service table modification error.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ServiceTableError {
+    /// There is no such service.
+    NoSuchService,
+    /// The operation is allowed only for static services.
+    NotStatic,
+    /// A different service with the same identifier already exists.
+    Conflict,
+}
+
+impl ServiceTableError {
+    /// Get error description.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::NoSuchService => "no such service",
+            Self::NotStatic => "not a static service",
+            Self::Conflict => "service already exists",
+        }
+    }
+}
+
+impl Display for ServiceTableError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::error::Error for ServiceTableError {}
+
 /// Stable implementation of the Hasher trait.
 struct StableHasher {
     data: Vec<u8>,
@@ -114,6 +144,9 @@ struct ServiceTableElement {
     static_service: bool,
     /// Flag indicating static service visibility.
     enabled: bool,
+    /// Flag indicating a static service managed remotely (the enabled flag
+    /// of such services is persistent).
+    remote: bool,
     /// UNIX timestamp (in UTC) of the last discovery event.
     last_seen: i64,
     /// Active flag.
@@ -133,6 +166,7 @@ impl ServiceTableElement {
             service: svc,
             static_service: static_svc,
             enabled,
+            remote: false,
             last_seen: get_utc_timestamp(),
             active: true,
         }
@@ -176,16 +210,24 @@ impl ToJson for ServiceTableElement {
         let address = self.service.address().unwrap_or(default_address);
         let path = self.service.path().unwrap_or("");
 
-        object! {
+        let mut res = object! {
             "id" => self.id,
             "svc_type" => svc_type.code(),
             "mac" => format!("{}", mac),
             "address" => format!("{}", address),
             "path" => path,
             "static_svc" => self.static_service,
+            "remote_svc" => self.remote,
+            "enabled" => self.enabled,
             "last_seen" => self.last_seen,
             "active" => self.active
+        };
+
+        if let Some(limit) = self.service.rate_limit() {
+            res["rate_limit"] = limit.into();
         }
+
+        res
     }
 }
 
@@ -261,14 +303,28 @@ impl FromJson for ServiceTableElement {
             .get("active")
             .and_then(|v| v.as_bool())
             .unwrap_or(true);
+        let remote = service
+            .get("remote_svc")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        // static services are disabled unless they are configured again,
+        // only remotely managed services keep their enabled flag
+        let enabled = remote
+            && service
+                .get("enabled")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+        let rate_limit = service.get("rate_limit").and_then(|v| v.as_u32());
 
         let elem = Self {
             id,
-            service: svc?,
+            service: svc?.with_rate_limit(rate_limit),
             static_service: static_svc,
             last_seen,
             active,
-            enabled: false,
+            enabled,
+            remote,
         };
 
         Ok(elem)
@@ -388,11 +444,15 @@ impl ServiceTableData {
     fn update_element(&mut self, id: u16, svc: Service, enabled: bool) -> u16 {
         let elem = self.service_map.get_mut(&id).expect("broken service table");
 
-        let svc_change = elem.service != svc;
+        let old_svc = elem.to_service();
         let old_visible = elem.is_visible();
 
         elem.update(svc, enabled);
 
+        // bandwidth limits are not reported to the Arrow Service, so a
+        // change of the limit alone does not change the table version
+        let svc_change = !elem.service.eq_ignore_rate_limit(&old_svc);
+
         let new_visible = elem.is_visible();
 
         if old_visible != new_visible || (new_visible && svc_change) {
@@ -415,6 +475,107 @@ impl ServiceTableData {
         }
     }
 
+    /// Add a given remotely managed static service (or update an existing
+    /// one with the same identifier) and return its ID.
+    fn add_remote(&mut self, svc: Service) -> u16 {
+        let id = self.update(svc, true, true);
+
+        let elem = self.service_map.get_mut(&id).expect("broken service table");
+
+        let old_visible = elem.is_visible();
+
+        // a discovered service becomes static
+        elem.static_service = true;
+        elem.remote = true;
+
+        if old_visible != elem.is_visible() {
+            self.version += 1;
+        }
+
+        id
+    }
+
+    /// Replace service with a given ID. The service becomes a remotely
+    /// managed static service.
+    fn update_remote(&mut self, id: u16, svc: Service) -> Result<(), ServiceTableError> {
+        let key = svc.to_service_identifier();
+
+        let elem = self
+            .service_map
+            .get_mut(&id)
+            .filter(|elem| !elem.service.is_control())
+            .ok_or(ServiceTableError::NoSuchService)?;
+
+        if let Some(other) = self.identifier_map.get(&key) {
+            if *other != id {
+                return Err(ServiceTableError::Conflict);
+            }
+        }
+
+        let old_key = elem.service.to_service_identifier();
+
+        let enabled = !elem.static_service || elem.enabled;
+
+        elem.update(svc, enabled);
+        elem.static_service = true;
+        elem.remote = true;
+
+        self.identifier_map.remove(&old_key);
+        self.identifier_map.insert(key, id);
+
+        self.version += 1;
+
+        Ok(())
+    }
+
+    /// Get a mutable reference to a static service table element.
+    fn get_static_mut(&mut self, id: u16) -> Result<&mut ServiceTableElement, ServiceTableError> {
+        let elem = self
+            .service_map
+            .get_mut(&id)
+            .filter(|elem| !elem.service.is_control())
+            .ok_or(ServiceTableError::NoSuchService)?;
+
+        if elem.static_service {
+            Ok(elem)
+        } else {
+            Err(ServiceTableError::NotStatic)
+        }
+    }
+
+    /// Enable or disable a given static service. The service becomes
+    /// remotely managed.
+    fn set_enabled(&mut self, id: u16, enabled: bool) -> Result<(), ServiceTableError> {
+        let elem = self.get_static_mut(id)?;
+
+        let old_visible = elem.is_visible();
+
+        elem.enabled = enabled;
+        elem.remote = true;
+
+        if old_visible != elem.is_visible() {
+            self.version += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Remove a given static service.
+    fn remove(&mut self, id: u16) -> Result<(), ServiceTableError> {
+        let visible = self.get_static_mut(id)?.is_visible();
+
+        if let Some(elem) = self.service_map.remove(&id) {
+            self.identifier_map
+                .remove(&elem.service.to_service_identifier());
+        }
+
+        if visible {
+            self.version += 1;
+        }
+
+        Ok(())
+    }
+
     /// Update active flags of all services.
     fn update_active_services(&mut self) {
         let timestamp = get_utc_timestamp();
@@ -554,6 +715,29 @@ impl SharedServiceTable {
         self.data.lock().unwrap().update(svc, true, true)
     }
 
+    /// Add a given remotely managed static service into the table and
+    /// return its ID. The enabled flag of remotely managed services is
+    /// persistent.
+    pub fn add_remote(&mut self, svc: Service) -> u16 {
+        self.data.lock().unwrap().add_remote(svc)
+    }
+
+    /// Replace service with a given ID. The service becomes a remotely
+    /// managed static service.
+    pub fn update_remote(&mut self, id: u16, svc: Service) -> Result<(), ServiceTableError> {
+        self.data.lock().unwrap().update_remote(id, svc)
+    }
+
+    /// Enable or disable a given static service.
+    pub fn set_enabled(&mut self, id: u16, enabled: bool) -> Result<(), ServiceTableError> {
+        self.data.lock().unwrap().set_enabled(id, enabled)
+    }
+
+    /// Remove a given static service from the table.
+    pub fn remove(&mut self, id: u16) -> Result<(), ServiceTableError> {
+        self.data.lock().unwrap().remove(id)
+    }
+
     /// Update active flags of all services.
     pub fn update_active_services(&mut self) {
         self.data.lock().unwrap().update_active_services()
@@ -873,3 +1057,117 @@ fn test_deserialization_and_initialization() {
 
     assert_eq!(internal.version(), 5);
 }
+
+#[cfg(test)]
+#[test]
+fn test_rate_limit() {
+    let mut table = ServiceTableData::new();
+
+    let mac = MacAddr::zero();
+    let ip = Ipv4Addr::new(0, 0, 0, 0);
+    let addr = SocketAddr::V4(SocketAddrV4::new(ip, 0));
+
+    let svc = Service::rtsp(mac, addr, "/1".to_string());
+
+    let id = table.update(svc.clone().with_rate_limit(Some(1000)), true, true);
+
+    let version = table.version();
+
+    // the new limit is always used, however, a change of the limit alone
+    // must not change the table version
+    table.update(svc.clone(), true, true);
+
+    assert_eq!(table.version(), version);
+    assert_eq!(table.get(id).unwrap().rate_limit(), None);
+
+    let update = table.update(svc.clone().with_rate_limit(Some(1000)), true, true);
+
+    assert_eq!(update, id);
+    assert_eq!(table.version(), version);
+    assert_eq!(table.get(id).unwrap().rate_limit(), Some(1000));
+
+    // the same goes for rediscovered services
+    let discovered = Service::rtsp(mac, addr, "/2".to_string());
+
+    let id_2 = table.update(discovered.clone(), false, true);
+
+    let version = table.version();
+
+    table.update(discovered.clone().with_rate_limit(Some(2000)), false, true);
+
+    assert_eq!(table.version(), version);
+    assert_eq!(table.get(id_2).unwrap().rate_limit(), Some(2000));
+
+    let table = ServiceTableData::from_json(table.to_json()).unwrap();
+
+    // static services are not enabled until they are added again
+    let elem = &table.service_map[&id];
+
+    assert_eq!(elem.service.rate_limit(), Some(1000));
+}
+
+#[cfg(test)]
+#[test]
+fn test_remote_services() {
+    let mut table = ServiceTableData::new();
+
+    let mac = MacAddr::zero();
+    let ip = Ipv4Addr::new(10, 0, 0, 1);
+    let addr = SocketAddr::V4(SocketAddrV4::new(ip, 554));
+
+    let svc_1 = Service::rtsp(mac, addr, "/1".to_string());
+    let svc_2 = Service::rtsp(mac, addr, "/2".to_string());
+    let svc_3 = Service::http(mac, addr);
+
+    let id_1 = table.add_remote(svc_1);
+    let id_3 = table.update(svc_3.clone(), false, true);
+
+    assert_eq!(table.version(), 2);
+
+    // fix the path of the first service
+    table.update_remote(id_1, svc_2.clone()).unwrap();
+
+    assert_eq!(table.get(id_1), Some(svc_2.clone()));
+    assert_eq!(table.get_id(&svc_2.to_service_identifier()), Some(id_1));
+    assert_eq!(table.version(), 3);
+
+    assert_eq!(
+        table.update_remote(id_1, svc_3.clone()),
+        Err(ServiceTableError::Conflict)
+    );
+    assert_eq!(
+        table.update_remote(1, svc_2),
+        Err(ServiceTableError::NoSuchService)
+    );
+
+    // discovered services cannot be disabled or removed
+    assert_eq!(
+        table.set_enabled(id_3, false),
+        Err(ServiceTableError::NotStatic)
+    );
+    assert_eq!(table.remove(id_3), Err(ServiceTableError::NotStatic));
+
+    table.set_enabled(id_1, false).unwrap();
+
+    assert_eq!(table.get(id_1), None);
+    assert_eq!(table.version(), 4);
+
+    // remotely managed services keep their enabled flag
+    let restored = ServiceTableData::from_json(table.to_json()).unwrap();
+
+    assert!(restored.service_map[&id_1].remote);
+    assert_eq!(restored.get(id_1), None);
+
+    table.set_enabled(id_1, true).unwrap();
+
+    let restored = ServiceTableData::from_json(table.to_json()).unwrap();
+
+    assert!(restored.get(id_1).is_some());
+
+    table.remove(id_1).unwrap();
+
+    assert_eq!(table.get(id_1), None);
+    assert_eq!(table.remove(id_1), Err(ServiceTableError::NoSuchService));
+    assert_eq!(table.service_map.len(), 2);
+    assert_eq!(table.identifier_map.len(), 2);
+}
@@ -64,6 +64,24 @@ impl ServiceType {
             Self::TCP => SVC_TYPE_TCP,
         }
     }
+
+    /// Get service type for a given code.
+    pub fn from_code(code: u16) -> Option<Self> {
+        let res = match code {
+            SVC_TYPE_CONTROL_PROTOCOL => Self::ControlProtocol,
+            SVC_TYPE_RTSP => Self::RTSP,
+            SVC_TYPE_LOCKED_RTSP => Self::LockedRTSP,
+            SVC_TYPE_UNKNOWN_RTSP => Self::UnknownRTSP,
+            SVC_TYPE_UNSUPPORTED_RTSP => Self::UnsupportedRTSP,
+            SVC_TYPE_HTTP => Self::HTTP,
+            SVC_TYPE_MJPEG => Self::MJPEG,
+            SVC_TYPE_LOCKED_MJPEG => Self::LockedMJPEG,
+            SVC_TYPE_TCP => Self::TCP,
+            _ => return None,
+        };
+
+        Some(res)
+    }
 }
 
 /// Arrow service identifier.
@@ -89,6 +107,7 @@ pub struct Service {
     mac: Option<MacAddr>,
     address: Option<SocketAddr>,
     path: Option<String>,
+    rate_limit: Option<u32>,
 }
 
 impl Service {
@@ -100,6 +119,7 @@ impl Service {
             mac: None,
             address: None,
             path: None,
+            rate_limit: None,
         }
     }
 
@@ -110,6 +130,7 @@ impl Service {
             mac: Some(mac),
             address: Some(address),
             path: Some(path),
+            rate_limit: None,
         }
     }
 
@@ -120,6 +141,7 @@ impl Service {
             mac: Some(mac),
             address: Some(address),
             path,
+            rate_limit: None,
         }
     }
 
@@ -130,6 +152,7 @@ impl Service {
             mac: Some(mac),
             address: Some(address),
             path: None,
+            rate_limit: None,
         }
     }
 
@@ -140,6 +163,7 @@ impl Service {
             mac: Some(mac),
             address: Some(address),
             path: Some(path),
+            rate_limit: None,
         }
     }
 
@@ -150,6 +174,7 @@ impl Service {
             mac: Some(mac),
             address: Some(address),
             path: None,
+            rate_limit: None,
         }
     }
 
@@ -160,6 +185,7 @@ impl Service {
             mac: Some(mac),
             address: Some(address),
             path: Some(path),
+            rate_limit: None,
         }
     }
 
@@ -170,6 +196,7 @@ impl Service {
             mac: Some(mac),
             address: Some(address),
             path,
+            rate_limit: None,
         }
     }
 
@@ -180,9 +207,17 @@ impl Service {
             mac: Some(mac),
             address: Some(address),
             path: None,
+            rate_limit: None,
         }
     }
 
+    /// Set bandwidth limit (in bytes per second) for data sent from the
+    /// service to the Arrow Service (`None` means no limit).
+    pub fn with_rate_limit(mut self, limit: Option<u32>) -> Self {
+        self.rate_limit = limit;
+        self
+    }
+
     /// Check if this is the Control Protocol service.
     pub fn is_control(&self) -> bool {
         self.svc_type == ServiceType::ControlProtocol
@@ -218,6 +253,21 @@ impl Service {
         self.path.as_ref().map(|v| v as &str)
     }
 
+    /// Get service bandwidth limit (in bytes per second).
+    pub fn rate_limit(&self) -> Option<u32> {
+        self.rate_limit
+    }
+
+    /// Check if a given service is equal to this one except for the
+    /// bandwidth limit.
+    #[doc(hidden)]
+    pub fn eq_ignore_rate_limit(&self, other: &Service) -> bool {
+        self.svc_type == other.svc_type
+            && self.mac == other.mac
+            && self.address == other.address
+            && self.path == other.path
+    }
+
     /// Convert service to service identifier.
     #[doc(hidden)]
     pub fn to_service_identifier(&self) -> ServiceIdentifier {
@@ -14,79 +14,27 @@
 
 //! file logger definitions.
 
-use std::fs;
 use std::io;
 
 use std::fmt::Arguments;
-use std::fs::{File, OpenOptions};
-use std::io::Write;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 
 use crate::utils::logger::{Logger, Severity};
+use crate::utils::rotating_file::RotatingFile;
 
 /// Internal logger implementation.
 struct InternalFileLogger {
     level: Severity,
-    path: PathBuf,
-    file: File,
-    written: usize,
-    limit: usize,
-    rotations: usize,
+    file: RotatingFile,
 }
 
 impl InternalFileLogger {
     /// Write a given line into the underlaying file and rotate as necessary.
     fn write_line(&mut self, line: &str) -> io::Result<()> {
-        self.write(line.as_bytes())
-    }
-
-    /// Write given data into the underlaying file and rotate as necessary.
-    fn write(&mut self, data: &[u8]) -> io::Result<()> {
-        if (self.written + data.len()) > self.limit {
-            self.rotate()?;
-        }
-
-        self.file.write_all(data)?;
-
-        self.written += data.len();
-
+        self.file.write(line.as_bytes())?;
         self.file.flush()
     }
-
-    /// Rotate the log files.
-    fn rotate(&mut self) -> io::Result<()> {
-        for i in 0..self.rotations - 1 {
-            let mut from = self.path.as_os_str().to_os_string();
-            let mut to = self.path.as_os_str().to_os_string();
-
-            from.push(format!(".{}", self.rotations - i - 1));
-            to.push(format!(".{}", self.rotations - i));
-
-            let from = PathBuf::from(from);
-            let to = PathBuf::from(to);
-
-            if from.exists() {
-                fs::rename(&from, &to)?;
-            }
-        }
-
-        if self.rotations > 0 {
-            let mut to = self.path.as_os_str().to_os_string();
-
-            to.push(".1");
-
-            let to = PathBuf::from(to);
-
-            fs::rename(&self.path, &to)?;
-        }
-
-        self.file = File::create(&self.path)?;
-
-        self.written = 0;
-
-        Ok(())
-    }
 }
 
 impl Logger for InternalFileLogger {
@@ -131,26 +79,9 @@ impl FileLogger {
     where
         PathBuf: From<P>,
     {
-        let path = PathBuf::from(path);
-
-        let written = match path.metadata() {
-            Ok(metadata) => metadata.len(),
-            Err(_) => 0,
-        };
-
-        let file = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .append(true)
-            .open(&path)?;
-
         let logger = InternalFileLogger {
             level: Severity::INFO,
-            path,
-            file,
-            written: written as usize,
-            limit,
-            rotations,
+            file: RotatingFile::open(path, limit, rotations)?,
         };
 
         let logger = Self {
@@ -39,6 +39,7 @@ macro_rules! log_warn {
 }
 
 pub mod file;
+pub mod ring;
 pub mod stderr;
 
 #[cfg(not(target_os = "windows"))]
@@ -0,0 +1,236 @@
+// Copyright 2020 Angelcam, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! In-memory ring-buffer logger definitions.
+
+use std::collections::VecDeque;
+use std::fmt::Arguments;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use crate::utils::logger::{BoxLogger, Logger, Severity};
+
+/// A single log entry.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LogEntry {
+    timestamp: SystemTime,
+    severity: Severity,
+    file: String,
+    line: u32,
+    message: String,
+}
+
+impl LogEntry {
+    /// Create a new log entry.
+    pub fn new<F, M>(
+        timestamp: SystemTime,
+        severity: Severity,
+        file: F,
+        line: u32,
+        message: M,
+    ) -> Self
+    where
+        F: ToString,
+        M: ToString,
+    {
+        Self {
+            timestamp,
+            severity,
+            file: file.to_string(),
+            line,
+            message: message.to_string(),
+        }
+    }
+
+    /// Get time when the entry was created.
+    pub fn timestamp(&self) -> SystemTime {
+        self.timestamp
+    }
+
+    /// Get message severity.
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    /// Get name of the source file where the message was logged.
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    /// Get line number where the message was logged.
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    /// Get the log message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+/// Internal log buffer.
+struct InternalLogBuffer {
+    entries: VecDeque<LogEntry>,
+    capacity: usize,
+}
+
+/// Shared buffer holding a limited number of the most recent log entries.
+#[derive(Clone)]
+pub struct LogBuffer {
+    inner: Arc<Mutex<InternalLogBuffer>>,
+}
+
+impl LogBuffer {
+    /// Create a new log buffer holding at most a given number of entries.
+    pub fn new(capacity: usize) -> Self {
+        let inner = InternalLogBuffer {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        };
+
+        Self {
+            inner: Arc::new(Mutex::new(inner)),
+        }
+    }
+
+    /// Insert a given entry into the buffer. The oldest entry will be
+    /// dropped if the buffer is full.
+    pub fn push(&self, entry: LogEntry) {
+        let mut inner = self.inner.lock().unwrap();
+
+        if inner.capacity == 0 {
+            return;
+        }
+
+        while inner.entries.len() >= inner.capacity {
+            inner.entries.pop_front();
+        }
+
+        inner.entries.push_back(entry);
+    }
+
+    /// Get the number of entries in the buffer.
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().entries.len()
+    }
+
+    /// Check if the buffer is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get at most a given number of the most recent entries with a given
+    /// minimum severity. The entries are ordered from the oldest one.
+    pub fn last(&self, count: usize, min_severity: Severity) -> Vec<LogEntry> {
+        let inner = self.inner.lock().unwrap();
+
+        let mut res = inner
+            .entries
+            .iter()
+            .rev()
+            .filter(|entry| entry.severity >= min_severity)
+            .take(count)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        res.reverse();
+
+        res
+    }
+}
+
+/// Logger keeping a copy of the most recent log entries in memory. All
+/// messages are passed to the underlaying logger as well.
+#[derive(Clone)]
+pub struct RingBufferLogger {
+    logger: BoxLogger,
+    buffer: LogBuffer,
+}
+
+impl RingBufferLogger {
+    /// Create a new ring-buffer logger wrapping a given logger and storing
+    /// the log entries into a given buffer.
+    pub fn new(logger: BoxLogger, buffer: LogBuffer) -> Self {
+        Self { logger, buffer }
+    }
+}
+
+impl Logger for RingBufferLogger {
+    fn log(&mut self, file: &str, line: u32, s: Severity, msg: Arguments) {
+        if s >= self.logger.get_level() {
+            let entry = LogEntry::new(SystemTime::now(), s, file, line, msg);
+
+            self.buffer.push(entry);
+        }
+
+        self.logger.log(file, line, s, msg)
+    }
+
+    fn set_level(&mut self, s: Severity) {
+        self.logger.set_level(s);
+    }
+
+    fn get_level(&self) -> Severity {
+        self.logger.get_level()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::utils::logger::DummyLogger;
+
+    #[test]
+    fn test_ring_buffer_logger() {
+        let buffer = LogBuffer::new(3);
+
+        let mut inner = BoxLogger::new(DummyLogger::default());
+
+        inner.set_level(Severity::INFO);
+
+        let mut logger = RingBufferLogger::new(inner, buffer.clone());
+
+        log_debug!(logger, "dropped");
+        log_info!(logger, "msg {}", 1);
+        log_warn!(logger, "msg {}", 2);
+        log_info!(logger, "msg {}", 3);
+        log_warn!(logger, "msg {}", 4);
+
+        let messages = |entries: Vec<LogEntry>| {
+            entries
+                .iter()
+                .map(|entry| entry.message().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            messages(buffer.last(10, Severity::DEBUG)),
+            vec!["msg 2", "msg 3", "msg 4"]
+        );
+        assert_eq!(
+            messages(buffer.last(2, Severity::DEBUG)),
+            vec!["msg 3", "msg 4"]
+        );
+        assert_eq!(
+            messages(buffer.last(10, Severity::WARN)),
+            vec!["msg 2", "msg 4"]
+        );
+
+        let entry = buffer.last(1, Severity::DEBUG).pop().unwrap();
+
+        assert_eq!(entry.severity(), Severity::WARN);
+        assert_eq!(entry.file(), file!());
+    }
+}
@@ -19,6 +19,7 @@ pub mod json;
 #[macro_use]
 pub mod logger;
 
+pub mod rotating_file;
 pub mod string;
 
 use std::fmt;
@@ -110,6 +111,32 @@ pub unsafe fn cstr_to_string(ptr: *const i8) -> String {
     slice.to_string()
 }
 
+/// Get resident memory size of the current process in bytes (`None` means
+/// that the value is not available on this platform).
+#[cfg(target_os = "linux")]
+pub fn memory_usage() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+
+    let kb = line
+        .trim_start_matches("VmRSS:")
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+
+    Some(kb << 10)
+}
+
+/// Get resident memory size of the current process in bytes (`None` means
+/// that the value is not available on this platform).
+#[cfg(not(target_os = "linux"))]
+pub fn memory_usage() -> Option<u64> {
+    None
+}
+
 /// Unwrap a given result or log an error with a given severity and return None.
 pub fn result_or_log<L, T, E, M>(
     logger: &mut L,
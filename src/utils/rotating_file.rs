@@ -0,0 +1,116 @@
+// Copyright 2020 Angelcam, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Size-limited files with automatic rotation.
+
+use std::fs;
+use std::io;
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+/// File that gets rotated once it reaches a given size limit. Backup files
+/// are named using the original path with a numeric suffix (e.g. "file.1",
+/// "file.2", ...). Written data is buffered and it gets flushed on rotation,
+/// on an explicit flush and when the file is dropped.
+pub struct RotatingFile {
+    path: PathBuf,
+    file: BufWriter<File>,
+    written: usize,
+    limit: usize,
+    rotations: usize,
+}
+
+impl RotatingFile {
+    /// Open a given file in the append mode with a given size limit and a
+    /// given number of backup files (rotations).
+    pub fn open<P>(path: P, limit: usize, rotations: usize) -> io::Result<Self>
+    where
+        PathBuf: From<P>,
+    {
+        let path = PathBuf::from(path);
+
+        let written = match path.metadata() {
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        };
+
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+
+        let res = Self {
+            path,
+            file: BufWriter::new(file),
+            written: written as usize,
+            limit,
+            rotations,
+        };
+
+        Ok(res)
+    }
+
+    /// Write given data into the underlaying file and rotate as necessary.
+    pub fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        if (self.written + data.len()) > self.limit {
+            self.rotate()?;
+        }
+
+        self.file.write_all(data)?;
+
+        self.written += data.len();
+
+        Ok(())
+    }
+
+    /// Flush all buffered data into the underlaying file.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+
+    /// Rotate the files.
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        for i in 0..self.rotations.saturating_sub(1) {
+            let mut from = self.path.as_os_str().to_os_string();
+            let mut to = self.path.as_os_str().to_os_string();
+
+            from.push(format!(".{}", self.rotations - i - 1));
+            to.push(format!(".{}", self.rotations - i));
+
+            let from = PathBuf::from(from);
+            let to = PathBuf::from(to);
+
+            if from.exists() {
+                fs::rename(&from, &to)?;
+            }
+        }
+
+        if self.rotations > 0 {
+            let mut to = self.path.as_os_str().to_os_string();
+
+            to.push(".1");
+
+            let to = PathBuf::from(to);
+
+            fs::rename(&self.path, &to)?;
+        }
+
+        self.file = BufWriter::new(File::create(&self.path)?);
+
+        self.written = 0;
+
+        Ok(())
+    }
+}
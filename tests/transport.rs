@@ -0,0 +1,112 @@
+// Copyright 2020 Angelcam, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io;
+
+use std::sync::{Arc, Mutex};
+
+use futures::future::FutureExt;
+
+use tokio::io::{AsyncReadExt, DuplexStream};
+
+use arrow_client::config::{Config, MacAddr, PersistentConfig};
+use arrow_client::openssl::ssl::SslConnectorBuilder;
+use arrow_client::runtime;
+use arrow_client::storage::Storage;
+use arrow_client::{ArrowClient, ArrowStream, ArrowTransport, ArrowTransportFuture};
+
+/// Storage that keeps everything in memory.
+struct MemoryStorage;
+
+impl Storage for MemoryStorage {
+    fn save_configuration(&mut self, _: &PersistentConfig) -> Result<(), io::Error> {
+        Ok(())
+    }
+
+    fn load_configuration(&mut self) -> Result<PersistentConfig, io::Error> {
+        Ok(PersistentConfig::new())
+    }
+
+    fn load_ca_certificates(&mut self, _: &mut SslConnectorBuilder) -> Result<(), io::Error> {
+        Ok(())
+    }
+}
+
+/// Transport that hands out a given in-memory pipe.
+struct PipeTransport {
+    stream: Mutex<Option<DuplexStream>>,
+    addresses: Arc<Mutex<Vec<String>>>,
+}
+
+impl ArrowTransport for PipeTransport {
+    fn connect(&self, addr: &str) -> ArrowTransportFuture {
+        self.addresses.lock().unwrap().push(addr.to_string());
+
+        let stream = self.stream.lock().unwrap().take();
+
+        let res = stream
+            .map(|stream| Box::new(stream) as Box<dyn ArrowStream>)
+            .ok_or_else(|| io::Error::from(io::ErrorKind::ConnectionRefused));
+
+        futures::future::ready(res).boxed()
+    }
+}
+
+#[test]
+fn test_custom_transport() {
+    let (client_end, mut service_end) = tokio::io::duplex(65536);
+
+    let addresses = Arc::new(Mutex::new(Vec::new()));
+
+    let transport = PipeTransport {
+        stream: Mutex::new(Some(client_end)),
+        addresses: addresses.clone(),
+    };
+
+    let mut builder = Config::builder();
+
+    builder.mac_address(Some(MacAddr::new(0, 1, 2, 3, 4, 5)));
+
+    let config = builder.build(MemoryStorage, "pipe:1").unwrap();
+
+    runtime::run(async move {
+        let (client, task) = ArrowClient::with_transport(config, transport);
+
+        tokio::spawn(task);
+
+        // Arrow Message header: version, service ID, session ID, payload size
+        let mut header = [0u8; 11];
+
+        service_end.read_exact(&mut header).await.unwrap();
+
+        let service = u16::from_be_bytes([header[1], header[2]]);
+        let size = u32::from_be_bytes([header[7], header[8], header[9], header[10]]);
+
+        assert_eq!(header[0], 1);
+        assert_eq!(service, 0);
+
+        let mut payload = vec![0u8; size as usize];
+
+        service_end.read_exact(&mut payload).await.unwrap();
+
+        // control message header: message ID, message type (REGISTER)
+        let msg_type = u16::from_be_bytes([payload[2], payload[3]]);
+
+        assert_eq!(msg_type, 2);
+
+        std::mem::drop(client);
+    });
+
+    assert_eq!(*addresses.lock().unwrap(), vec![String::from("pipe:1")]);
+}